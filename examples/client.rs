@@ -1,8 +1,5 @@
 use anyhow::Result;
-use async_prost::AsyncProstStream;
-use futures::{SinkExt, StreamExt};
-use kv_server::{CommandRequest, CommandResponse, Kvpair};
-use tokio::net::TcpStream;
+use kv_server::{CommandRequest, KvClient, Kvpair};
 use tracing::info;
 
 #[tokio::main]
@@ -11,24 +8,15 @@ async fn main() -> Result<()> {
 
     let addr = "127.0.0.1:9527";
 
-    let stream = TcpStream::connect(addr).await?;
-
-    let mut client =
-        AsyncProstStream::<_, CommandResponse, CommandRequest, _>::from(stream).for_async();
+    let client = KvClient::connect(addr).await?;
 
     let cmd = CommandRequest::new_hset("t1", "k1", "v1".into());
-    client.send(cmd).await?;
-
-    if let Some(Ok(data)) = client.next().await {
-        info!("Got Response {:?}", data);
-    }
+    let data = client.execute(cmd).await?;
+    info!("Got Response {:?}", data);
 
     let cmd = CommandRequest::new_hget("t1", "k1");
-    client.send(cmd).await?;
-
-    if let Some(Ok(data)) = client.next().await {
-        info!("Got Response {:?}", data);
-    }
+    let data = client.execute(cmd).await?;
+    info!("Got Response {:?}", data);
 
     let pairs = vec![
         Kvpair::new("k1", 1.into()),
@@ -37,11 +25,8 @@ async fn main() -> Result<()> {
     ];
 
     let cmd = CommandRequest::new_hmset("t1", pairs);
-    client.send(cmd).await?;
-
-    if let Some(Ok(data)) = client.next().await {
-        info!("Got Response {:?}", data);
-    }
+    let data = client.execute(cmd).await?;
+    info!("Got Response {:?}", data);
 
     Ok(())
 }
@@ -1,8 +1,8 @@
 use anyhow::Result;
-use async_prost::AsyncProstStream;
 use futures::{SinkExt, StreamExt};
-use kv_server::{CommandRequest, CommandResponse};
+use kv_server::{CommandResponse, FrameCodec, ServerFrames};
 use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
 use tracing::info;
 
 #[tokio::main]
@@ -19,8 +19,7 @@ async fn main() -> Result<()> {
         info!("client: {:?} connected", addr);
 
         tokio::spawn(async move {
-            let mut stream =
-                AsyncProstStream::<_, CommandRequest, CommandResponse, _>::from(stream).for_async();
+            let mut stream: ServerFrames<_> = Framed::new(stream, FrameCodec::default());
 
             while let Some(Ok(msg)) = stream.next().await {
                 info!("Got a new command: {:?}", msg);
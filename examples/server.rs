@@ -1,7 +1,5 @@
 use anyhow::Result;
-use async_prost::AsyncProstStream;
-use futures::{SinkExt, StreamExt};
-use kv_server::{CommandRequest, CommandResponse, MemTable, Service, ServiceInner};
+use kv_server::{FrameConfig, MemTable, ProstServerStream, Service, ServiceInner};
 use tokio::net::TcpListener;
 use tracing::info;
 
@@ -20,16 +18,9 @@ async fn main() -> Result<()> {
         let (stream, addr) = listener.accept().await?;
         info!("client: {:?} connected", addr);
 
-        let handler = service.clone();
-
+        let stream = ProstServerStream::new(stream, service.clone(), FrameConfig::default());
         tokio::spawn(async move {
-            let mut stream =
-                AsyncProstStream::<_, CommandRequest, CommandResponse, _>::from(stream).for_async();
-
-            while let Some(Ok(msg)) = stream.next().await {
-                let resp = handler.execute(msg);
-                stream.send(resp).await.unwrap();
-            }
+            stream.process().await.unwrap();
             info!("client {:?} disconnected", addr);
         });
     }
@@ -0,0 +1,714 @@
+use anyhow::{bail, Result};
+use clap::{ArgEnum, Parser, Subcommand};
+use kv_server::{
+    value, ChangeEvent, CommandRequest, CommandResponse, KvClient, Kvpair, Publication,
+    TableFormat, TableReader, TableWriter, TlsClientConnector, Value, YamuxCtrl,
+};
+use std::fs::{self, File};
+use std::io::{BufReader as StdBufReader, BufWriter};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+const VALUE_HELP: &str = "Values are plain strings unless prefixed with `int:`, `float:`, \
+                          `bool:`, `bin:` (hex encoded) or `str:`.";
+
+#[derive(Debug, Parser)]
+#[clap(name = "kv-cli", version, about = "Command line client for kv-server")]
+#[clap(after_help = VALUE_HELP)]
+struct Args {
+    /// Address of the kv-server
+    #[clap(short, long, default_value = "127.0.0.1:9527")]
+    addr: String,
+    /// How responses are printed
+    #[clap(short, long, arg_enum, default_value = "table")]
+    output: Output,
+    /// Connect over TLS
+    #[clap(long)]
+    tls: bool,
+    /// PEM file of the CA that signed the server cert; defaults to the web roots
+    #[clap(long, requires = "tls")]
+    ca: Option<String>,
+    /// Server name to verify; defaults to the host part of --addr
+    #[clap(long, requires = "tls")]
+    domain: Option<String>,
+    /// PEM file of the client cert for mutual TLS
+    #[clap(long, requires_all = &["tls", "key"])]
+    cert: Option<String>,
+    /// PEM file of the client key for mutual TLS
+    #[clap(long, requires_all = &["tls", "cert"])]
+    key: Option<String>,
+    /// Talk to a server expecting yamux multiplexed connections
+    #[clap(long)]
+    multiplex: bool,
+    /// Command to run once; starts an interactive shell when omitted
+    #[clap(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+/// A single line typed into the interactive shell.
+#[derive(Debug, Parser)]
+#[clap(name = "kv-cli", no_binary_name = true, after_help = VALUE_HELP)]
+struct Line {
+    #[clap(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Clone, Copy, Debug, ArgEnum)]
+enum Output {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+enum Cmd {
+    /// Get the value of a key
+    Hget { table: String, key: String },
+    /// Get all pairs in a table
+    Hgetall { table: String },
+    /// Get the values of several keys
+    Hmget {
+        table: String,
+        #[clap(required = true)]
+        keys: Vec<String>,
+    },
+    /// Set the value of a key
+    Hset {
+        table: String,
+        key: String,
+        #[clap(parse(try_from_str = parse_value), allow_hyphen_values = true)]
+        value: Value,
+        /// Expire the key after this many milliseconds
+        #[clap(long)]
+        ttl: Option<u64>,
+    },
+    /// Set several keys: hmset <table> <key> <value> [<key> <value>...]
+    Hmset {
+        table: String,
+        #[clap(required = true, allow_hyphen_values = true)]
+        pairs: Vec<String>,
+        /// Expire the keys after this many milliseconds
+        #[clap(long)]
+        ttl: Option<u64>,
+    },
+    /// Delete a key
+    Hdel { table: String, key: String },
+    /// Delete several keys
+    Hmdel {
+        table: String,
+        #[clap(required = true)]
+        keys: Vec<String>,
+    },
+    /// Check whether a key exists
+    Hexist { table: String, key: String },
+    /// Check whether several keys exist
+    Hmexist {
+        table: String,
+        #[clap(required = true)]
+        keys: Vec<String>,
+    },
+    /// Expire a key after the given milliseconds
+    Expire {
+        table: String,
+        key: String,
+        ttl_ms: u64,
+    },
+    /// Remaining time to live of a key in milliseconds
+    Ttl { table: String, key: String },
+    /// Remove the expiration of a key
+    Persist { table: String, key: String },
+    /// Add an integer to the value of a key
+    Hincrby {
+        table: String,
+        key: String,
+        #[clap(allow_hyphen_values = true)]
+        delta: i64,
+    },
+    /// Add a float to the value of a key
+    Hincrbyfloat {
+        table: String,
+        key: String,
+        #[clap(allow_hyphen_values = true)]
+        delta: f64,
+    },
+    /// Replace a value only if it still equals the expected one
+    Hcas {
+        table: String,
+        key: String,
+        /// Expected current value; the key must be absent when omitted
+        #[clap(long, parse(try_from_str = parse_value), allow_hyphen_values = true)]
+        expected: Option<Value>,
+        /// New value; the key is deleted when omitted
+        #[clap(long, parse(try_from_str = parse_value), allow_hyphen_values = true)]
+        value: Option<Value>,
+    },
+    /// Iterate over a table in key order
+    Hscan {
+        table: String,
+        /// Cursor returned by the previous scan
+        #[clap(long, default_value = "")]
+        cursor: String,
+        #[clap(long, default_value = "0")]
+        limit: u32,
+        #[clap(long, default_value = "")]
+        prefix: String,
+        /// Glob pattern supporting `*` and `?`
+        #[clap(long, default_value = "")]
+        pattern: String,
+    },
+    /// Get pairs with keys in [start, end); an empty bound is unbounded
+    Hrange {
+        table: String,
+        #[clap(default_value = "")]
+        start: String,
+        #[clap(default_value = "")]
+        end: String,
+        #[clap(long)]
+        reverse: bool,
+        #[clap(long, default_value = "0")]
+        limit: u32,
+    },
+    /// List all tables
+    ListTables,
+    /// Number of keys in a table
+    TableLen { table: String },
+    /// Delete a table with all its keys
+    DropTable { table: String },
+    /// Rename a table
+    RenameTable { from: String, to: String },
+    /// Dump all tables to a file under the backup directory of the server
+    Backup { path: String },
+    /// Load a backup from the backup directory of the server into a store without tables
+    Restore { path: String },
+    /// Write a table to a local JSON Lines or CSV file
+    Export {
+        table: String,
+        file: String,
+        /// jsonl or csv; guessed from the file extension when omitted
+        #[clap(long)]
+        format: Option<TableFormat>,
+    },
+    /// Load a local JSON Lines or CSV file into a table
+    Import {
+        table: String,
+        file: String,
+        /// jsonl or csv; guessed from the file extension when omitted
+        #[clap(long)]
+        format: Option<TableFormat>,
+        /// Number of pairs sent per hmset request
+        #[clap(long, default_value = "1000")]
+        batch_size: usize,
+    },
+    /// Publish a message to a channel
+    Publish {
+        channel: String,
+        #[clap(parse(try_from_str = parse_value), allow_hyphen_values = true)]
+        message: Value,
+    },
+    /// Subscribe to channels and print messages until the connection closes
+    Subscribe {
+        #[clap(required = true)]
+        channels: Vec<String>,
+        /// Treat the channels as glob patterns
+        #[clap(long)]
+        pattern: bool,
+    },
+    /// Print changes of a table until the connection closes
+    Watch {
+        table: String,
+        /// Only watch this key
+        #[clap(long, conflicts_with = "prefix")]
+        key: Option<String>,
+        /// Only watch keys starting with this prefix
+        #[clap(long)]
+        prefix: Option<String>,
+    },
+}
+
+impl TryFrom<Cmd> for CommandRequest {
+    type Error = anyhow::Error;
+
+    fn try_from(cmd: Cmd) -> Result<Self> {
+        let req = match cmd {
+            Cmd::Hget { table, key } => CommandRequest::new_hget(table, key),
+            Cmd::Hgetall { table } => CommandRequest::new_hgetall(table),
+            Cmd::Hmget { table, keys } => CommandRequest::new_hmget(table, keys),
+            Cmd::Hset {
+                table,
+                key,
+                value,
+                ttl,
+            } => CommandRequest::new_hset_with_ttl(table, key, value, ttl.unwrap_or_default()),
+            Cmd::Hmset { table, pairs, ttl } => {
+                let pairs = parse_pairs(pairs)?;
+                CommandRequest::new_hmset_with_ttl(table, pairs, ttl.unwrap_or_default())
+            }
+            Cmd::Hdel { table, key } => CommandRequest::new_hdel(table, key),
+            Cmd::Hmdel { table, keys } => CommandRequest::new_hmdel(table, keys),
+            Cmd::Hexist { table, key } => CommandRequest::new_hexist(table, key),
+            Cmd::Hmexist { table, keys } => CommandRequest::new_hmexist(table, keys),
+            Cmd::Expire { table, key, ttl_ms } => CommandRequest::new_expire(table, key, ttl_ms),
+            Cmd::Ttl { table, key } => CommandRequest::new_ttl(table, key),
+            Cmd::Persist { table, key } => CommandRequest::new_persist(table, key),
+            Cmd::Hincrby { table, key, delta } => CommandRequest::new_hincrby(table, key, delta),
+            Cmd::Hincrbyfloat { table, key, delta } => {
+                CommandRequest::new_hincrbyfloat(table, key, delta)
+            }
+            Cmd::Hcas {
+                table,
+                key,
+                expected,
+                value,
+            } => CommandRequest::new_hcas(table, key, expected, value),
+            Cmd::Hscan {
+                table,
+                cursor,
+                limit,
+                prefix,
+                pattern,
+            } => CommandRequest::new_hscan(table, cursor, limit, prefix, pattern),
+            Cmd::Hrange {
+                table,
+                start,
+                end,
+                reverse,
+                limit,
+            } => CommandRequest::new_hrange(table, start, end, reverse, limit),
+            Cmd::ListTables => CommandRequest::new_list_tables(),
+            Cmd::TableLen { table } => CommandRequest::new_table_len(table),
+            Cmd::DropTable { table } => CommandRequest::new_drop_table(table),
+            Cmd::RenameTable { from, to } => CommandRequest::new_rename_table(from, to),
+            Cmd::Backup { path } => CommandRequest::new_backup(path),
+            Cmd::Restore { path } => CommandRequest::new_restore(path),
+            Cmd::Export { .. } | Cmd::Import { .. } => {
+                bail!("export and import run on the client and can not be queued")
+            }
+            Cmd::Publish { channel, message } => CommandRequest::new_publish(channel, message),
+            Cmd::Subscribe { channels, pattern } => match pattern {
+                true => CommandRequest::new_psubscribe(channels),
+                false => CommandRequest::new_subscribe(channels),
+            },
+            Cmd::Watch { table, key, prefix } => CommandRequest::new_watch(
+                table,
+                key.unwrap_or_default(),
+                prefix.unwrap_or_default(),
+            ),
+        };
+        Ok(req)
+    }
+}
+
+fn parse_value(s: &str) -> Result<Value> {
+    let value = match s.split_once(':') {
+        Some(("int", v)) => v.parse::<i64>()?.into(),
+        Some(("float", v)) => v.parse::<f64>()?.into(),
+        Some(("bool", v)) => v.parse::<bool>()?.into(),
+        Some(("bin", v)) => Value {
+            value: Some(value::Value::Binary(hex::decode(v)?.into())),
+        },
+        Some(("str", v)) => v.into(),
+        _ => s.into(),
+    };
+    Ok(value)
+}
+
+fn parse_pairs(args: Vec<String>) -> Result<Vec<Kvpair>> {
+    if args.len() % 2 == 1 {
+        bail!("expect <key> <value> pairs, got {} arguments", args.len());
+    }
+    args.chunks(2)
+        .map(|kv| Ok(Kvpair::new(kv[0].as_str(), parse_value(&kv[1])?)))
+        .collect()
+}
+
+fn format_value(v: &Value) -> String {
+    match &v.value {
+        Some(value::Value::String(s)) => format!("{:?}", s),
+        Some(value::Value::Binary(b)) => format!("bin:{}", hex::encode(b)),
+        Some(value::Value::Integer(i)) => i.to_string(),
+        Some(value::Value::Float(f)) => format!("{:?}", f),
+        Some(value::Value::Bool(b)) => b.to_string(),
+        None => "(nil)".into(),
+    }
+}
+
+fn render(res: &CommandResponse, output: Output) -> String {
+    match output {
+        Output::Table => render_table(res),
+        Output::Json => serde_json::Value::from(res).to_string(),
+    }
+}
+
+fn render_publication(publication: &Publication, output: Output) -> String {
+    let data = publication.data.clone().unwrap_or_default();
+    match output {
+        Output::Table if publication.pattern.is_empty() => {
+            format!("{}: {}", publication.channel, format_value(&data))
+        }
+        Output::Table => format!(
+            "{} ({}): {}",
+            publication.channel,
+            publication.pattern,
+            format_value(&data)
+        ),
+        Output::Json => serde_json::json!({
+            "channel": publication.channel,
+            "pattern": publication.pattern,
+            "data": serde_json::Value::from(&data),
+        })
+        .to_string(),
+    }
+}
+
+fn render_change(change: &ChangeEvent, output: Output) -> String {
+    let old = change.old_value.as_ref();
+    let new = change.new_value.as_ref();
+    match output {
+        Output::Table => {
+            let old = old.map(format_value).unwrap_or_else(|| "(none)".into());
+            match new {
+                Some(new) => format!(
+                    "set {}/{}: {} -> {}",
+                    change.table,
+                    change.key,
+                    old,
+                    format_value(new)
+                ),
+                None => format!("del {}/{}: {}", change.table, change.key, old),
+            }
+        }
+        Output::Json => serde_json::json!({
+            "table": change.table,
+            "key": change.key,
+            "deleted": change.deleted,
+            "old_value": old.map(serde_json::Value::from),
+            "new_value": new.map(serde_json::Value::from),
+        })
+        .to_string(),
+    }
+}
+
+fn render_table(res: &CommandResponse) -> String {
+    let mut lines = vec![format!("status: {}", res.status)];
+    if !res.message.is_empty() {
+        lines.push(format!("message: {}", res.message));
+    }
+    for (i, v) in res.values.iter().enumerate() {
+        lines.push(format!("{}) {}", i + 1, format_value(v)));
+    }
+    if !res.pairs.is_empty() {
+        let width = res.pairs.iter().map(|p| p.key.len()).max().unwrap_or(0);
+        lines.push(format!("{:width$}  VALUE", "KEY", width = width.max(3)));
+        for p in &res.pairs {
+            let value = p.value.as_ref().map(format_value).unwrap_or_default();
+            lines.push(format!("{:width$}  {}", p.key, value, width = width.max(3)));
+        }
+    }
+    if !res.cursor.is_empty() {
+        lines.push(format!("cursor: {}", res.cursor));
+    }
+    for (i, res) in res.results.iter().enumerate() {
+        lines.push(format!("[{}] {}", i + 1, render_table(res)));
+    }
+    lines.join("\n")
+}
+
+impl Args {
+    fn connector(&self) -> Result<TlsClientConnector> {
+        let host = match self.addr.rsplit_once(':') {
+            Some((host, _)) => host,
+            None => &self.addr,
+        };
+        let domain = self.domain.as_deref().unwrap_or(host);
+        let ca = self.ca.as_ref().map(fs::read_to_string).transpose()?;
+        let identity = match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => Some((fs::read_to_string(cert)?, fs::read_to_string(key)?)),
+            _ => None,
+        };
+        let identity = identity.as_ref().map(|(c, k)| (c.as_str(), k.as_str()));
+        Ok(TlsClientConnector::new(domain, identity, ca.as_deref())?)
+    }
+
+    async fn start<S>(self, stream: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        match self.multiplex {
+            true => {
+                let mut ctrl = YamuxCtrl::new_client(stream);
+                let client = KvClient::new(ctrl.open_stream().await?);
+                run(client, self.cmd, self.output).await
+            }
+            false => run(KvClient::new(stream), self.cmd, self.output).await,
+        }
+    }
+}
+
+async fn run(client: KvClient, cmd: Option<Cmd>, output: Output) -> Result<()> {
+    match cmd {
+        Some(cmd) => {
+            let listens = listens(&cmd);
+            let res = call(&client, cmd).await?;
+            println!("{}", render(&res, output));
+            match listens && res.status == 200 {
+                true => listen(&client, output).await,
+                false => Ok(()),
+            }
+        }
+        None => repl(&client, output).await,
+    }
+}
+
+async fn call(client: &KvClient, cmd: Cmd) -> Result<CommandResponse> {
+    let count = match cmd {
+        Cmd::Export {
+            table,
+            file,
+            format,
+        } => export(client, &table, &file, format).await?,
+        Cmd::Import {
+            table,
+            file,
+            format,
+            batch_size,
+        } => import(client, &table, &file, format, batch_size).await?,
+        cmd => return Ok(client.execute(cmd.try_into()?).await?),
+    };
+    Ok(Value::from(count as i64).into())
+}
+
+/// Rows fetched per hscan while exporting.
+const EXPORT_PAGE: u32 = 1000;
+
+fn table_format(file: &str, format: Option<TableFormat>) -> Result<TableFormat> {
+    match format.or_else(|| TableFormat::from_path(file)) {
+        Some(format) => Ok(format),
+        None => bail!("cannot guess the format of {}, pass --format", file),
+    }
+}
+
+async fn export(
+    client: &KvClient,
+    table: &str,
+    file: &str,
+    format: Option<TableFormat>,
+) -> Result<usize> {
+    let format = table_format(file, format)?;
+    let mut writer = TableWriter::new(BufWriter::new(File::create(file)?), format)?;
+    let mut cursor = String::new();
+    loop {
+        let (pairs, next) = client.hscan(table, &cursor, EXPORT_PAGE, "", "").await?;
+        for pair in &pairs {
+            writer.write(pair)?;
+        }
+        match next {
+            Some(next) => cursor = next,
+            None => return Ok(writer.finish()?),
+        }
+    }
+}
+
+async fn import(
+    client: &KvClient,
+    table: &str,
+    file: &str,
+    format: Option<TableFormat>,
+    batch_size: usize,
+) -> Result<usize> {
+    let format = table_format(file, format)?;
+    let mut reader = TableReader::new(StdBufReader::new(File::open(file)?), format);
+    let mut count = 0;
+    loop {
+        let pairs = reader.next_batch(batch_size)?;
+        if pairs.is_empty() {
+            return Ok(count);
+        }
+        count += pairs.len();
+        client.hmset(table, pairs).await?;
+    }
+}
+
+/// Whether the command makes the server push messages to the connection.
+fn listens(cmd: &Cmd) -> bool {
+    matches!(cmd, Cmd::Subscribe { .. } | Cmd::Watch { .. })
+}
+
+/// Print publications and watched changes until the connection closes.
+async fn listen(client: &KvClient, output: Output) -> Result<()> {
+    loop {
+        let line = tokio::select! {
+            Some(publication) = client.next_publication() => render_publication(&publication, output),
+            Some(change) = client.next_change() => render_change(&change, output),
+            else => return Ok(()),
+        };
+        println!("{}", line);
+    }
+}
+
+async fn repl(client: &KvClient, output: Output) -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    // commands queued since `multi`, sent as one transaction by `exec`
+    let mut queued: Option<Vec<CommandRequest>> = None;
+    loop {
+        stdout.write_all(b"kv> ").await?;
+        stdout.flush().await?;
+
+        let line = match lines.next_line().await? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let words = match shell_words::split(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("quit" | "exit") => return Ok(()),
+            Some("multi") => {
+                match queued {
+                    Some(_) => eprintln!("error: multi calls can not be nested"),
+                    None => queued = Some(Vec::new()),
+                }
+                continue;
+            }
+            Some("discard") => {
+                if queued.take().is_none() {
+                    eprintln!("error: discard without multi");
+                }
+                continue;
+            }
+            Some("exec") => {
+                let cmd = match queued.take() {
+                    Some(cmds) => CommandRequest::new_transaction(cmds),
+                    None => {
+                        eprintln!("error: exec without multi");
+                        continue;
+                    }
+                };
+                match client.execute(cmd).await {
+                    Ok(res) => println!("{}", render(&res, output)),
+                    Err(e) => eprintln!("error: {}", e),
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        let cmd = match Line::try_parse_from(words) {
+            Ok(line) => line.cmd,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        if let Some(cmds) = queued.as_mut() {
+            match CommandRequest::try_from(cmd) {
+                Ok(cmd) => {
+                    cmds.push(cmd);
+                    println!("queued");
+                }
+                Err(e) => eprintln!("error: {}", e),
+            }
+            continue;
+        }
+        let listens = listens(&cmd);
+        match call(client, cmd).await {
+            // the shell has no way to stop listening, like `subscribe` in redis-cli
+            Ok(res) if listens && res.status == 200 => {
+                println!("{}", render(&res, output));
+                return listen(client, output).await;
+            }
+            Ok(res) => println!("{}", render(&res, output)),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let stream = TcpStream::connect(&args.addr).await?;
+    match args.tls {
+        true => {
+            let stream = args.connector()?.connect(stream).await?;
+            args.start(stream).await
+        }
+        false => args.start(stream).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_value_should_work() {
+        assert_eq!(parse_value("v1").unwrap(), "v1".into());
+        assert_eq!(parse_value("a:b").unwrap(), "a:b".into());
+        assert_eq!(parse_value("str:int:1").unwrap(), "int:1".into());
+        assert_eq!(parse_value("int:-42").unwrap(), (-42).into());
+        assert_eq!(parse_value("float:1.5").unwrap(), 1.5.into());
+        assert_eq!(parse_value("bool:true").unwrap(), true.into());
+        assert_eq!(
+            parse_value("bin:00ff").unwrap(),
+            Value {
+                value: Some(value::Value::Binary(vec![0, 255].into())),
+            }
+        );
+        assert!(parse_value("int:abc").is_err());
+        assert!(parse_value("bin:xyz").is_err());
+    }
+
+    #[test]
+    fn line_should_be_parsed_into_request() {
+        let line = Line::try_parse_from(["hset", "t1", "k1", "int:10", "--ttl", "500"]).unwrap();
+        let req: CommandRequest = line.cmd.try_into().unwrap();
+        assert_eq!(
+            req,
+            CommandRequest::new_hset_with_ttl("t1", "k1", 10.into(), 500)
+        );
+
+        let line = Line::try_parse_from(["hmset", "t1", "k1", "v1", "k2", "bool:false"]).unwrap();
+        let req: CommandRequest = line.cmd.try_into().unwrap();
+        let pairs = vec![
+            Kvpair::new("k1", "v1".into()),
+            Kvpair::new("k2", false.into()),
+        ];
+        assert_eq!(req, CommandRequest::new_hmset("t1", pairs));
+
+        let line = Line::try_parse_from(["hmset", "t1", "k1"]).unwrap();
+        assert!(CommandRequest::try_from(line.cmd).is_err());
+    }
+
+    #[test]
+    fn table_format_should_be_guessed_from_file() {
+        assert_eq!(table_format("t1.csv", None).unwrap(), TableFormat::Csv);
+        assert_eq!(
+            table_format("t1.csv", Some(TableFormat::Jsonl)).unwrap(),
+            TableFormat::Jsonl
+        );
+        assert!(table_format("t1.dump", None).is_err());
+
+        let line = Line::try_parse_from(["export", "t1", "t1.dump", "--format", "xml"]);
+        assert!(line.is_err());
+    }
+
+    #[test]
+    fn response_should_be_rendered() {
+        let mut res: CommandResponse = vec![Kvpair::new("k1", 1.into())].into();
+        res.cursor = "k1".into();
+
+        let table = render(&res, Output::Table);
+        assert_eq!(table, "status: 200\nKEY  VALUE\nk1   1\ncursor: k1");
+
+        let json: serde_json::Value = serde_json::from_str(&render(&res, Output::Json)).unwrap();
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["pairs"][0], json!({ "key": "k1", "value": 1 }));
+        assert_eq!(json["cursor"], "k1");
+    }
+}
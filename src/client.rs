@@ -0,0 +1,713 @@
+use crate::{
+    ChangeEvent, ClientFrames, CommandRequest, CommandResponse, FrameCodec, FrameConfig, KvError,
+    Kvpair, Publication, TlsClientConnector, Value,
+};
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_util::codec::Framed;
+use tracing::warn;
+
+type Reply = oneshot::Sender<Result<CommandResponse, KvError>>;
+
+/// Async client speaking the protobuf protocol. Clones share one connection,
+/// and concurrent calls are pipelined and matched to responses by request id.
+#[derive(Clone)]
+pub struct KvClient {
+    requests: mpsc::Sender<(CommandRequest, Reply)>,
+    publications: Arc<Mutex<mpsc::Receiver<Publication>>>,
+    changes: Arc<Mutex<mpsc::Receiver<ChangeEvent>>>,
+}
+
+impl KvClient {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, KvError> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self::new(stream))
+    }
+
+    pub async fn connect_tls(
+        addr: impl ToSocketAddrs,
+        connector: &TlsClientConnector,
+    ) -> Result<Self, KvError> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self::new(connector.connect(stream).await?))
+    }
+
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        Self::with_config(stream, FrameConfig::default())
+    }
+
+    pub fn with_config<S>(stream: S, config: FrameConfig) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(MAX_PENDING);
+        let (publications_tx, publications) = mpsc::channel(MAX_PENDING_PUSHES);
+        let (changes_tx, changes) = mpsc::channel(MAX_PENDING_PUSHES);
+        let pushes = Pushes {
+            publications: publications_tx,
+            changes: changes_tx,
+        };
+        let frames = Framed::new(stream, FrameCodec::new(config));
+        tokio::spawn(drive(frames, rx, pushes));
+        Self {
+            requests: tx,
+            publications: Arc::new(Mutex::new(publications)),
+            changes: Arc::new(Mutex::new(changes)),
+        }
+    }
+
+    /// Send a request and return the raw response, whatever its status.
+    pub async fn execute(&self, cmd: CommandRequest) -> Result<CommandResponse, KvError> {
+        let (reply, rx) = oneshot::channel();
+        self.requests
+            .send((cmd, reply))
+            .await
+            .map_err(|_| connection_closed())?;
+        rx.await.map_err(|_| connection_closed())?
+    }
+
+    async fn call(&self, cmd: CommandRequest) -> Result<CommandResponse, KvError> {
+        self.execute(cmd).await?.into_result()
+    }
+
+    async fn call_value(&self, cmd: CommandRequest) -> Result<Value, KvError> {
+        let res = self.call(cmd).await?;
+        Ok(res.values.into_iter().next().unwrap_or_default())
+    }
+
+    pub async fn hget(&self, table: &str, key: &str) -> Result<Value, KvError> {
+        self.call_value(CommandRequest::new_hget(table, key)).await
+    }
+
+    pub async fn hgetall(&self, table: &str) -> Result<Vec<Kvpair>, KvError> {
+        Ok(self.call(CommandRequest::new_hgetall(table)).await?.pairs)
+    }
+
+    /// Pairs of the keys that were found; missing keys are left out.
+    pub async fn hmget(&self, table: &str, keys: Vec<String>) -> Result<Vec<Kvpair>, KvError> {
+        Ok(self
+            .call(CommandRequest::new_hmget(table, keys))
+            .await?
+            .pairs)
+    }
+
+    /// Set a value, returning the previous one.
+    pub async fn hset(
+        &self,
+        table: &str,
+        key: &str,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, KvError> {
+        let cmd = CommandRequest::new_hset(table, key, value.into());
+        Ok(non_empty(self.call_value(cmd).await?))
+    }
+
+    pub async fn hset_with_ttl(
+        &self,
+        table: &str,
+        key: &str,
+        value: impl Into<Value>,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        let cmd = CommandRequest::new_hset_with_ttl(table, key, value.into(), ttl_ms(ttl));
+        Ok(non_empty(self.call_value(cmd).await?))
+    }
+
+    /// Set several values, returning the previous ones.
+    pub async fn hmset(&self, table: &str, pairs: Vec<Kvpair>) -> Result<Vec<Kvpair>, KvError> {
+        Ok(self
+            .call(CommandRequest::new_hmset(table, pairs))
+            .await?
+            .pairs)
+    }
+
+    /// Delete a key, returning whether it existed.
+    pub async fn hdel(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        let res = self.call(CommandRequest::new_hdel(table, key)).await?;
+        Ok(res.pairs.iter().any(|p| p.value.is_some()))
+    }
+
+    /// Delete several keys, returning how many existed.
+    pub async fn hmdel(&self, table: &str, keys: Vec<String>) -> Result<usize, KvError> {
+        let res = self.call(CommandRequest::new_hmdel(table, keys)).await?;
+        Ok(res.pairs.iter().filter(|p| p.value.is_some()).count())
+    }
+
+    pub async fn hexist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        self.call_value(CommandRequest::new_hexist(table, key))
+            .await?
+            .try_into()
+    }
+
+    pub async fn hmexist(
+        &self,
+        table: &str,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, bool)>, KvError> {
+        let res = self.call(CommandRequest::new_hmexist(table, keys)).await?;
+        res.pairs
+            .into_iter()
+            .map(|p| Ok((p.key, p.value.unwrap_or_default().try_into()?)))
+            .collect()
+    }
+
+    pub async fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<(), KvError> {
+        self.call(CommandRequest::new_expire(table, key, ttl_ms(ttl)))
+            .await?;
+        Ok(())
+    }
+
+    /// Remaining time to live, `None` if the key never expires.
+    pub async fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        let ms: i64 = self
+            .call_value(CommandRequest::new_ttl(table, key))
+            .await?
+            .try_into()?;
+        Ok((ms >= 0).then(|| Duration::from_millis(ms as u64)))
+    }
+
+    /// Remove the expiry of a key, returning whether it had one.
+    pub async fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        self.call_value(CommandRequest::new_persist(table, key))
+            .await?
+            .try_into()
+    }
+
+    pub async fn hincrby(&self, table: &str, key: &str, delta: i64) -> Result<i64, KvError> {
+        self.call_value(CommandRequest::new_hincrby(table, key, delta))
+            .await?
+            .try_into()
+    }
+
+    pub async fn hincrbyfloat(&self, table: &str, key: &str, delta: f64) -> Result<f64, KvError> {
+        self.call_value(CommandRequest::new_hincrbyfloat(table, key, delta))
+            .await?
+            .try_into()
+    }
+
+    /// Like `Storage::compare_and_swap`, a conflict yields `Ok(Err(current))`.
+    pub async fn hcas(
+        &self,
+        table: &str,
+        key: &str,
+        expected: Option<Value>,
+        value: Option<Value>,
+    ) -> Result<Result<(), Option<Value>>, KvError> {
+        let cmd = CommandRequest::new_hcas(table, key, expected, value);
+        let res = self.execute(cmd).await?;
+        let current = res.values.first().cloned().unwrap_or_default();
+        match res.into_result() {
+            Ok(_) => Ok(Ok(())),
+            Err(KvError::CasConflict(_, _)) => Ok(Err(non_empty(current))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// One page of keys after `cursor`, plus the cursor of the next page.
+    pub async fn hscan(
+        &self,
+        table: &str,
+        cursor: &str,
+        limit: u32,
+        prefix: &str,
+        pattern: &str,
+    ) -> Result<(Vec<Kvpair>, Option<String>), KvError> {
+        let cmd = CommandRequest::new_hscan(table, cursor, limit, prefix, pattern);
+        let res = self.call(cmd).await?;
+        let cursor = (!res.cursor.is_empty()).then_some(res.cursor);
+        Ok((res.pairs, cursor))
+    }
+
+    pub async fn hrange(
+        &self,
+        table: &str,
+        start: &str,
+        end: &str,
+        reverse: bool,
+        limit: u32,
+    ) -> Result<Vec<Kvpair>, KvError> {
+        let cmd = CommandRequest::new_hrange(table, start, end, reverse, limit);
+        Ok(self.call(cmd).await?.pairs)
+    }
+
+    pub async fn list_tables(&self) -> Result<Vec<String>, KvError> {
+        let res = self.call(CommandRequest::new_list_tables()).await?;
+        res.values.into_iter().map(String::try_from).collect()
+    }
+
+    pub async fn table_len(&self, table: &str) -> Result<usize, KvError> {
+        let len: i64 = self
+            .call_value(CommandRequest::new_table_len(table))
+            .await?
+            .try_into()?;
+        Ok(len as usize)
+    }
+
+    pub async fn drop_table(&self, table: &str) -> Result<bool, KvError> {
+        self.call_value(CommandRequest::new_drop_table(table))
+            .await?
+            .try_into()
+    }
+
+    pub async fn rename_table(&self, from: &str, to: &str) -> Result<(), KvError> {
+        self.call(CommandRequest::new_rename_table(from, to))
+            .await?;
+        Ok(())
+    }
+
+    /// Dump all tables to a file under the backup directory of the server, returning
+    /// the number of keys.
+    pub async fn backup(&self, path: &str) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_backup(path)).await
+    }
+
+    /// Load a backup under the backup directory of the server into a store without tables.
+    pub async fn restore(&self, path: &str) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_restore(path)).await
+    }
+
+    /// Run single-key commands all-or-nothing, returning their responses. The first
+    /// write failing for any key aborts the transaction with `TransactionAborted`;
+    /// failed reads, like a missing key, are returned as responses.
+    pub async fn transaction(
+        &self,
+        commands: Vec<CommandRequest>,
+    ) -> Result<Vec<CommandResponse>, KvError> {
+        let res = self.call(CommandRequest::new_transaction(commands)).await?;
+        Ok(res.results)
+    }
+
+    /// Subscribe the connection to channels, returning how many channels
+    /// and patterns it is subscribed to now.
+    pub async fn subscribe(&self, channels: Vec<String>) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_subscribe(channels))
+            .await
+    }
+
+    /// Unsubscribe from channels, or from all of them when empty.
+    pub async fn unsubscribe(&self, channels: Vec<String>) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_unsubscribe(channels))
+            .await
+    }
+
+    pub async fn psubscribe(&self, patterns: Vec<String>) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_psubscribe(patterns))
+            .await
+    }
+
+    pub async fn punsubscribe(&self, patterns: Vec<String>) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_punsubscribe(patterns))
+            .await
+    }
+
+    /// Publish to a channel, returning how many subscribers received it.
+    pub async fn publish(&self, channel: &str, data: impl Into<Value>) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_publish(channel, data.into()))
+            .await
+    }
+
+    /// Next message for the subscriptions of this connection, `None` once
+    /// the connection is closed.
+    pub async fn next_publication(&self) -> Option<Publication> {
+        self.publications.lock().await.recv().await
+    }
+
+    /// Watch a table, one key of it, or the keys with a prefix; pass empty
+    /// strings for what is not used. Returns the number of watches now.
+    pub async fn watch(&self, table: &str, key: &str, prefix: &str) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_watch(table, key, prefix))
+            .await
+    }
+
+    /// Drop a watch, or all of them when every argument is empty.
+    pub async fn unwatch(&self, table: &str, key: &str, prefix: &str) -> Result<usize, KvError> {
+        self.call_count(CommandRequest::new_unwatch(table, key, prefix))
+            .await
+    }
+
+    /// Next change of a watched key, `None` once the connection is closed.
+    pub async fn next_change(&self) -> Option<ChangeEvent> {
+        self.changes.lock().await.recv().await
+    }
+
+    async fn call_count(&self, cmd: CommandRequest) -> Result<usize, KvError> {
+        let count: i64 = self.call_value(cmd).await?.try_into()?;
+        Ok(count as usize)
+    }
+}
+
+/// Requests queued for the connection before callers have to wait.
+const MAX_PENDING: usize = 128;
+/// Publications or changes buffered until read; later ones are dropped.
+const MAX_PENDING_PUSHES: usize = 1024;
+
+/// Where messages the server sends unasked are delivered.
+struct Pushes {
+    publications: mpsc::Sender<Publication>,
+    changes: mpsc::Sender<ChangeEvent>,
+}
+
+/// Requests sent and waiting for their response, by request id.
+type Pending = std::sync::Mutex<HashMap<u64, Reply>>;
+
+/// Own the connection: tag outgoing requests with ids and route each
+/// response back to whoever sent the request. Pushed messages go to `pushes`.
+/// Requests are written while responses are read, so a slow write never
+/// stops the server from getting its responses out.
+async fn drive<S>(
+    frames: ClientFrames<S>,
+    requests: mpsc::Receiver<(CommandRequest, Reply)>,
+    pushes: Pushes,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let (sink, stream) = frames.split();
+    let pending = Pending::default();
+
+    let failure = tokio::select! {
+        // every handle is gone, so nobody waits for a response
+        _ = write_requests(sink, requests, &pending) => return,
+        failure = read_responses(stream, &pending, &pushes) => failure,
+    };
+    // requests the server never got to answer share the error it hung up with
+    if let Some(res) = failure {
+        for (_, reply) in lock(&pending).drain() {
+            let _ = reply.send(res.clone().into_result());
+        }
+    }
+}
+
+async fn write_requests<S>(
+    mut sink: SplitSink<ClientFrames<S>, CommandRequest>,
+    mut requests: mpsc::Receiver<(CommandRequest, Reply)>,
+    pending: &Pending,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let mut next_id = 1;
+    while let Some((cmd, reply)) = requests.recv().await {
+        let id = next_id;
+        next_id += 1;
+        // the response may be read before `send` returns
+        lock(pending).insert(id, reply);
+        let res = sink.send(cmd.with_id(id)).await;
+        let e = match res {
+            Ok(()) => continue,
+            Err(e) => e,
+        };
+        // nothing was written for these, the connection is still usable
+        let usable = matches!(e, KvError::FrameTooLarge(_, _) | KvError::EncodeError(_));
+        if let Some(reply) = lock(pending).remove(&id) {
+            let _ = reply.send(Err(e));
+        }
+        if !usable {
+            return;
+        }
+    }
+}
+
+/// Route responses until the connection closes, returning the error the server
+/// reported for the connection as a whole, if any.
+async fn read_responses<S>(
+    mut stream: SplitStream<ClientFrames<S>>,
+    pending: &Pending,
+    pushes: &Pushes,
+) -> Option<CommandResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let mut failure = None;
+    while let Some(res) = stream.next().await {
+        match res {
+            Ok(CommandResponse {
+                publication: Some(publication),
+                ..
+            }) => {
+                if pushes.publications.try_send(publication).is_err() {
+                    warn!("publication buffer is full, dropping publication");
+                }
+            }
+            Ok(CommandResponse {
+                change: Some(change),
+                ..
+            }) => {
+                if pushes.changes.try_send(change).is_err() {
+                    warn!("change buffer is full, dropping change");
+                }
+            }
+            Ok(res) => match lock(pending).remove(&res.id) {
+                Some(reply) => {
+                    let _ = reply.send(Ok(res));
+                }
+                // an error not tied to any request, e.g. an oversized frame; the
+                // server still answers what it read before and then hangs up
+                None if res.id == 0 => {
+                    warn!("server reported: {}", res.message);
+                    failure = Some(res);
+                }
+                None => warn!("got response for unknown request {}", res.id),
+            },
+            Err(e) => {
+                warn!("failed to read response: {}", e);
+                break;
+            }
+        }
+    }
+    failure
+}
+
+fn lock(pending: &Pending) -> std::sync::MutexGuard<'_, HashMap<u64, Reply>> {
+    pending
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn connection_closed() -> KvError {
+    KvError::Internal("connection closed by server".into())
+}
+
+/// The server answers with an empty value when there is nothing to return.
+fn non_empty(v: Value) -> Option<Value> {
+    v.value.is_some().then_some(v)
+}
+
+fn ttl_ms(ttl: Duration) -> u64 {
+    ttl.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Compression, FrameCodec, MemTable, ProstServerStream, ServerFrames, Service, ServiceInner,
+    };
+    use bytes::Bytes;
+
+    fn start_server() -> KvClient {
+        connect(ServiceInner::new(MemTable::new()).into())
+    }
+
+    fn connect(service: Service) -> KvClient {
+        let (client, server) = tokio::io::duplex(4096);
+        let stream = ProstServerStream::new(server, service, FrameConfig::default());
+        tokio::spawn(stream.process());
+        KvClient::new(client)
+    }
+
+    #[tokio::test]
+    async fn client_basic_commands_should_work() {
+        let client = start_server();
+
+        assert_eq!(client.hset("t1", "k1", "v1").await.unwrap(), None);
+        assert_eq!(client.hset("t1", "k1", "v2").await, Ok(Some("v1".into())));
+        assert_eq!(client.hget("t1", "k1").await, Ok("v2".into()));
+        assert_eq!(
+            client.hget("t1", "k2").await,
+            Err(KvError::NotFound("t1".into(), "k2".into()))
+        );
+        assert_eq!(client.hexist("t1", "k1").await, Ok(true));
+        assert_eq!(client.hincrby("t1", "n", 5).await, Ok(5));
+        assert_eq!(client.hincrbyfloat("t1", "f", 1.5).await, Ok(1.5));
+
+        let keys = vec!["k1".to_string(), "k2".to_string()];
+        let pairs = client.hmget("t1", keys.clone()).await.unwrap();
+        assert_eq!(pairs, vec![Kvpair::new("k1", "v2".into())]);
+        assert_eq!(client.hmdel("t1", keys).await, Ok(1));
+        assert_eq!(client.hdel("t1", "k1").await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn client_expire_and_cas_should_work() {
+        let client = start_server();
+
+        client.hset("t1", "k1", 1).await.unwrap();
+        assert_eq!(client.ttl("t1", "k1").await, Ok(None));
+        client
+            .expire("t1", "k1", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(matches!(client.ttl("t1", "k1").await, Ok(Some(_))));
+        assert_eq!(client.persist("t1", "k1").await, Ok(true));
+        assert_eq!(
+            client.expire("t1", "k2", Duration::from_secs(1)).await,
+            Err(KvError::NotFound("t1".into(), "k2".into()))
+        );
+
+        let res = client
+            .hcas("t1", "k1", Some(2.into()), Some(3.into()))
+            .await;
+        assert_eq!(res, Ok(Err(Some(1.into()))));
+        let res = client
+            .hcas("t1", "k1", Some(1.into()), Some(3.into()))
+            .await;
+        assert_eq!(res, Ok(Ok(())));
+    }
+
+    #[tokio::test]
+    async fn client_table_commands_should_work() {
+        let client = start_server();
+
+        client.hset("t1", "a", 1).await.unwrap();
+        client.hset("t1", "b", 2).await.unwrap();
+        assert_eq!(client.list_tables().await, Ok(vec!["t1".to_string()]));
+        assert_eq!(client.table_len("t1").await, Ok(2));
+
+        let (page, cursor) = client.hscan("t1", "", 1, "", "").await.unwrap();
+        assert_eq!(page, vec![Kvpair::new("a", 1.into())]);
+        assert_eq!(cursor.as_deref(), Some("a"));
+        let pairs = client.hrange("t1", "", "", true, 0).await.unwrap();
+        assert_eq!(pairs[0], Kvpair::new("b", 2.into()));
+
+        assert_eq!(
+            client.rename_table("t2", "t3").await,
+            Err(KvError::TableNotFound("t2".into()))
+        );
+        client.rename_table("t1", "t2").await.unwrap();
+        assert_eq!(client.drop_table("t2").await, Ok(true));
+        assert_eq!(client.list_tables().await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn client_transaction_should_work() {
+        let client = start_server();
+
+        let cmds = vec![
+            CommandRequest::new_hset("t1", "a", 1.into()),
+            CommandRequest::new_hset("t2", "b", 2.into()),
+        ];
+        let results = client.transaction(cmds).await.unwrap();
+        assert_eq!(results.len(), 2);
+
+        let cmds = vec![
+            CommandRequest::new_hdel("t1", "a"),
+            CommandRequest::new_hcas("t2", "b", Some(5.into()), Some(6.into())),
+        ];
+        let res = client.transaction(cmds).await;
+        assert!(matches!(res, Err(KvError::TransactionAborted(1, _))));
+        assert_eq!(client.hget("t1", "a").await, Ok(1.into()));
+    }
+
+    #[tokio::test]
+    async fn client_should_pipeline_concurrent_requests() {
+        let client = start_server();
+
+        let tasks = (0..50).map(|i| {
+            let client = client.clone();
+            async move {
+                let key = format!("k{}", i);
+                client.hset("t1", &key, i).await.unwrap();
+                client.hget("t1", &key).await
+            }
+        });
+        let values = futures::future::join_all(tasks).await;
+        for (i, value) in values.into_iter().enumerate() {
+            assert_eq!(value, Ok((i as i64).into()));
+        }
+    }
+
+    #[tokio::test]
+    async fn client_should_receive_publications() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let subscriber = connect(service.clone());
+        let publisher = connect(service);
+
+        assert_eq!(subscriber.subscribe(vec!["news".into()]).await, Ok(1));
+        assert_eq!(subscriber.psubscribe(vec!["n*".into()]).await, Ok(2));
+        assert_eq!(publisher.publish("news", "hello").await, Ok(2));
+        // requests still work on a subscribed connection
+        subscriber.hset("t1", "k1", 1).await.unwrap();
+
+        let mut patterns = Vec::new();
+        for _ in 0..2 {
+            let publication = subscriber.next_publication().await.unwrap();
+            assert_eq!(publication.channel, "news");
+            assert_eq!(publication.data, Some("hello".into()));
+            patterns.push(publication.pattern);
+        }
+        patterns.sort();
+        assert_eq!(patterns, vec!["".to_string(), "n*".to_string()]);
+
+        assert_eq!(subscriber.unsubscribe(vec![]).await, Ok(1));
+        assert_eq!(subscriber.punsubscribe(vec![]).await, Ok(0));
+        assert_eq!(publisher.publish("news", "bye").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn client_should_receive_watched_changes() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let watcher = connect(service.clone());
+        let writer = connect(service);
+
+        assert_eq!(watcher.watch("t1", "k1", "").await, Ok(1));
+        writer.hset("t1", "k2", 1).await.unwrap();
+        writer.hset("t1", "k1", 1).await.unwrap();
+        writer.hset("t1", "k1", 2).await.unwrap();
+        writer.hdel("t1", "k1").await.unwrap();
+
+        let expected = vec![
+            ChangeEvent::set("t1", "k1".into(), None, 1.into()),
+            ChangeEvent::set("t1", "k1".into(), Some(1.into()), 2.into()),
+            ChangeEvent::del("t1", "k1".into(), 2.into()),
+        ];
+        for change in expected {
+            assert_eq!(watcher.next_change().await, Some(change));
+        }
+        assert_eq!(watcher.unwatch("t1", "k1", "").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn connection_error_should_not_fail_answered_requests() {
+        let (client, server) = tokio::io::duplex(4096);
+        let client = KvClient::new(client);
+        let mut server: ServerFrames<_> =
+            Framed::new(server, FrameCodec::new(FrameConfig::default()));
+        tokio::spawn(async move {
+            let cmd = server.next().await.unwrap().unwrap();
+            let error: CommandResponse = KvError::FrameTooLarge(8192, 4096).into();
+            server.send(error).await.unwrap();
+            let mut res: CommandResponse = Value::from(1).into();
+            res.id = cmd.id;
+            server.send(res).await.unwrap();
+            // hang up without answering the second request
+            server.next().await.unwrap().unwrap();
+        });
+
+        assert_eq!(client.hget("t1", "k1").await, Ok(1.into()));
+        assert_eq!(
+            client.hget("t1", "k1").await,
+            Err(KvError::FrameTooLarge(8192, 4096))
+        );
+    }
+
+    #[tokio::test]
+    async fn large_pipelined_frames_should_not_deadlock() {
+        let config = FrameConfig {
+            compression: Compression::None,
+            max_frame_size: 1024 * 1024,
+            ..FrameConfig::default()
+        };
+        let (client, server) = tokio::io::duplex(4096);
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        tokio::spawn(ProstServerStream::new(server, service, config.clone()).process());
+        let client = KvClient::with_config(client, config);
+
+        let value = Bytes::from(vec![1u8; 256 * 1024]);
+        let calls = (0..16).map(|_| client.hset("t1", "k1", value.clone()));
+        let res = tokio::time::timeout(Duration::from_secs(10), futures::future::join_all(calls));
+        for res in res
+            .await
+            .expect("requests and responses should keep flowing")
+        {
+            res.unwrap();
+        }
+    }
+}
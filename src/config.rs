@@ -0,0 +1,223 @@
+use crate::{FrameConfig, FsyncPolicy, TlsServerAcceptor};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub general: GeneralConfig,
+    pub storage: StorageConfig,
+    pub log: LogConfig,
+    pub limits: LimitConfig,
+    pub frame: FrameConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralConfig {
+    pub addr: String,
+    /// Expect yamux multiplexed connections instead of a single stream.
+    pub multiplex: bool,
+    /// Also serve the Redis protocol (RESP) on this address.
+    pub resp_addr: Option<String>,
+    /// Also serve the HTTP/JSON gateway on this address, without TLS.
+    pub http_addr: Option<String>,
+    /// Also serve gRPC on this address, without TLS.
+    pub grpc_addr: Option<String>,
+    /// Allow the HTTP gateway and gRPC next to a TLS main port, although they bypass it.
+    pub insecure: bool,
+    /// Directory clients may write backups to and restore them from, none to refuse both.
+    pub backup_dir: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "args")]
+pub enum StorageConfig {
+    #[default]
+    MemTable,
+    /// A MemTable restored from and logging its changes to a write-ahead log.
+    MemTableWal(WalConfig),
+    SledDb(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WalConfig {
+    pub path: String,
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+    /// Snapshot the tables once the log grows past this many bytes, 0 to never.
+    #[serde(default = "default_compact_after")]
+    pub compact_after: u64,
+}
+
+fn default_compact_after() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// PEM files for serving TLS; clients must present a cert signed by `client_ca` if set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+    pub client_ca: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitConfig {
+    /// Maximum number of concurrently served connections.
+    pub max_connections: usize,
+    /// How often expired keys are purged, in milliseconds.
+    pub reaper_interval_ms: u64,
+}
+
+impl ServerConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check what parsing cannot, like limits of the wire format.
+    pub fn validate(&self) -> Result<()> {
+        self.frame.validate()?;
+        let general = &self.general;
+        let plaintext = general.http_addr.is_some() || general.grpc_addr.is_some();
+        if self.tls.is_some() && plaintext && !general.insecure {
+            bail!("http_addr and grpc_addr bypass tls, set insecure = true to serve them anyway");
+        }
+        Ok(())
+    }
+}
+
+impl WalConfig {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            fsync: FsyncPolicy::default(),
+            compact_after: default_compact_after(),
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn load_acceptor(&self) -> Result<TlsServerAcceptor> {
+        let cert = fs::read_to_string(&self.cert)?;
+        let key = fs::read_to_string(&self.key)?;
+        let client_ca = self
+            .client_ca
+            .as_ref()
+            .map(fs::read_to_string)
+            .transpose()?;
+        Ok(TlsServerAcceptor::new(&cert, &key, client_ca.as_deref())?)
+    }
+}
+
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:9527".into(),
+            multiplex: false,
+            resp_addr: None,
+            http_addr: None,
+            grpc_addr: None,
+            insecure: false,
+            backup_dir: None,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+        }
+    }
+}
+
+impl Default for LimitConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 1024,
+            reaper_interval_ms: 1000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_config_should_be_loaded() {
+        let config = ServerConfig::load("fixtures/server.toml").unwrap();
+        assert_eq!(config.general.addr, "127.0.0.1:9527");
+        assert_eq!(config.general.resp_addr, None);
+        assert_eq!(config.general.http_addr, None);
+        assert_eq!(config.general.grpc_addr, None);
+        assert_eq!(config.storage, StorageConfig::SledDb("/tmp/kv".into()));
+        assert_eq!(config.log.level, "info");
+        assert_eq!(config.limits.max_connections, 1024);
+        assert_eq!(config.tls, None);
+    }
+
+    #[test]
+    fn partial_server_config_should_use_defaults() {
+        let config: ServerConfig = toml::from_str("[limits]\nmax_connections = 8\n").unwrap();
+        assert_eq!(config.general, GeneralConfig::default());
+        assert_eq!(config.storage, StorageConfig::MemTable);
+        assert_eq!(config.limits.max_connections, 8);
+        assert_eq!(config.limits.reaper_interval_ms, 1000);
+    }
+
+    #[test]
+    fn wal_storage_config_should_be_parsed() {
+        let toml = "[storage]\ntype = \"MemTableWal\"\nargs = { path = \"/tmp/kv.wal\" }\n";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let wal = WalConfig {
+            path: "/tmp/kv.wal".into(),
+            fsync: FsyncPolicy::EverySecond,
+            compact_after: 64 * 1024 * 1024,
+        };
+        assert_eq!(config.storage, StorageConfig::MemTableWal(wal.clone()));
+        assert_eq!(WalConfig::new("/tmp/kv.wal"), wal);
+    }
+
+    #[test]
+    fn oversized_frame_config_should_be_rejected() {
+        let config: ServerConfig =
+            toml::from_str("[frame]\nmax_frame_size = 2147483648\n").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn plaintext_listeners_next_to_tls_should_need_opt_in() {
+        let toml = "[general]\ngrpc_addr = \"127.0.0.1:50051\"\n[tls]\ncert = \"c\"\nkey = \"k\"\n";
+        let mut config: ServerConfig = toml::from_str(toml).unwrap();
+        assert!(config.validate().is_err());
+        config.general.insecure = true;
+        assert!(config.validate().is_ok());
+        config.tls = None;
+        config.general.insecure = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn tls_config_should_be_parsed() {
+        let config: ServerConfig =
+            toml::from_str("[tls]\ncert = \"server.cert\"\nkey = \"server.key\"\n").unwrap();
+        let tls = config.tls.unwrap();
+        assert_eq!(tls.cert, "server.cert");
+        assert_eq!(tls.client_ca, None);
+    }
+}
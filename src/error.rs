@@ -6,12 +6,27 @@ pub enum KvError {
     #[error("Not found for table: {0}, key: {1}")]
     NotFound(String, String),
 
+    #[error("Table not found: {0}")]
+    TableNotFound(String),
+
+    #[error("Table already exists: {0}")]
+    TableExists(String),
+
     #[error("Cannot parse command: `{0}`")]
     InvalidCommand(String),
 
     #[error("Cannot convert value {:0} to {1}")]
     ConvertError(Value, &'static str),
 
+    #[error("Compare and swap conflict for table: {0}, key: {1}")]
+    CasConflict(String, String),
+
+    #[error("Transaction aborted by command {0}: {1}")]
+    TransactionAborted(usize, String),
+
+    #[error("Invalid record at line {0}: {1}")]
+    InvalidRecord(usize, String),
+
     #[error("Cannot process command {0} with table: {1}, key: {2}. Error: {}")]
     StorageError(&'static str, String, String, String),
 
@@ -21,9 +36,33 @@ pub enum KvError {
     #[error("Failed to decode protobuf message")]
     DecodeError(#[from] prost::DecodeError),
 
+    #[error("Frame of {0} bytes exceeds the limit of {1} bytes")]
+    FrameTooLarge(usize, usize),
+
+    #[error("Invalid frame: {0}")]
+    FrameError(String),
+
+    #[error("TLS error: {0}")]
+    TlsError(String),
+
+    #[error("I/O error: {0}")]
+    IoError(String),
+
     #[error("Failed to access Sled DB")]
     SledError(#[from] sled::Error),
     
     #[error("Internal error: {0}")]
     Internal(String),
 }
+
+impl From<std::io::Error> for KvError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e.to_string())
+    }
+}
+
+impl From<yamux::ConnectionError> for KvError {
+    fn from(e: yamux::ConnectionError) -> Self {
+        Self::IoError(e.to_string())
+    }
+}
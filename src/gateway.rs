@@ -0,0 +1,233 @@
+use crate::{CommandRequest, CommandResponse, KvError, Kvpair, Service, Storage, Value};
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// REST routes over the service. Every reply is the JSON encoded
+/// `CommandResponse`, sent with its status code.
+///
+/// - `GET /tables` lists tables
+/// - `GET /tables/:table` returns all pairs, `DELETE` drops the table
+/// - `GET|PUT|DELETE /tables/:table/keys/:key` reads, sets or deletes a key;
+///   `PUT` takes the JSON value as body and an optional `ttl_ms` query
+/// - `POST /tables/:table/batch/get|set|delete` work on several keys, taking
+///   a list of keys, or an object of key to value for `set`
+pub fn router<Store: Storage + Send + Sync + 'static>(service: Service<Store>) -> Router {
+    Router::new()
+        .route("/tables", get(list_tables::<Store>))
+        .route(
+            "/tables/:table",
+            get(get_table::<Store>).delete(drop_table::<Store>),
+        )
+        .route(
+            "/tables/:table/keys/:key",
+            get(get_key::<Store>)
+                .put(set_key::<Store>)
+                .delete(delete_key::<Store>),
+        )
+        .route("/tables/:table/batch/get", post(batch_get::<Store>))
+        .route("/tables/:table/batch/set", post(batch_set::<Store>))
+        .route("/tables/:table/batch/delete", post(batch_delete::<Store>))
+        .layer(Extension(service))
+}
+
+type Reply = (StatusCode, Json<serde_json::Value>);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SetParams {
+    ttl_ms: u64,
+}
+
+async fn list_tables<Store: Storage>(Extension(service): Extension<Service<Store>>) -> Reply {
+    reply(service.execute(CommandRequest::new_list_tables()))
+}
+
+async fn get_table<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path(table): Path<String>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_hgetall(table)))
+}
+
+async fn drop_table<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path(table): Path<String>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_drop_table(table)))
+}
+
+async fn get_key<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path((table, key)): Path<(String, String)>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_hget(table, key)))
+}
+
+async fn set_key<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path((table, key)): Path<(String, String)>,
+    Query(params): Query<SetParams>,
+    Json(value): Json<serde_json::Value>,
+) -> Reply {
+    let value = match Value::try_from(value) {
+        Ok(value) => value,
+        Err(e) => return reply(e.into()),
+    };
+    let cmd = CommandRequest::new_hset_with_ttl(table, key, value, params.ttl_ms);
+    reply(service.execute(cmd))
+}
+
+async fn delete_key<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path((table, key)): Path<(String, String)>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_hdel(table, key)))
+}
+
+async fn batch_get<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path(table): Path<String>,
+    Json(keys): Json<Vec<String>>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_hmget(table, keys)))
+}
+
+async fn batch_set<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path(table): Path<String>,
+    Query(params): Query<SetParams>,
+    Json(pairs): Json<BTreeMap<String, serde_json::Value>>,
+) -> Reply {
+    let pairs: Result<Vec<_>, KvError> = pairs
+        .into_iter()
+        .map(|(k, v)| Ok(Kvpair::new(k, v.try_into()?)))
+        .collect();
+    let cmd = match pairs {
+        Ok(pairs) => CommandRequest::new_hmset_with_ttl(table, pairs, params.ttl_ms),
+        Err(e) => return reply(e.into()),
+    };
+    reply(service.execute(cmd))
+}
+
+async fn batch_delete<Store: Storage>(
+    Extension(service): Extension<Service<Store>>,
+    Path(table): Path<String>,
+    Json(keys): Json<Vec<String>>,
+) -> Reply {
+    reply(service.execute(CommandRequest::new_hmdel(table, keys)))
+}
+
+fn reply(res: CommandResponse) -> Reply {
+    let status =
+        StatusCode::from_u16(res.status as u16).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, Json(serde_json::Value::from(&res)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemTable, ServiceInner};
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn call(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let body = match body {
+            Some(body) => Body::from(body.to_string()),
+            None => Body::empty(),
+        };
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn app() -> Router {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        router(service)
+    }
+
+    #[tokio::test]
+    async fn key_routes_should_work() {
+        let app = app();
+
+        let uri = "/tables/t1/keys/k1";
+        let (status, _) = call(&app, Method::PUT, uri, Some(json!("v1"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = call(&app, Method::PUT, uri, Some(json!(1.5))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["values"], json!(["v1"]));
+        let (_, body) = call(&app, Method::GET, uri, None).await;
+        assert_eq!(body["values"], json!([1.5]));
+
+        let (status, _) = call(&app, Method::DELETE, uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = call(&app, Method::GET, uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["message"].as_str().unwrap().starts_with("Not found"));
+
+        let body = json!({ "binary": "AQI=" });
+        let (status, _) = call(&app, Method::PUT, "/tables/t1/keys/bin", Some(body.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, res) = call(&app, Method::GET, "/tables/t1/keys/bin", None).await;
+        assert_eq!(res["values"], json!([body]));
+    }
+
+    #[tokio::test]
+    async fn batch_and_table_routes_should_work() {
+        let app = app();
+
+        let pairs = json!({ "a": 1, "b": true });
+        let (status, _) = call(&app, Method::POST, "/tables/t1/batch/set", Some(pairs)).await;
+        assert_eq!(status, StatusCode::OK);
+        let keys = json!(["a", "b"]);
+        let (_, body) = call(&app, Method::POST, "/tables/t1/batch/get", Some(keys)).await;
+        let mut pairs = body["pairs"].as_array().unwrap().clone();
+        pairs.sort_by_key(|p| p["key"].to_string());
+        assert_eq!(
+            pairs,
+            vec![
+                json!({ "key": "a", "value": 1 }),
+                json!({ "key": "b", "value": true })
+            ]
+        );
+
+        let (_, body) = call(&app, Method::GET, "/tables", None).await;
+        assert_eq!(body["values"], json!(["t1"]));
+        let keys = json!(["a"]);
+        call(&app, Method::POST, "/tables/t1/batch/delete", Some(keys)).await;
+        let (_, body) = call(&app, Method::GET, "/tables/t1", None).await;
+        assert_eq!(body["pairs"], json!([{ "key": "b", "value": true }]));
+        let (_, body) = call(&app, Method::DELETE, "/tables/t1", None).await;
+        assert_eq!(body["values"], json!([true]));
+    }
+
+    #[tokio::test]
+    async fn invalid_values_should_be_rejected() {
+        let app = app();
+
+        let body = Some(json!([1, 2]));
+        let (status, body) = call(&app, Method::PUT, "/tables/t1/keys/k1", body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("Unsupported JSON value"));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::command_request::RequestData;
+use crate::kv_service_server::{KvService, KvServiceServer};
+use crate::*;
+use futures::stream::{self, BoxStream, StreamExt};
+use tonic::{Code, Request, Response, Status};
+
+/// gRPC `KvService` backed by the same `Service` as the other frontends.
+pub struct GrpcService<Store> {
+    service: Service<Store>,
+}
+
+pub fn grpc_service<Store: Storage + Send + Sync + 'static>(
+    service: Service<Store>,
+) -> KvServiceServer<GrpcService<Store>> {
+    KvServiceServer::new(GrpcService { service })
+}
+
+type GrpcResult<T> = Result<Response<T>, Status>;
+
+impl<Store: Storage + Send + Sync + 'static> GrpcService<Store> {
+    async fn run(&self, data: RequestData) -> GrpcResult<CommandResponse> {
+        let cmd = CommandRequest {
+            request_data: Some(data),
+            ..Default::default()
+        };
+        self.call(cmd).await
+    }
+
+    /// Execute a command, on the blocking pool if it would hold up an async worker.
+    async fn call(&self, cmd: CommandRequest) -> GrpcResult<CommandResponse> {
+        if !cmd.is_blocking() {
+            return Ok(Response::new(self.service.execute(cmd)));
+        }
+        let service = self.service.clone();
+        tokio::task::spawn_blocking(move || service.execute(cmd))
+            .await
+            .map(Response::new)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+#[tonic::async_trait]
+impl<Store: Storage + Send + Sync + 'static> KvService for GrpcService<Store> {
+    async fn execute(&self, req: Request<CommandRequest>) -> GrpcResult<CommandResponse> {
+        self.call(req.into_inner()).await
+    }
+
+    async fn hget(&self, req: Request<Hget>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hget(req.into_inner())).await
+    }
+
+    async fn hgetall(&self, req: Request<Hgetall>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hgetall(req.into_inner())).await
+    }
+
+    async fn hmget(&self, req: Request<Hmget>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hmget(req.into_inner())).await
+    }
+
+    async fn hset(&self, req: Request<Hset>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hset(req.into_inner())).await
+    }
+
+    async fn hmset(&self, req: Request<Hmset>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hmset(req.into_inner())).await
+    }
+
+    async fn hdel(&self, req: Request<Hdel>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hdel(req.into_inner())).await
+    }
+
+    async fn hmdel(&self, req: Request<Hmdel>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hmdel(req.into_inner())).await
+    }
+
+    async fn hexist(&self, req: Request<Hexist>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hexist(req.into_inner())).await
+    }
+
+    async fn hmexist(&self, req: Request<Hmexist>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hmexist(req.into_inner())).await
+    }
+
+    async fn expire(&self, req: Request<Expire>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Expire(req.into_inner())).await
+    }
+
+    async fn ttl(&self, req: Request<Ttl>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Ttl(req.into_inner())).await
+    }
+
+    async fn persist(&self, req: Request<Persist>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Persist(req.into_inner())).await
+    }
+
+    async fn hincrby(&self, req: Request<Hincrby>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hincrby(req.into_inner())).await
+    }
+
+    async fn hincrbyfloat(&self, req: Request<Hincrbyfloat>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hincrbyfloat(req.into_inner())).await
+    }
+
+    async fn hcas(&self, req: Request<Hcas>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hcas(req.into_inner())).await
+    }
+
+    async fn hscan(&self, req: Request<Hscan>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hscan(req.into_inner())).await
+    }
+
+    async fn hrange(&self, req: Request<Hrange>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Hrange(req.into_inner())).await
+    }
+
+    async fn list_tables(&self, req: Request<ListTables>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::ListTables(req.into_inner())).await
+    }
+
+    async fn table_len(&self, req: Request<TableLen>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::TableLen(req.into_inner())).await
+    }
+
+    async fn drop_table(&self, req: Request<DropTable>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::DropTable(req.into_inner())).await
+    }
+
+    async fn rename_table(&self, req: Request<RenameTable>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::RenameTable(req.into_inner())).await
+    }
+
+    async fn publish(&self, req: Request<Publish>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Publish(req.into_inner())).await
+    }
+
+    async fn transaction(&self, req: Request<Transaction>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Transaction(req.into_inner())).await
+    }
+
+    async fn backup(&self, req: Request<Backup>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Backup(req.into_inner())).await
+    }
+
+    async fn restore(&self, req: Request<Restore>) -> GrpcResult<CommandResponse> {
+        self.run(RequestData::Restore(req.into_inner())).await
+    }
+
+    type ScanStream = BoxStream<'static, Result<Kvpair, Status>>;
+
+    async fn scan(&self, req: Request<Hscan>) -> GrpcResult<Self::ScanStream> {
+        let service = self.service.clone();
+        // `None` once the last page was sent
+        let pages = stream::unfold(Some(req.into_inner()), move |scan| {
+            let service = service.clone();
+            async move {
+                let scan = scan?;
+                let cmd = CommandRequest::new_hscan(
+                    scan.table.as_str(),
+                    scan.cursor.as_str(),
+                    scan.limit,
+                    scan.prefix.as_str(),
+                    scan.pattern.as_str(),
+                );
+                let (pairs, next) = match service.execute(cmd).into_result() {
+                    Ok(res) => {
+                        let next = (!res.cursor.is_empty()).then_some(Hscan {
+                            cursor: res.cursor,
+                            ..scan
+                        });
+                        (res.pairs.into_iter().map(Ok).collect(), next)
+                    }
+                    Err(e) => (vec![Err(to_status(e))], None),
+                };
+                Some((stream::iter::<Vec<_>>(pairs), next))
+            }
+        });
+        Ok(Response::new(pages.flatten().boxed()))
+    }
+}
+
+fn to_status(e: KvError) -> Status {
+    let code = match e {
+        KvError::NotFound(_, _) | KvError::TableNotFound(_) => Code::NotFound,
+        KvError::InvalidCommand(_) => Code::InvalidArgument,
+        _ => Code::Internal,
+    };
+    Status::new(code, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv_service_client::KvServiceClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    async fn start_server() -> String {
+        start_server_with(ServiceInner::new(MemTable::new()).into()).await
+    }
+
+    async fn start_server_with(service: Service) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(grpc_service(service))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn grpc_typed_and_generic_calls_should_work() {
+        let mut client = KvServiceClient::connect(start_server().await)
+            .await
+            .unwrap();
+
+        let hset = Hset {
+            table: "t1".into(),
+            pair: Some(Kvpair::new("k1", "v1".into())),
+            ttl_ms: 0,
+        };
+        let res = client.hset(hset).await.unwrap().into_inner();
+        assert_eq!(res.status, 200);
+
+        let hget = Hget {
+            table: "t1".into(),
+            key: "k1".into(),
+        };
+        let res = client.hget(hget).await.unwrap().into_inner();
+        assert_eq!(res.values, vec!["v1".into()]);
+
+        let cmd = CommandRequest::new_hget("t1", "k2");
+        let res = client.execute(cmd).await.unwrap().into_inner();
+        assert_eq!(res.status, 404);
+    }
+
+    #[tokio::test]
+    async fn grpc_scan_should_stream_all_pages() {
+        let mut client = KvServiceClient::connect(start_server().await)
+            .await
+            .unwrap();
+
+        let pairs: Vec<_> = (0..25)
+            .map(|i| Kvpair::new(format!("k{:02}", i), (i as i64).into()))
+            .collect();
+        client
+            .execute(CommandRequest::new_hmset("t1", pairs.clone()))
+            .await
+            .unwrap();
+
+        let scan = Hscan {
+            table: "t1".into(),
+            limit: 10,
+            ..Default::default()
+        };
+        let mut stream = client.scan(scan).await.unwrap().into_inner();
+        let mut scanned = Vec::new();
+        while let Some(pair) = stream.message().await.unwrap() {
+            scanned.push(pair);
+        }
+        assert_eq!(scanned, pairs);
+    }
+
+    #[tokio::test]
+    async fn grpc_backup_and_restore_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = ServiceInner::new(MemTable::new()).backup_dir(dir.path());
+        let mut client = KvServiceClient::connect(start_server_with(service.into()).await)
+            .await
+            .unwrap();
+        client
+            .execute(CommandRequest::new_hset("t1", "k1", "v1".into()))
+            .await
+            .unwrap();
+
+        let backup = Backup {
+            path: "kv.backup".into(),
+        };
+        let res = client.backup(backup).await.unwrap().into_inner();
+        assert_eq!(res.status, 200);
+        assert!(dir.path().join("kv.backup").exists());
+
+        // the store still has its table, so the restore is refused
+        let cmd = CommandRequest::new_restore("kv.backup");
+        let res = client.execute(cmd).await.unwrap().into_inner();
+        assert_eq!(res.status, 409);
+    }
+}
@@ -1,9 +1,23 @@
+mod client;
+mod config;
 mod error;
+mod gateway;
+mod grpc;
+mod network;
 mod pb;
+mod resp;
 mod service;
 mod storage;
+mod transfer;
 
+pub use client::*;
+pub use config::*;
 pub use error::KvError;
+pub use gateway::*;
+pub use grpc::*;
+pub use network::*;
 pub use pb::abi::*;
+pub use resp::*;
 pub use service::*;
 pub use storage::*;
+pub use transfer::*;
@@ -1,3 +1,382 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use kv_server::{
+    copy_store, grpc_service, router, serve_multiplexed, FrameConfig, FsyncPolicy, KvError,
+    MemTable, ProstServerStream, RespServerStream, ServerConfig, Service, ServiceInner, SledDb,
+    Storage, StorageConfig, TlsServerAcceptor, WalConfig,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// How often the size of a write-ahead log is checked for compaction.
+const COMPACT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Parser)]
+#[clap(name = "kv-server", version, about = "Serve the kv protocol over TCP")]
+struct Args {
+    /// Path of the TOML config file
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+    /// Address to listen on, e.g. 127.0.0.1:9527
+    #[clap(short, long)]
+    addr: Option<String>,
+    /// Storage backend: memtable or sled
+    #[clap(short, long)]
+    storage: Option<String>,
+    /// Path of the sled database, implies `--storage sled`
+    #[clap(short, long)]
+    path: Option<String>,
+    /// Path of the write-ahead log keeping a memtable across restarts, implies `--storage memtable`
+    #[clap(long)]
+    wal: Option<String>,
+    /// When to flush the write-ahead log to disk: always, every-second or never
+    #[clap(long)]
+    fsync: Option<FsyncPolicy>,
+    /// Log level or filter, e.g. debug or kv_server=trace
+    #[clap(long)]
+    log_level: Option<String>,
+    /// Maximum number of concurrent connections
+    #[clap(long)]
+    max_connections: Option<usize>,
+    /// Expect yamux multiplexed connections
+    #[clap(long)]
+    multiplex: bool,
+    /// Also serve the Redis protocol on this address, e.g. 127.0.0.1:6379
+    #[clap(long)]
+    resp_addr: Option<String>,
+    /// Also serve the HTTP/JSON gateway on this address, e.g. 127.0.0.1:8080
+    #[clap(long)]
+    http_addr: Option<String>,
+    /// Also serve gRPC on this address, e.g. 127.0.0.1:50051
+    #[clap(long)]
+    grpc_addr: Option<String>,
+    /// Serve HTTP and gRPC without TLS even when the main port uses it
+    #[clap(long)]
+    insecure: bool,
+    /// Directory clients may back up to and restore from
+    #[clap(long)]
+    backup_dir: Option<String>,
+    /// Run a maintenance command instead of serving
+    #[clap(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Cmd {
+    /// Copy all tables of a store into an empty one, e.g. from a memtable log into sled
+    Migrate {
+        /// Store to copy from: sled:<path> or wal:<path> for a memtable write-ahead log.
+        /// A wal source is recovered like on startup, so a torn tail is cut off and an
+        /// interrupted compaction finished in place
+        from: String,
+        /// Store to copy into, in the same form
+        to: String,
+        /// Skip comparing the copy with the source
+        #[clap(long)]
+        no_verify: bool,
+    },
+}
+
+impl Args {
+    fn into_config(self) -> Result<ServerConfig> {
+        let mut config = match &self.config {
+            Some(path) => ServerConfig::load(path)?,
+            None => ServerConfig::default(),
+        };
+
+        if let Some(addr) = self.addr {
+            config.general.addr = addr;
+        }
+        config.storage = match (self.storage.as_deref(), self.path, self.wal) {
+            (_, Some(_), Some(_)) => bail!("--path and --wal cannot be combined"),
+            (None, None, None) => config.storage,
+            (Some("memtable"), None, None) => StorageConfig::MemTable,
+            (Some("memtable"), Some(_), None) => bail!("--path is only valid for sled storage"),
+            (Some("memtable") | None, None, Some(path)) => match config.storage {
+                // keep fsync and compact_after of the config file
+                StorageConfig::MemTableWal(wal) => {
+                    StorageConfig::MemTableWal(WalConfig { path, ..wal })
+                }
+                _ => StorageConfig::MemTableWal(WalConfig::new(path)),
+            },
+            (Some("sled"), None, Some(_)) => bail!("--wal is only valid for memtable storage"),
+            (Some("sled") | None, Some(path), None) => StorageConfig::SledDb(path),
+            (Some("sled"), None, None) => match config.storage {
+                StorageConfig::SledDb(path) => StorageConfig::SledDb(path),
+                _ => bail!("--path is required for sled storage"),
+            },
+            (Some(other), _, _) => bail!("unknown storage backend: {}", other),
+        };
+        if let Some(fsync) = self.fsync {
+            match &mut config.storage {
+                StorageConfig::MemTableWal(wal) => wal.fsync = fsync,
+                _ => bail!("--fsync is only valid with a write-ahead log"),
+            }
+        }
+        if let Some(level) = self.log_level {
+            config.log.level = level;
+        }
+        if let Some(max) = self.max_connections {
+            config.limits.max_connections = max;
+        }
+        if self.multiplex {
+            config.general.multiplex = true;
+        }
+        if let Some(addr) = self.resp_addr {
+            config.general.resp_addr = Some(addr);
+        }
+        if let Some(addr) = self.http_addr {
+            config.general.http_addr = Some(addr);
+        }
+        if let Some(addr) = self.grpc_addr {
+            config.general.grpc_addr = Some(addr);
+        }
+        if self.insecure {
+            config.general.insecure = true;
+        }
+        if let Some(dir) = self.backup_dir {
+            config.general.backup_dir = Some(dir);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = Args::parse();
+    let cmd = args.cmd.take();
+    let config = args.into_config()?;
+
+    let filter =
+        EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.log.level))?;
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+
+    if let Some(Cmd::Migrate {
+        from,
+        to,
+        no_verify,
+    }) = cmd
+    {
+        return migrate(&from, &to, !no_verify);
+    }
+
+    match config.storage.clone() {
+        StorageConfig::MemTable => {
+            let service = new_service(MemTable::new(), &config);
+            serve(config, service).await
+        }
+        StorageConfig::MemTableWal(wal) => {
+            let store = MemTable::with_wal(&wal.path, wal.fsync)?;
+            let service = new_service(store, &config);
+            if wal.compact_after > 0 {
+                service.spawn_compactor(COMPACT_INTERVAL, wal.compact_after);
+            }
+            serve(config, service).await
+        }
+        StorageConfig::SledDb(path) => {
+            let service = new_service(SledDb::new(path), &config);
+            serve(config, service).await
+        }
+    }
+}
+
+fn new_service<Store: Storage>(store: Store, config: &ServerConfig) -> Service<Store> {
+    let inner = ServiceInner::new(store);
+    match &config.general.backup_dir {
+        Some(dir) => inner.backup_dir(dir).into(),
+        None => inner.into(),
+    }
+}
+
+/// A store named like `sled:<path>` or `wal:<path>`.
+enum Store {
+    Sled(SledDb),
+    Wal(MemTable),
+}
+
+impl Store {
+    fn open(spec: &str) -> Result<Self> {
+        match spec.split_once(':') {
+            Some(("sled", path)) => Ok(Self::Sled(SledDb::new(path))),
+            // synced once by `sync` rather than after every put
+            Some(("wal", path)) => Ok(Self::Wal(MemTable::with_wal(path, FsyncPolicy::Never)?)),
+            _ => bail!("unknown store {}, expected sled:<path> or wal:<path>", spec),
+        }
+    }
+
+    fn storage(&self) -> &dyn Storage {
+        match self {
+            Self::Sled(db) => db,
+            Self::Wal(table) => table,
+        }
+    }
+
+    /// Make everything written so far durable; sled flushes itself when dropped.
+    fn sync(&self) -> Result<()> {
+        if let Self::Wal(table) = self {
+            table.sync()?;
+        }
+        Ok(())
+    }
+}
+
+fn migrate(from: &str, to: &str, verify: bool) -> Result<()> {
+    if from == to {
+        bail!("cannot migrate {} into itself", from);
+    }
+    let src = Store::open(from)?;
+    let dst = Store::open(to)?;
+    let done = copy_store(src.storage(), dst.storage(), verify, |p| {
+        info!(
+            "{} tables, {} keys copied, {} keys verified",
+            p.tables, p.keys, p.verified
+        )
+    })?;
+    dst.sync()?;
+    info!(
+        "migrated {} keys of {} tables from {} to {}",
+        done.keys, done.tables, from, to
+    );
+    Ok(())
+}
+
+/// Protocol spoken on a listener.
+#[derive(Clone)]
+enum Frontend {
+    Kv { frame: FrameConfig, multiplex: bool },
+    Resp { max_frame_size: usize },
+}
+
+async fn serve<Store: Storage + Send + Sync + 'static>(
+    config: ServerConfig,
+    service: Service<Store>,
+) -> Result<()> {
+    service.spawn_reaper(Duration::from_millis(config.limits.reaper_interval_ms));
+
+    let acceptor = match &config.tls {
+        Some(tls) => Some(tls.load_acceptor()?),
+        None => None,
+    };
+    let permits = Arc::new(Semaphore::new(config.limits.max_connections));
+
+    if let Some(addr) = &config.general.resp_addr {
+        let listener = TcpListener::bind(addr).await?;
+        info!("start listening for RESP on {}", addr);
+        let frontend = Frontend::Resp {
+            max_frame_size: config.frame.max_frame_size,
+        };
+        let (service, acceptor, permits) = (service.clone(), acceptor.clone(), permits.clone());
+        tokio::spawn(async move {
+            if let Err(e) = listen(listener, service, acceptor, permits, frontend).await {
+                warn!("RESP listener failed: {}", e);
+            }
+        });
+    }
+
+    if let Some(addr) = &config.general.http_addr {
+        let server = axum::Server::try_bind(&addr.parse()?)?;
+        info!("start listening for HTTP on {}", addr);
+        let app = router(service.clone());
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(app.into_make_service()).await {
+                warn!("HTTP gateway failed: {}", e);
+            }
+        });
+    }
+
+    if let Some(addr) = &config.general.grpc_addr {
+        let listener = TcpListener::bind(addr).await?;
+        info!("start listening for gRPC on {}", addr);
+        let server = Server::builder().add_service(grpc_service(service.clone()));
+        tokio::spawn(async move {
+            if let Err(e) = server
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+            {
+                warn!("gRPC server failed: {}", e);
+            }
+        });
+    }
+
+    let addr = &config.general.addr;
+    let listener = TcpListener::bind(addr).await?;
+    info!(
+        "start listening on {} with {:?}, tls: {}, multiplex: {}",
+        addr,
+        config.storage,
+        acceptor.is_some(),
+        config.general.multiplex
+    );
+    let frontend = Frontend::Kv {
+        frame: config.frame.clone(),
+        multiplex: config.general.multiplex,
+    };
+    listen(listener, service, acceptor, permits, frontend).await
+}
+
+/// Accept connections until the listener fails, sharing `permits` with other listeners.
+async fn listen<Store: Storage + Send + Sync + 'static>(
+    listener: TcpListener,
+    service: Service<Store>,
+    acceptor: Option<TlsServerAcceptor>,
+    permits: Arc<Semaphore>,
+    frontend: Frontend,
+) -> Result<()> {
+    loop {
+        let permit = permits.clone().acquire_owned().await?;
+        let (stream, addr) = listener.accept().await?;
+        info!("client: {:?} connected", addr);
+
+        let (service, acceptor, frontend) = (service.clone(), acceptor.clone(), frontend.clone());
+        tokio::spawn(async move {
+            let res = match acceptor {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => handle(stream, service, frontend).await,
+                    Err(e) => Err(e),
+                },
+                None => handle(stream, service, frontend).await,
+            };
+            if let Err(e) = res {
+                warn!("failed to serve {:?}: {}", addr, e);
+            }
+            info!("client {:?} disconnected", addr);
+            drop(permit);
+        });
+    }
+}
+
+async fn handle<S, Store>(
+    stream: S,
+    service: Service<Store>,
+    frontend: Frontend,
+) -> Result<(), KvError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    Store: Storage + Send + Sync + 'static,
+{
+    match frontend {
+        Frontend::Kv {
+            frame,
+            multiplex: true,
+        } => serve_multiplexed(stream, service, frame).await,
+        Frontend::Kv { frame, .. } => {
+            ProstServerStream::new(stream, service, frame)
+                .process()
+                .await
+        }
+        Frontend::Resp { max_frame_size } => {
+            RespServerStream::new(stream, service, max_frame_size)
+                .process()
+                .await
+        }
+    }
 }
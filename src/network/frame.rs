@@ -0,0 +1,291 @@
+use crate::KvError;
+use bytes::{Buf, BufMut, BytesMut};
+use flate2::{read::GzDecoder, write::GzEncoder};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frame header: a big-endian u32 carrying the payload length and flags.
+const LEN_LEN: usize = 4;
+/// Set when the payload is compressed.
+const COMPRESSION_BIT: u32 = 1 << 31;
+/// Set when a compressed payload uses lz4 rather than gzip.
+const LZ4_BIT: u32 = 1 << 30;
+/// The remaining 30 bits hold the payload length, so frames stay below 1GB.
+const LEN_MASK: u32 = LZ4_BIT - 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Gzip,
+    Lz4,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameConfig {
+    /// Algorithm used for outgoing payloads; incoming frames describe their own.
+    pub compression: Compression,
+    /// Payloads smaller than this are sent uncompressed.
+    pub compression_threshold: usize,
+    /// Largest payload accepted or sent, before and after decompression.
+    pub max_frame_size: usize,
+}
+
+impl Default for FrameConfig {
+    fn default() -> Self {
+        Self {
+            compression: Compression::Gzip,
+            // leave room for the headers of a typical 1500 bytes MTU
+            compression_threshold: 1436,
+            max_frame_size: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl FrameConfig {
+    /// Reject sizes the 30 bits of the length field cannot carry.
+    pub fn validate(&self) -> Result<(), KvError> {
+        match self.max_frame_size > LEN_MASK as usize {
+            true => Err(KvError::FrameError(format!(
+                "max_frame_size {} exceeds the limit of {} bytes",
+                self.max_frame_size, LEN_MASK
+            ))),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Codec reading `In` frames and writing `Out` frames.
+#[derive(Clone, Debug)]
+pub struct FrameCodec<In, Out> {
+    config: FrameConfig,
+    _p: PhantomData<fn(Out) -> In>,
+}
+
+impl<In, Out> FrameCodec<In, Out> {
+    pub fn new(config: FrameConfig) -> Self {
+        Self {
+            config,
+            _p: PhantomData,
+        }
+    }
+
+    fn check_size(&self, len: usize) -> Result<(), KvError> {
+        match len > self.config.max_frame_size {
+            true => Err(KvError::FrameTooLarge(len, self.config.max_frame_size)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<In, Out> Default for FrameCodec<In, Out> {
+    fn default() -> Self {
+        Self::new(FrameConfig::default())
+    }
+}
+
+impl<In: Message + Default, Out> Decoder for FrameCodec<In, Out> {
+    type Item = In;
+    type Error = KvError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < LEN_LEN {
+            return Ok(None);
+        }
+
+        let header = u32::from_be_bytes(src[..LEN_LEN].try_into().unwrap());
+        let len = (header & LEN_MASK) as usize;
+        self.check_size(len)?;
+
+        if src.len() < LEN_LEN + len {
+            src.reserve(LEN_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(LEN_LEN);
+        let payload = src.split_to(len);
+        let msg = match (header & COMPRESSION_BIT != 0, header & LZ4_BIT != 0) {
+            (false, _) => In::decode(payload)?,
+            (true, false) => In::decode(&self.gunzip(&payload)?[..])?,
+            (true, true) => In::decode(&self.lz4_decompress(&payload)?[..])?,
+        };
+        Ok(Some(msg))
+    }
+}
+
+impl<In, Out: Message> Encoder<Out> for FrameCodec<In, Out> {
+    type Error = KvError;
+
+    fn encode(&mut self, item: Out, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let size = item.encoded_len();
+        self.check_size(size)?;
+
+        let mut buf = Vec::with_capacity(size);
+        item.encode(&mut buf)?;
+
+        let (payload, flags) = match self.config.compression {
+            Compression::Gzip if size >= self.config.compression_threshold => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&buf)?;
+                (encoder.finish()?, COMPRESSION_BIT)
+            }
+            Compression::Lz4 if size >= self.config.compression_threshold => (
+                lz4_flex::compress_prepend_size(&buf),
+                COMPRESSION_BIT | LZ4_BIT,
+            ),
+            _ => (buf, 0),
+        };
+        // compressed output can in rare cases be larger than the input
+        self.check_size(payload.len())?;
+
+        dst.reserve(LEN_LEN + payload.len());
+        dst.put_u32(payload.len() as u32 | flags);
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+impl<In, Out> FrameCodec<In, Out> {
+    fn gunzip(&self, payload: &[u8]) -> Result<Vec<u8>, KvError> {
+        let limit = self.config.max_frame_size;
+        let mut buf = Vec::new();
+        // read one byte past the limit to tell a full frame from an oversized one
+        GzDecoder::new(payload)
+            .take(limit as u64 + 1)
+            .read_to_end(&mut buf)?;
+        self.check_size(buf.len())?;
+        Ok(buf)
+    }
+
+    fn lz4_decompress(&self, payload: &[u8]) -> Result<Vec<u8>, KvError> {
+        if payload.len() < 4 {
+            return Err(KvError::FrameError("truncated lz4 payload".into()));
+        }
+        let size = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+        self.check_size(size)?;
+        lz4_flex::decompress_size_prepended(payload).map_err(|e| KvError::FrameError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CommandRequest, CommandResponse, Value};
+    use bytes::Bytes;
+
+    type RequestCodec = FrameCodec<CommandRequest, CommandRequest>;
+
+    fn codec(compression: Compression) -> RequestCodec {
+        FrameCodec::new(FrameConfig {
+            compression,
+            compression_threshold: 64,
+            max_frame_size: 4096,
+        })
+    }
+
+    fn roundtrip(codec: &mut RequestCodec, cmd: CommandRequest) -> (u32, CommandRequest) {
+        let mut buf = BytesMut::new();
+        codec.encode(cmd, &mut buf).unwrap();
+        let header = u32::from_be_bytes(buf[..4].try_into().unwrap());
+        let decoded = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(buf.is_empty());
+        (header & !LEN_MASK, decoded)
+    }
+
+    fn large_value() -> Value {
+        Bytes::from(vec![b'a'; 1024]).into()
+    }
+
+    #[test]
+    fn small_frame_should_not_be_compressed() {
+        let cmd = CommandRequest::new_hget("t1", "k1");
+        let (flags, decoded) = roundtrip(&mut codec(Compression::Gzip), cmd.clone());
+        assert_eq!(flags, 0);
+        assert_eq!(decoded, cmd);
+    }
+
+    #[test]
+    fn large_frame_should_be_compressed() {
+        let cmd = CommandRequest::new_hset("t1", "k1", large_value());
+
+        let (flags, decoded) = roundtrip(&mut codec(Compression::Gzip), cmd.clone());
+        assert_eq!(flags, COMPRESSION_BIT);
+        assert_eq!(decoded, cmd);
+
+        let (flags, decoded) = roundtrip(&mut codec(Compression::Lz4), cmd.clone());
+        assert_eq!(flags, COMPRESSION_BIT | LZ4_BIT);
+        assert_eq!(decoded, cmd);
+
+        let (flags, decoded) = roundtrip(&mut codec(Compression::None), cmd.clone());
+        assert_eq!(flags, 0);
+        assert_eq!(decoded, cmd);
+    }
+
+    #[test]
+    fn partial_frame_should_wait_for_more_data() {
+        let mut codec = codec(Compression::Gzip);
+        let mut buf = BytesMut::new();
+        codec
+            .encode(CommandRequest::new_hget("t1", "k1"), &mut buf)
+            .unwrap();
+
+        let mut partial = buf.split_to(buf.len() - 1);
+        assert_eq!(codec.decode(&mut partial).unwrap(), None);
+        partial.unsplit(buf);
+        assert!(codec.decode(&mut partial).unwrap().is_some());
+    }
+
+    #[test]
+    fn oversized_frame_should_be_rejected() {
+        let mut codec = codec(Compression::None);
+        let cmd = CommandRequest::new_hset("t1", "k1", Bytes::from(vec![0u8; 8192]).into());
+        let mut buf = BytesMut::new();
+        assert!(matches!(
+            codec.encode(cmd, &mut buf),
+            Err(KvError::FrameTooLarge(_, 4096))
+        ));
+
+        // only the header is needed to reject an incoming frame
+        buf.put_u32(8192);
+        assert_eq!(
+            codec.decode(&mut buf),
+            Err(KvError::FrameTooLarge(8192, 4096))
+        );
+    }
+
+    #[test]
+    fn frame_size_beyond_length_field_should_be_rejected() {
+        let mut config = FrameConfig::default();
+        assert_eq!(config.validate(), Ok(()));
+        config.max_frame_size = LEN_MASK as usize;
+        assert_eq!(config.validate(), Ok(()));
+        config.max_frame_size += 1;
+        assert!(matches!(config.validate(), Err(KvError::FrameError(_))));
+    }
+
+    #[test]
+    fn compressed_frame_should_not_expand_past_limit() {
+        let mut sender = FrameCodec::<CommandResponse, CommandResponse>::new(FrameConfig {
+            compression: Compression::Gzip,
+            compression_threshold: 64,
+            max_frame_size: 1024 * 1024,
+        });
+        let res: CommandResponse = Value::from(Bytes::from(vec![0u8; 64 * 1024])).into();
+        let mut buf = BytesMut::new();
+        sender.encode(res, &mut buf).unwrap();
+        assert!(buf.len() < 4096);
+
+        let mut receiver = FrameCodec::<CommandResponse, CommandResponse>::new(FrameConfig {
+            max_frame_size: 4096,
+            ..FrameConfig::default()
+        });
+        assert!(matches!(
+            receiver.decode(&mut buf),
+            Err(KvError::FrameTooLarge(_, 4096))
+        ));
+    }
+}
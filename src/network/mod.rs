@@ -0,0 +1,158 @@
+mod frame;
+mod multiplex;
+mod tls;
+
+pub use frame::{Compression, FrameCodec, FrameConfig};
+pub use multiplex::{serve_multiplexed, YamuxCtrl};
+pub use tls::{TlsClientConnector, TlsServerAcceptor};
+
+use crate::{CommandRequest, CommandResponse, KvError, Service, Storage};
+use futures::stream::FuturesUnordered;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+use tracing::warn;
+
+/// Server side of a connection: reads requests, writes responses.
+pub type ServerFrames<S> = Framed<S, FrameCodec<CommandRequest, CommandResponse>>;
+/// Client side of a connection: writes requests, reads responses.
+pub type ClientFrames<S> = Framed<S, FrameCodec<CommandResponse, CommandRequest>>;
+
+pub struct ProstServerStream<S, Store> {
+    inner: ServerFrames<S>,
+    service: Service<Store>,
+}
+
+impl<S, Store> ProstServerStream<S, Store>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    Store: Storage + Send + Sync + 'static,
+{
+    pub fn new(stream: S, service: Service<Store>, config: FrameConfig) -> Self {
+        Self {
+            inner: Framed::new(stream, FrameCodec::new(config)),
+            service,
+        }
+    }
+
+    /// Serve requests until the peer disconnects. Pipelined requests run
+    /// concurrently and are answered as they complete, tagged with their id.
+    /// Publications and watched changes for the connection are pushed in
+    /// between, with id 0.
+    pub async fn process(self) -> Result<(), KvError> {
+        let (mut sink, mut stream) = self.inner.split();
+        let mut subscriber = self.service.subscriber();
+        let mut in_flight = FuturesUnordered::new();
+        let mut reading = true;
+
+        loop {
+            tokio::select! {
+                cmd = stream.next(), if reading && in_flight.len() < MAX_IN_FLIGHT => match cmd {
+                    Some(Ok(cmd)) if cmd.is_subscription() => sink.send(subscriber.execute(cmd)).await?,
+                    Some(Ok(cmd)) => in_flight.push(execute(self.service.clone(), cmd)),
+                    // the rest of the frame is never read, so report and hang up
+                    Some(Err(e @ KvError::FrameTooLarge(_, _))) => {
+                        warn!("rejected frame: {}", e);
+                        sink.send(e.into()).await?;
+                        reading = false;
+                    }
+                    Some(Err(e)) => return Err(e),
+                    None => reading = false,
+                },
+                Some(res) = in_flight.next() => sink.send(res).await?,
+                // the subscriber never runs dry, so stop once the peer is gone
+                Some(res) = subscriber.recv(), if reading => sink.send(res).await?,
+                else => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maximum number of requests of one connection executed at the same time.
+const MAX_IN_FLIGHT: usize = 128;
+
+async fn execute<Store>(service: Service<Store>, cmd: CommandRequest) -> CommandResponse
+where
+    Store: Storage + Send + Sync + 'static,
+{
+    let id = cmd.id;
+    let res = match cmd.is_blocking() {
+        true => tokio::task::spawn_blocking(move || service.execute(cmd)).await,
+        false => tokio::spawn(async move { service.execute(cmd) }).await,
+    };
+    match res {
+        Ok(res) => res,
+        Err(e) => {
+            let mut res: CommandResponse = KvError::Internal(e.to_string()).into();
+            res.id = id;
+            res
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KvClient, MemTable, ServiceInner, Value};
+    use bytes::Bytes;
+
+    fn start_server(config: FrameConfig) -> tokio::io::DuplexStream {
+        let (client, server) = tokio::io::duplex(4096);
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        tokio::spawn(ProstServerStream::new(server, service, config).process());
+        client
+    }
+
+    #[tokio::test]
+    async fn server_stream_should_handle_compressed_frames() {
+        let stream = start_server(FrameConfig::default());
+        let config = FrameConfig {
+            compression: Compression::Lz4,
+            ..FrameConfig::default()
+        };
+        let client = KvClient::with_config(stream, config);
+
+        let value: Value = Bytes::from(vec![b'x'; 16 * 1024]).into();
+        client.hset("t1", "k1", value.clone()).await.unwrap();
+        assert_eq!(client.hget("t1", "k1").await, Ok(value));
+    }
+
+    #[tokio::test]
+    async fn server_stream_should_reject_oversized_frames() {
+        let stream = start_server(FrameConfig {
+            max_frame_size: 1024,
+            compression: Compression::None,
+            ..FrameConfig::default()
+        });
+        let config = FrameConfig {
+            compression: Compression::None,
+            ..FrameConfig::default()
+        };
+        let client = KvClient::with_config(stream, config);
+
+        let value: Value = Bytes::from(vec![0u8; 2048]).into();
+        let res = client.hset("t1", "k1", value).await;
+        assert!(matches!(res, Err(KvError::FrameTooLarge(_, 1024))));
+        assert!(client.hget("t1", "k1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn server_stream_should_echo_request_ids() {
+        let stream = start_server(FrameConfig::default());
+        let mut client: ClientFrames<_> = Framed::new(stream, FrameCodec::default());
+
+        for id in 1..=3 {
+            let cmd = CommandRequest::new_hset("t1", "k1", (id as i64).into());
+            client.feed(cmd.with_id(id)).await.unwrap();
+        }
+        client.flush().await.unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(client.next().await.unwrap().unwrap().id);
+        }
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{FrameConfig, KvError, ProstServerStream, Service, Storage};
+use futures::{future, TryStreamExt};
+use std::marker::PhantomData;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
+use tracing::warn;
+use yamux::{Config, Connection, ConnectionError, Control, Mode, WindowUpdateMode};
+
+/// Client side of a yamux connection; each opened stream carries its own
+/// request/response exchange.
+pub struct YamuxCtrl<S> {
+    ctrl: Control,
+    _conn: PhantomData<S>,
+}
+
+impl<S> YamuxCtrl<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn new_client(stream: S) -> Self {
+        let conn = Connection::new(stream.compat(), config(), Mode::Client);
+        let ctrl = conn.control();
+        // drive the connection; clients never accept inbound streams
+        tokio::spawn(yamux::into_stream(conn).try_for_each_concurrent(None, |_| future::ok(())));
+        Self {
+            ctrl,
+            _conn: PhantomData,
+        }
+    }
+
+    pub async fn open_stream(&mut self) -> Result<Compat<yamux::Stream>, KvError> {
+        let stream = self.ctrl.open_stream().await?;
+        Ok(stream.compat())
+    }
+}
+
+/// Serve each stream of a yamux connection on its own task until the peer hangs up.
+pub async fn serve_multiplexed<S, Store>(
+    stream: S,
+    service: Service<Store>,
+    frame: FrameConfig,
+) -> Result<(), KvError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    Store: Storage + Send + Sync + 'static,
+{
+    let conn = Connection::new(stream.compat(), config(), Mode::Server);
+    yamux::into_stream(conn)
+        .try_for_each_concurrent(None, |stream| {
+            let stream = ProstServerStream::new(stream.compat(), service.clone(), frame.clone());
+            tokio::spawn(async move {
+                if let Err(e) = stream.process().await {
+                    warn!("failed to serve stream: {}", e);
+                }
+            });
+            future::ok::<_, ConnectionError>(())
+        })
+        .await?;
+    Ok(())
+}
+
+fn config() -> Config {
+    let mut config = Config::default();
+    // only grant more window once the receiver has read its data
+    config.set_window_update_mode(WindowUpdateMode::OnRead);
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KvClient, MemTable, ServiceInner};
+    use tokio::io::DuplexStream;
+
+    fn start_server() -> YamuxCtrl<DuplexStream> {
+        let (client, server) = tokio::io::duplex(4096);
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        tokio::spawn(serve_multiplexed(server, service, FrameConfig::default()));
+        YamuxCtrl::new_client(client)
+    }
+
+    #[tokio::test]
+    async fn yamux_streams_should_share_one_connection() {
+        let mut ctrl = start_server();
+
+        let c1 = KvClient::new(ctrl.open_stream().await.unwrap());
+        let c2 = KvClient::new(ctrl.open_stream().await.unwrap());
+        c1.hset("t1", "k1", "v1").await.unwrap();
+        assert_eq!(c2.hget("t1", "k1").await, Ok("v1".into()));
+    }
+
+    #[tokio::test]
+    async fn yamux_streams_should_run_concurrently() {
+        let mut ctrl = start_server();
+
+        let mut tasks = Vec::new();
+        for _ in 0..20 {
+            let client = KvClient::new(ctrl.open_stream().await.unwrap());
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..10 {
+                    client.hincrby("t1", "counter", 1).await.unwrap();
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let client = KvClient::new(ctrl.open_stream().await.unwrap());
+        assert_eq!(client.hget("t1", "counter").await, Ok(200.into()));
+    }
+}
@@ -0,0 +1,257 @@
+use crate::KvError;
+use rustls_pemfile::Item;
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerConfig,
+    ServerName,
+};
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+
+/// Server side TLS; requires client certificates when built with a client CA.
+#[derive(Clone)]
+pub struct TlsServerAcceptor {
+    inner: TlsAcceptor,
+}
+
+/// Client side TLS, optionally presenting a certificate for mutual TLS.
+#[derive(Clone)]
+pub struct TlsClientConnector {
+    inner: TlsConnector,
+    domain: ServerName,
+}
+
+impl TlsServerAcceptor {
+    /// Build from PEM encoded server cert chain, private key and client CA.
+    pub fn new(cert: &str, key: &str, client_ca: Option<&str>) -> Result<Self, KvError> {
+        let certs = load_certs(cert)?;
+        let key = load_key(key)?;
+
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match client_ca {
+            Some(ca) => {
+                let roots = load_roots(ca)?;
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(certs, key)
+            .map_err(|e| KvError::TlsError(e.to_string()))?;
+
+        Ok(Self {
+            inner: TlsAcceptor::from(Arc::new(config)),
+        })
+    }
+
+    pub async fn accept<S>(&self, stream: S) -> Result<server::TlsStream<S>, KvError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        Ok(self.inner.accept(stream).await?)
+    }
+}
+
+impl TlsClientConnector {
+    /// Build for servers named `domain`, trusting `server_ca` or the
+    /// well-known web roots, with an optional PEM encoded (cert, key) identity.
+    pub fn new(
+        domain: &str,
+        identity: Option<(&str, &str)>,
+        server_ca: Option<&str>,
+    ) -> Result<Self, KvError> {
+        let roots = match server_ca {
+            Some(ca) => load_roots(ca)?,
+            None => {
+                let mut roots = RootCertStore::empty();
+                roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+                    OwnedTrustAnchor::from_subject_spki_name_constraints(
+                        ta.subject,
+                        ta.spki,
+                        ta.name_constraints,
+                    )
+                }));
+                roots
+            }
+        };
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let config = match identity {
+            Some((cert, key)) => builder
+                .with_single_cert(load_certs(cert)?, load_key(key)?)
+                .map_err(|e| KvError::TlsError(e.to_string()))?,
+            None => builder.with_no_client_auth(),
+        };
+        let domain = ServerName::try_from(domain)
+            .map_err(|_| KvError::TlsError(format!("invalid server name: {}", domain)))?;
+
+        Ok(Self {
+            inner: TlsConnector::from(Arc::new(config)),
+            domain,
+        })
+    }
+
+    pub async fn connect<S>(&self, stream: S) -> Result<client::TlsStream<S>, KvError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        Ok(self.inner.connect(self.domain.clone(), stream).await?)
+    }
+}
+
+fn load_certs(pem: &str) -> Result<Vec<Certificate>, KvError> {
+    let certs: Vec<_> = rustls_pemfile::certs(&mut Cursor::new(pem))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    match certs.is_empty() {
+        true => Err(KvError::TlsError("no certificate found".into())),
+        false => Ok(certs),
+    }
+}
+
+fn load_key(pem: &str) -> Result<PrivateKey, KvError> {
+    let mut reader = Cursor::new(pem);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key))
+            }
+            _ => {}
+        }
+    }
+    Err(KvError::TlsError("no private key found".into()))
+}
+
+fn load_roots(pem: &str) -> Result<RootCertStore, KvError> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(pem)? {
+        roots
+            .add(&cert)
+            .map_err(|e| KvError::TlsError(e.to_string()))?;
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FrameConfig, KvClient, MemTable, ProstServerStream, Service, ServiceInner};
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+    use tokio::io::DuplexStream;
+
+    struct TestCerts {
+        ca: String,
+        server: (String, String),
+        client: (String, String),
+    }
+
+    /// A CA plus a server cert for `kvserver.acme.inc` and a client cert.
+    fn generate_certs() -> TestCerts {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "kv test CA");
+        let ca = Certificate::from_params(params).unwrap();
+
+        let sign = |name: &str| {
+            let cert = rcgen::generate_simple_self_signed(vec![name.into()]).unwrap();
+            let pem = cert.serialize_pem_with_signer(&ca).unwrap();
+            (pem, cert.serialize_private_key_pem())
+        };
+
+        TestCerts {
+            ca: ca.serialize_pem().unwrap(),
+            server: sign("kvserver.acme.inc"),
+            client: sign("awesome-device-id"),
+        }
+    }
+
+    fn start_server(acceptor: TlsServerAcceptor) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(4096);
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        tokio::spawn(async move {
+            let stream = acceptor.accept(server).await?;
+            ProstServerStream::new(stream, service, FrameConfig::default())
+                .process()
+                .await
+        });
+        client
+    }
+
+    #[tokio::test]
+    async fn tls_should_work() {
+        let certs = generate_certs();
+        let (cert, key) = &certs.server;
+        let acceptor = TlsServerAcceptor::new(cert, key, None).unwrap();
+        let connector =
+            TlsClientConnector::new("kvserver.acme.inc", None, Some(&certs.ca)).unwrap();
+
+        let stream = connector.connect(start_server(acceptor)).await.unwrap();
+        let client = KvClient::new(stream);
+        client.hset("t1", "k1", "v1").await.unwrap();
+        assert_eq!(client.hget("t1", "k1").await, Ok("v1".into()));
+    }
+
+    #[tokio::test]
+    async fn mutual_tls_should_work() {
+        let certs = generate_certs();
+        let (cert, key) = &certs.server;
+        let acceptor = TlsServerAcceptor::new(cert, key, Some(&certs.ca)).unwrap();
+        let (cert, key) = &certs.client;
+        let identity = Some((cert.as_str(), key.as_str()));
+        let connector =
+            TlsClientConnector::new("kvserver.acme.inc", identity, Some(&certs.ca)).unwrap();
+
+        let stream = connector.connect(start_server(acceptor)).await.unwrap();
+        let client = KvClient::new(stream);
+        assert_eq!(client.hset("t1", "k1", 1).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn mutual_tls_without_client_cert_should_fail() {
+        let certs = generate_certs();
+        let (cert, key) = &certs.server;
+        let acceptor = TlsServerAcceptor::new(cert, key, Some(&certs.ca)).unwrap();
+        let connector =
+            TlsClientConnector::new("kvserver.acme.inc", None, Some(&certs.ca)).unwrap();
+
+        // with TLS 1.3 the client only learns about the rejection on first use
+        let res = match connector.connect(start_server(acceptor)).await {
+            Ok(stream) => KvClient::new(stream).hget("t1", "k1").await,
+            Err(e) => Err(e),
+        };
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn tls_with_wrong_domain_should_fail() {
+        let certs = generate_certs();
+        let (cert, key) = &certs.server;
+        let acceptor = TlsServerAcceptor::new(cert, key, None).unwrap();
+        let connector =
+            TlsClientConnector::new("kvserver1.acme.inc", None, Some(&certs.ca)).unwrap();
+
+        let res = connector.connect(start_server(acceptor)).await;
+        assert!(matches!(res, Err(KvError::IoError(_))));
+    }
+
+    #[test]
+    fn invalid_pem_should_be_rejected() {
+        let certs = generate_certs();
+        let (cert, _) = &certs.server;
+        assert!(matches!(
+            TlsServerAcceptor::new(cert, "not a key", None),
+            Err(KvError::TlsError(_))
+        ));
+        assert!(matches!(
+            TlsClientConnector::new("kvserver.acme.inc", None, Some("not a cert")),
+            Err(KvError::TlsError(_))
+        ));
+    }
+}
@@ -1,144 +1,1883 @@
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct CommandRequest {
-    #[prost(oneof="command_request::RequestData", tags="1, 2, 3, 4, 5, 6, 7, 8, 9")]
+    /// chosen by the client to match pipelined responses, 0 if unused
+    #[prost(uint64, tag = "100")]
+    pub id: u64,
+    #[prost(
+        oneof = "command_request::RequestData",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31"
+    )]
     pub request_data: ::core::option::Option<command_request::RequestData>,
 }
 /// Nested message and enum types in `CommandRequest`.
 pub mod command_request {
-    #[derive(PartialOrd)]
-    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    #[derive(PartialOrd, Clone, PartialEq, ::prost::Oneof)]
     pub enum RequestData {
-        #[prost(message, tag="1")]
+        #[prost(message, tag = "1")]
         Hget(super::Hget),
-        #[prost(message, tag="2")]
+        #[prost(message, tag = "2")]
         Hgetall(super::Hgetall),
-        #[prost(message, tag="3")]
+        #[prost(message, tag = "3")]
         Hmget(super::Hmget),
-        #[prost(message, tag="4")]
+        #[prost(message, tag = "4")]
         Hset(super::Hset),
-        #[prost(message, tag="5")]
+        #[prost(message, tag = "5")]
         Hmset(super::Hmset),
-        #[prost(message, tag="6")]
+        #[prost(message, tag = "6")]
         Hdel(super::Hdel),
-        #[prost(message, tag="7")]
+        #[prost(message, tag = "7")]
         Hmdel(super::Hmdel),
-        #[prost(message, tag="8")]
+        #[prost(message, tag = "8")]
         Hexist(super::Hexist),
-        #[prost(message, tag="9")]
+        #[prost(message, tag = "9")]
         Hmexist(super::Hmexist),
+        #[prost(message, tag = "10")]
+        Expire(super::Expire),
+        #[prost(message, tag = "11")]
+        Ttl(super::Ttl),
+        #[prost(message, tag = "12")]
+        Persist(super::Persist),
+        #[prost(message, tag = "13")]
+        Hincrby(super::Hincrby),
+        #[prost(message, tag = "14")]
+        Hincrbyfloat(super::Hincrbyfloat),
+        #[prost(message, tag = "15")]
+        Hcas(super::Hcas),
+        #[prost(message, tag = "16")]
+        Hscan(super::Hscan),
+        #[prost(message, tag = "17")]
+        Hrange(super::Hrange),
+        #[prost(message, tag = "18")]
+        ListTables(super::ListTables),
+        #[prost(message, tag = "19")]
+        TableLen(super::TableLen),
+        #[prost(message, tag = "20")]
+        DropTable(super::DropTable),
+        #[prost(message, tag = "21")]
+        RenameTable(super::RenameTable),
+        #[prost(message, tag = "22")]
+        Subscribe(super::Subscribe),
+        #[prost(message, tag = "23")]
+        Unsubscribe(super::Unsubscribe),
+        #[prost(message, tag = "24")]
+        Psubscribe(super::Psubscribe),
+        #[prost(message, tag = "25")]
+        Punsubscribe(super::Punsubscribe),
+        #[prost(message, tag = "26")]
+        Publish(super::Publish),
+        #[prost(message, tag = "27")]
+        Watch(super::Watch),
+        #[prost(message, tag = "28")]
+        Unwatch(super::Unwatch),
+        #[prost(message, tag = "29")]
+        Transaction(super::Transaction),
+        #[prost(message, tag = "30")]
+        Backup(super::Backup),
+        #[prost(message, tag = "31")]
+        Restore(super::Restore),
     }
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct CommandResponse {
-    #[prost(uint32, tag="1")]
+    #[prost(uint32, tag = "1")]
     pub status: u32,
-    #[prost(string, tag="2")]
+    #[prost(string, tag = "2")]
     pub message: ::prost::alloc::string::String,
-    #[prost(message, repeated, tag="3")]
+    #[prost(message, repeated, tag = "3")]
     pub values: ::prost::alloc::vec::Vec<Value>,
-    #[prost(message, repeated, tag="4")]
+    #[prost(message, repeated, tag = "4")]
     pub pairs: ::prost::alloc::vec::Vec<Kvpair>,
+    #[prost(string, tag = "5")]
+    pub cursor: ::prost::alloc::string::String,
+    /// id of the request this response answers
+    #[prost(uint64, tag = "6")]
+    pub id: u64,
+    /// set on messages pushed to subscribers, which have id 0
+    #[prost(message, optional, tag = "7")]
+    pub publication: ::core::option::Option<Publication>,
+    /// set on key changes pushed to watchers, which have id 0
+    #[prost(message, optional, tag = "8")]
+    pub change: ::core::option::Option<ChangeEvent>,
+    /// one response per command of a committed transaction
+    #[prost(message, repeated, tag = "9")]
+    pub results: ::prost::alloc::vec::Vec<CommandResponse>,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Value {
-    #[prost(oneof="value::Value", tags="1, 2, 3, 4, 5")]
+    #[prost(oneof = "value::Value", tags = "1, 2, 3, 4, 5")]
     pub value: ::core::option::Option<value::Value>,
 }
 /// Nested message and enum types in `Value`.
 pub mod value {
-    #[derive(PartialOrd)]
-    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    #[derive(PartialOrd, Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
-        #[prost(string, tag="1")]
+        #[prost(string, tag = "1")]
         String(::prost::alloc::string::String),
-        #[prost(bytes, tag="2")]
+        #[prost(bytes, tag = "2")]
         Binary(::prost::bytes::Bytes),
-        #[prost(int64, tag="3")]
+        #[prost(int64, tag = "3")]
         Integer(i64),
-        #[prost(double, tag="4")]
+        #[prost(double, tag = "4")]
         Float(f64),
-        #[prost(bool, tag="5")]
+        #[prost(bool, tag = "5")]
         Bool(bool),
     }
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Kvpair {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub key: ::prost::alloc::string::String,
-    #[prost(message, optional, tag="2")]
+    #[prost(message, optional, tag = "2")]
     pub value: ::core::option::Option<Value>,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hget {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, tag="2")]
+    #[prost(string, tag = "2")]
     pub key: ::prost::alloc::string::String,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hgetall {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hmget {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, repeated, tag="2")]
+    #[prost(string, repeated, tag = "2")]
     pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hset {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(message, optional, tag="2")]
+    #[prost(message, optional, tag = "2")]
     pub pair: ::core::option::Option<Kvpair>,
+    #[prost(uint64, tag = "3")]
+    pub ttl_ms: u64,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hmset {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(message, repeated, tag="2")]
+    #[prost(message, repeated, tag = "2")]
     pub pairs: ::prost::alloc::vec::Vec<Kvpair>,
+    #[prost(uint64, tag = "3")]
+    pub ttl_ms: u64,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hdel {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, tag="2")]
+    #[prost(string, tag = "2")]
     pub key: ::prost::alloc::string::String,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hmdel {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, repeated, tag="2")]
+    #[prost(string, repeated, tag = "2")]
     pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hexist {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, tag="2")]
+    #[prost(string, tag = "2")]
     pub key: ::prost::alloc::string::String,
 }
-#[derive(PartialOrd)]
-#[derive(Clone, PartialEq, ::prost::Message)]
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
 pub struct Hmexist {
-    #[prost(string, tag="1")]
+    #[prost(string, tag = "1")]
     pub table: ::prost::alloc::string::String,
-    #[prost(string, repeated, tag="2")]
+    #[prost(string, repeated, tag = "2")]
     pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Expire {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub ttl_ms: u64,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Ttl {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Persist {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Hincrby {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(int64, tag = "3")]
+    pub delta: i64,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Hincrbyfloat {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(double, tag = "3")]
+    pub delta: f64,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Hcas {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub expected: ::core::option::Option<Value>,
+    #[prost(message, optional, tag = "4")]
+    pub value: ::core::option::Option<Value>,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Hscan {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub cursor: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+    #[prost(string, tag = "4")]
+    pub prefix: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub pattern: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Hrange {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub start: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub end: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub start_inclusive: bool,
+    #[prost(bool, tag = "5")]
+    pub end_inclusive: bool,
+    #[prost(bool, tag = "6")]
+    pub reverse: bool,
+    #[prost(uint32, tag = "7")]
+    pub limit: u32,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct ListTables {}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct TableLen {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct DropTable {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct RenameTable {
+    #[prost(string, tag = "1")]
+    pub from: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub to: ::prost::alloc::string::String,
+}
+/// subscriptions only live as long as the connection that made them
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Subscribe {
+    #[prost(string, repeated, tag = "1")]
+    pub channels: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// drop the given channels, or all of them when empty
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Unsubscribe {
+    #[prost(string, repeated, tag = "1")]
+    pub channels: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// subscribe to every channel matching a glob pattern
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Psubscribe {
+    #[prost(string, repeated, tag = "1")]
+    pub patterns: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Punsubscribe {
+    #[prost(string, repeated, tag = "1")]
+    pub patterns: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Publish {
+    #[prost(string, tag = "1")]
+    pub channel: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub data: ::core::option::Option<Value>,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Publication {
+    #[prost(string, tag = "1")]
+    pub channel: ::prost::alloc::string::String,
+    /// the pattern that matched, empty for a plain subscription
+    #[prost(string, tag = "2")]
+    pub pattern: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub data: ::core::option::Option<Value>,
+}
+/// watch a whole table, a single key, or the keys with a prefix. Changes are pushed for
+/// hset, hmset, hdel, hmdel, hincrby, hincrbyfloat and hcas, also inside transactions;
+/// expiry changes, keys reaped after expiring, dropped or renamed tables and restores
+/// are not reported
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Watch {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub prefix: ::prost::alloc::string::String,
+}
+/// drop the matching watch, or all of them when empty
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Unwatch {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub prefix: ::prost::alloc::string::String,
+}
+/// run single-key commands all-or-nothing; the first write failing for any key aborts,
+/// failed reads are returned as responses
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Transaction {
+    #[prost(message, repeated, tag = "1")]
+    pub commands: ::prost::alloc::vec::Vec<CommandRequest>,
+}
+/// dump every table to a file on the server, answering with the number of keys; the path
+/// is relative to the backup_dir of the server
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Backup {
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+}
+/// load a backup, or a MemTable snapshot, under the backup_dir into a store without tables
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct Restore {
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct ChangeEvent {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    /// the key was deleted rather than set
+    #[prost(bool, tag = "3")]
+    pub deleted: bool,
+    /// absent if the key did not exist before, or if it was incremented
+    #[prost(message, optional, tag = "4")]
+    pub old_value: ::core::option::Option<Value>,
+    /// absent for deletions
+    #[prost(message, optional, tag = "5")]
+    pub new_value: ::core::option::Option<Value>,
+}
+/// a change to a MemTable, as appended to its write-ahead log; snapshots are
+/// written as such records too
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct WalRecord {
+    /// applied all-or-nothing on replay
+    #[prost(message, repeated, tag = "1")]
+    pub ops: ::prost::alloc::vec::Vec<WalOp>,
+    /// set on the first record of a log or snapshot, a log written after a
+    /// snapshot continues it with the same generation
+    #[prost(uint64, tag = "2")]
+    pub generation: u64,
+    /// on a snapshot: how much of the log of the previous generation it contains
+    #[prost(uint64, tag = "3")]
+    pub offset: u64,
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct WalOp {
+    #[prost(oneof = "wal_op::Op", tags = "1, 2, 3, 4")]
+    pub op: ::core::option::Option<wal_op::Op>,
+}
+/// Nested message and enum types in `WalOp`.
+pub mod wal_op {
+    #[derive(PartialOrd, Clone, PartialEq, ::prost::Oneof)]
+    pub enum Op {
+        #[prost(message, tag = "1")]
+        Put(super::WalPut),
+        #[prost(message, tag = "2")]
+        Del(super::Hdel),
+        #[prost(message, tag = "3")]
+        DropTable(super::DropTable),
+        #[prost(message, tag = "4")]
+        RenameTable(super::RenameTable),
+    }
+}
+#[derive(PartialOrd, Clone, PartialEq, ::prost::Message)]
+pub struct WalPut {
+    #[prost(string, tag = "1")]
+    pub table: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub value: ::core::option::Option<Value>,
+    /// unix time in milliseconds the key expires at, 0 for never
+    #[prost(uint64, tag = "4")]
+    pub expire_at: u64,
+}
+#[doc = r" Generated client implementations."]
+pub mod kv_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    #[doc = " Application errors are reported in `CommandResponse.status`, as over TCP."]
+    #[derive(Debug, Clone)]
+    pub struct KvServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl KvServiceClient<tonic::transport::Channel> {
+        #[doc = r" Attempt to create a new client by connecting to a given endpoint."]
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> KvServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::ResponseBody: Body + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> KvServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + Send + Sync,
+        {
+            KvServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        #[doc = r" Compress requests with `gzip`."]
+        #[doc = r""]
+        #[doc = r" This requires the server to support it otherwise it might respond with an"]
+        #[doc = r" error."]
+        pub fn send_gzip(mut self) -> Self {
+            self.inner = self.inner.send_gzip();
+            self
+        }
+        #[doc = r" Enable decompressing responses with `gzip`."]
+        pub fn accept_gzip(mut self) -> Self {
+            self.inner = self.inner.accept_gzip();
+            self
+        }
+        #[doc = " run any command"]
+        pub async fn execute(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CommandRequest>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Execute");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hget(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hget>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hget");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hgetall(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hgetall>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hgetall");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hmget(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hmget>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hmget");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hset(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hset>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hset");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hmset(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hmset>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hmset");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hdel(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hdel>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hdel");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hmdel(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hmdel>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hmdel");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hexist(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hexist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hexist");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hmexist(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hmexist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hmexist");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn expire(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Expire>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Expire");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn ttl(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Ttl>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Ttl");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn persist(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Persist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Persist");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hincrby(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hincrby>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hincrby");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hincrbyfloat(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hincrbyfloat>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hincrbyfloat");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hcas(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hcas>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hcas");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hscan(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hscan>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hscan");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn hrange(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hrange>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Hrange");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_tables(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListTables>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/ListTables");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn table_len(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TableLen>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/TableLen");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn drop_table(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DropTable>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/DropTable");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn rename_table(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RenameTable>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/RenameTable");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn publish(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Publish>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Publish");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn transaction(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Transaction>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Transaction");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn backup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Backup>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Backup");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn restore(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Restore>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Restore");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " every matching pair from the cursor on, fetched `limit` at a time"]
+        pub async fn scan(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Hscan>,
+        ) -> Result<tonic::Response<tonic::codec::Streaming<super::Kvpair>>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/abi.KvService/Scan");
+            self.inner
+                .server_streaming(request.into_request(), path, codec)
+                .await
+        }
+    }
+}
+#[doc = r" Generated server implementations."]
+pub mod kv_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    #[doc = "Generated trait containing gRPC methods that should be implemented for use with KvServiceServer."]
+    #[async_trait]
+    pub trait KvService: Send + Sync + 'static {
+        #[doc = " run any command"]
+        async fn execute(
+            &self,
+            request: tonic::Request<super::CommandRequest>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hget(
+            &self,
+            request: tonic::Request<super::Hget>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hgetall(
+            &self,
+            request: tonic::Request<super::Hgetall>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hmget(
+            &self,
+            request: tonic::Request<super::Hmget>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hset(
+            &self,
+            request: tonic::Request<super::Hset>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hmset(
+            &self,
+            request: tonic::Request<super::Hmset>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hdel(
+            &self,
+            request: tonic::Request<super::Hdel>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hmdel(
+            &self,
+            request: tonic::Request<super::Hmdel>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hexist(
+            &self,
+            request: tonic::Request<super::Hexist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hmexist(
+            &self,
+            request: tonic::Request<super::Hmexist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn expire(
+            &self,
+            request: tonic::Request<super::Expire>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn ttl(
+            &self,
+            request: tonic::Request<super::Ttl>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn persist(
+            &self,
+            request: tonic::Request<super::Persist>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hincrby(
+            &self,
+            request: tonic::Request<super::Hincrby>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hincrbyfloat(
+            &self,
+            request: tonic::Request<super::Hincrbyfloat>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hcas(
+            &self,
+            request: tonic::Request<super::Hcas>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hscan(
+            &self,
+            request: tonic::Request<super::Hscan>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn hrange(
+            &self,
+            request: tonic::Request<super::Hrange>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn list_tables(
+            &self,
+            request: tonic::Request<super::ListTables>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn table_len(
+            &self,
+            request: tonic::Request<super::TableLen>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn drop_table(
+            &self,
+            request: tonic::Request<super::DropTable>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn rename_table(
+            &self,
+            request: tonic::Request<super::RenameTable>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn publish(
+            &self,
+            request: tonic::Request<super::Publish>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn transaction(
+            &self,
+            request: tonic::Request<super::Transaction>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn backup(
+            &self,
+            request: tonic::Request<super::Backup>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        async fn restore(
+            &self,
+            request: tonic::Request<super::Restore>,
+        ) -> Result<tonic::Response<super::CommandResponse>, tonic::Status>;
+        #[doc = "Server streaming response type for the Scan method."]
+        type ScanStream: futures_core::Stream<Item = Result<super::Kvpair, tonic::Status>>
+            + Send
+            + 'static;
+        #[doc = " every matching pair from the cursor on, fetched `limit` at a time"]
+        async fn scan(
+            &self,
+            request: tonic::Request<super::Hscan>,
+        ) -> Result<tonic::Response<Self::ScanStream>, tonic::Status>;
+    }
+    #[doc = " Application errors are reported in `CommandResponse.status`, as over TCP."]
+    #[derive(Debug)]
+    pub struct KvServiceServer<T: KvService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: (),
+        send_compression_encodings: (),
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: KvService> KvServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for KvServiceServer<T>
+    where
+        T: KvService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = Never;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/abi.KvService/Execute" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExecuteSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::CommandRequest> for ExecuteSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CommandRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).execute(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExecuteSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hget" => {
+                    #[allow(non_camel_case_types)]
+                    struct HgetSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hget> for HgetSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hget>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hget(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HgetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hgetall" => {
+                    #[allow(non_camel_case_types)]
+                    struct HgetallSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hgetall> for HgetallSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Hgetall>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hgetall(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HgetallSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hmget" => {
+                    #[allow(non_camel_case_types)]
+                    struct HmgetSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hmget> for HmgetSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hmget>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hmget(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HmgetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hset" => {
+                    #[allow(non_camel_case_types)]
+                    struct HsetSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hset> for HsetSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hset>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hset(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HsetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hmset" => {
+                    #[allow(non_camel_case_types)]
+                    struct HmsetSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hmset> for HmsetSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hmset>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hmset(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HmsetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hdel" => {
+                    #[allow(non_camel_case_types)]
+                    struct HdelSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hdel> for HdelSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hdel>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hdel(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HdelSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hmdel" => {
+                    #[allow(non_camel_case_types)]
+                    struct HmdelSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hmdel> for HmdelSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hmdel>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hmdel(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HmdelSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hexist" => {
+                    #[allow(non_camel_case_types)]
+                    struct HexistSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hexist> for HexistSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hexist>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hexist(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HexistSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hmexist" => {
+                    #[allow(non_camel_case_types)]
+                    struct HmexistSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hmexist> for HmexistSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Hmexist>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hmexist(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HmexistSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Expire" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExpireSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Expire> for ExpireSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Expire>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).expire(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExpireSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Ttl" => {
+                    #[allow(non_camel_case_types)]
+                    struct TtlSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Ttl> for TtlSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Ttl>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).ttl(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TtlSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Persist" => {
+                    #[allow(non_camel_case_types)]
+                    struct PersistSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Persist> for PersistSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Persist>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).persist(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PersistSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hincrby" => {
+                    #[allow(non_camel_case_types)]
+                    struct HincrbySvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hincrby> for HincrbySvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Hincrby>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hincrby(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HincrbySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hincrbyfloat" => {
+                    #[allow(non_camel_case_types)]
+                    struct HincrbyfloatSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hincrbyfloat> for HincrbyfloatSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Hincrbyfloat>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hincrbyfloat(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HincrbyfloatSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hcas" => {
+                    #[allow(non_camel_case_types)]
+                    struct HcasSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hcas> for HcasSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hcas>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hcas(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HcasSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hscan" => {
+                    #[allow(non_camel_case_types)]
+                    struct HscanSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hscan> for HscanSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hscan>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hscan(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HscanSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Hrange" => {
+                    #[allow(non_camel_case_types)]
+                    struct HrangeSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Hrange> for HrangeSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hrange>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).hrange(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HrangeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/ListTables" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListTablesSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::ListTables> for ListTablesSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListTables>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_tables(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListTablesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/TableLen" => {
+                    #[allow(non_camel_case_types)]
+                    struct TableLenSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::TableLen> for TableLenSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TableLen>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).table_len(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TableLenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/DropTable" => {
+                    #[allow(non_camel_case_types)]
+                    struct DropTableSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::DropTable> for DropTableSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DropTable>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).drop_table(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DropTableSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/RenameTable" => {
+                    #[allow(non_camel_case_types)]
+                    struct RenameTableSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::RenameTable> for RenameTableSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RenameTable>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).rename_table(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RenameTableSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Publish" => {
+                    #[allow(non_camel_case_types)]
+                    struct PublishSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Publish> for PublishSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Publish>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).publish(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PublishSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Transaction" => {
+                    #[allow(non_camel_case_types)]
+                    struct TransactionSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Transaction> for TransactionSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Transaction>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).transaction(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TransactionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Backup" => {
+                    #[allow(non_camel_case_types)]
+                    struct BackupSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Backup> for BackupSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Backup>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).backup(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BackupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Restore" => {
+                    #[allow(non_camel_case_types)]
+                    struct RestoreSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::UnaryService<super::Restore> for RestoreSvc<T> {
+                        type Response = super::CommandResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Restore>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).restore(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RestoreSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/abi.KvService/Scan" => {
+                    #[allow(non_camel_case_types)]
+                    struct ScanSvc<T: KvService>(pub Arc<T>);
+                    impl<T: KvService> tonic::server::ServerStreamingService<super::Hscan> for ScanSvc<T> {
+                        type Response = super::Kvpair;
+                        type ResponseStream = T::ScanStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Hscan>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).scan(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ScanSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: KvService> Clone for KvServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
+        }
+    }
+    impl<T: KvService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: KvService> tonic::transport::NamedService for KvServiceServer<T> {
+        const NAME: &'static str = "abi.KvService";
+    }
+}
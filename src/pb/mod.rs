@@ -3,10 +3,17 @@ pub mod abi;
 use crate::pb::abi::command_request::RequestData;
 use crate::KvError;
 use abi::*;
+use bytes::Bytes;
 use http::StatusCode;
 use prost::Message;
+use serde_json::json;
 
 impl CommandRequest {
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+        self
+    }
+
     pub fn new_hset<T>(table: T, key: T, value: Value) -> Self
     where
         T: Into<String>,
@@ -15,7 +22,23 @@ impl CommandRequest {
             request_data: Some(RequestData::Hset(Hset {
                 table: table.into(),
                 pair: Some(Kvpair::new(key, value)),
+                ttl_ms: 0,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hset_with_ttl<T>(table: T, key: T, value: Value, ttl_ms: u64) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hset(Hset {
+                table: table.into(),
+                pair: Some(Kvpair::new(key, value)),
+                ttl_ms,
             })),
+            id: 0,
         }
     }
 
@@ -27,7 +50,23 @@ impl CommandRequest {
             request_data: Some(RequestData::Hmset(Hmset {
                 table: table.into(),
                 pairs,
+                ttl_ms: 0,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hmset_with_ttl<T>(table: T, pairs: Vec<Kvpair>, ttl_ms: u64) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hmset(Hmset {
+                table: table.into(),
+                pairs,
+                ttl_ms,
             })),
+            id: 0,
         }
     }
 
@@ -40,6 +79,7 @@ impl CommandRequest {
                 table: table.into(),
                 key: key.into(),
             })),
+            id: 0,
         }
     }
 
@@ -52,6 +92,7 @@ impl CommandRequest {
                 table: table.into(),
                 keys,
             })),
+            id: 0,
         }
     }
 
@@ -63,6 +104,7 @@ impl CommandRequest {
             request_data: Some(RequestData::Hgetall(Hgetall {
                 table: table.into(),
             })),
+            id: 0,
         }
     }
 
@@ -75,6 +117,7 @@ impl CommandRequest {
                 table: table.into(),
                 key: key.into(),
             })),
+            id: 0,
         }
     }
 
@@ -87,6 +130,7 @@ impl CommandRequest {
                 table: table.into(),
                 keys,
             })),
+            id: 0,
         }
     }
 
@@ -99,6 +143,7 @@ impl CommandRequest {
                 table: table.into(),
                 key: key.into(),
             })),
+            id: 0,
         }
     }
 
@@ -111,6 +156,310 @@ impl CommandRequest {
                 table: table.into(),
                 keys,
             })),
+            id: 0,
+        }
+    }
+
+    pub fn new_expire<T>(table: T, key: T, ttl_ms: u64) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Expire(Expire {
+                table: table.into(),
+                key: key.into(),
+                ttl_ms,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_ttl<T>(table: T, key: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Ttl(Ttl {
+                table: table.into(),
+                key: key.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_persist<T>(table: T, key: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Persist(Persist {
+                table: table.into(),
+                key: key.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hincrby<T>(table: T, key: T, delta: i64) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hincrby(Hincrby {
+                table: table.into(),
+                key: key.into(),
+                delta,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hincrbyfloat<T>(table: T, key: T, delta: f64) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hincrbyfloat(Hincrbyfloat {
+                table: table.into(),
+                key: key.into(),
+                delta,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hcas<T>(table: T, key: T, expected: Option<Value>, value: Option<Value>) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hcas(Hcas {
+                table: table.into(),
+                key: key.into(),
+                expected,
+                value,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_hscan<T>(table: T, cursor: T, limit: u32, prefix: T, pattern: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hscan(Hscan {
+                table: table.into(),
+                cursor: cursor.into(),
+                limit,
+                prefix: prefix.into(),
+                pattern: pattern.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    /// Range over `[start, end)`, an empty `start` or `end` leaves that side unbounded.
+    pub fn new_hrange<T>(table: T, start: T, end: T, reverse: bool, limit: u32) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Hrange(Hrange {
+                table: table.into(),
+                start: start.into(),
+                end: end.into(),
+                start_inclusive: true,
+                end_inclusive: false,
+                reverse,
+                limit,
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_list_tables() -> Self {
+        Self {
+            request_data: Some(RequestData::ListTables(ListTables {})),
+            id: 0,
+        }
+    }
+
+    pub fn new_table_len<T>(table: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::TableLen(TableLen {
+                table: table.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_drop_table<T>(table: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::DropTable(DropTable {
+                table: table.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_rename_table<T>(from: T, to: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::RenameTable(RenameTable {
+                from: from.into(),
+                to: to.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_subscribe(channels: Vec<String>) -> Self {
+        Self {
+            request_data: Some(RequestData::Subscribe(Subscribe { channels })),
+            id: 0,
+        }
+    }
+
+    pub fn new_unsubscribe(channels: Vec<String>) -> Self {
+        Self {
+            request_data: Some(RequestData::Unsubscribe(Unsubscribe { channels })),
+            id: 0,
+        }
+    }
+
+    pub fn new_psubscribe(patterns: Vec<String>) -> Self {
+        Self {
+            request_data: Some(RequestData::Psubscribe(Psubscribe { patterns })),
+            id: 0,
+        }
+    }
+
+    pub fn new_punsubscribe(patterns: Vec<String>) -> Self {
+        Self {
+            request_data: Some(RequestData::Punsubscribe(Punsubscribe { patterns })),
+            id: 0,
+        }
+    }
+
+    pub fn new_publish<T>(channel: T, data: Value) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Publish(Publish {
+                channel: channel.into(),
+                data: Some(data),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_watch<T>(table: T, key: T, prefix: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Watch(Watch {
+                table: table.into(),
+                key: key.into(),
+                prefix: prefix.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_unwatch<T>(table: T, key: T, prefix: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Unwatch(Unwatch {
+                table: table.into(),
+                key: key.into(),
+                prefix: prefix.into(),
+            })),
+            id: 0,
+        }
+    }
+
+    pub fn new_transaction(commands: Vec<CommandRequest>) -> Self {
+        Self {
+            request_data: Some(RequestData::Transaction(Transaction { commands })),
+            id: 0,
+        }
+    }
+
+    pub fn new_backup<T>(path: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Backup(Backup { path: path.into() })),
+            id: 0,
+        }
+    }
+
+    pub fn new_restore<T>(path: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            request_data: Some(RequestData::Restore(Restore { path: path.into() })),
+            id: 0,
+        }
+    }
+
+    /// Whether the command manages the subscriptions of its connection.
+    pub fn is_subscription(&self) -> bool {
+        matches!(
+            self.request_data,
+            Some(
+                RequestData::Subscribe(_)
+                    | RequestData::Unsubscribe(_)
+                    | RequestData::Psubscribe(_)
+                    | RequestData::Punsubscribe(_)
+                    | RequestData::Watch(_)
+                    | RequestData::Unwatch(_)
+            )
+        )
+    }
+
+    /// Whether the command reads or writes a whole store, so it belongs on a blocking thread.
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self.request_data,
+            Some(RequestData::Backup(_) | RequestData::Restore(_))
+        )
+    }
+}
+
+impl ChangeEvent {
+    pub fn set(table: &str, key: String, old: Option<Value>, new: Value) -> Self {
+        Self {
+            table: table.into(),
+            key,
+            deleted: false,
+            old_value: old,
+            new_value: Some(new),
+        }
+    }
+
+    pub fn del(table: &str, key: String, old: Value) -> Self {
+        Self {
+            table: table.into(),
+            key,
+            deleted: true,
+            old_value: Some(old),
+            new_value: None,
         }
     }
 }
@@ -151,6 +500,22 @@ impl From<i64> for Value {
     }
 }
 
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Self {
+            value: Some(value::Value::Float(f)),
+        }
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(b: Bytes) -> Self {
+        Self {
+            value: Some(value::Value::Binary(b)),
+        }
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Self {
@@ -176,11 +541,24 @@ impl From<KvError> for CommandResponse {
             message: e.to_string(),
             values: vec![],
             pairs: vec![],
+            cursor: String::new(),
+            id: 0,
+            publication: None,
+            change: None,
+            results: vec![],
         };
 
         match e {
             KvError::NotFound(_, _) => result.status = StatusCode::NOT_FOUND.as_u16() as _,
+            KvError::TableNotFound(_) => result.status = StatusCode::NOT_FOUND.as_u16() as _,
+            KvError::TableExists(_) => result.status = StatusCode::CONFLICT.as_u16() as _,
             KvError::InvalidCommand(_) => result.status = StatusCode::BAD_REQUEST.as_u16() as _,
+            KvError::InvalidRecord(_, _) => result.status = StatusCode::BAD_REQUEST.as_u16() as _,
+            KvError::CasConflict(_, _) => result.status = StatusCode::CONFLICT.as_u16() as _,
+            KvError::TransactionAborted(_, _) => result.status = StatusCode::CONFLICT.as_u16() as _,
+            KvError::FrameTooLarge(_, _) => {
+                result.status = StatusCode::PAYLOAD_TOO_LARGE.as_u16() as _
+            }
             KvError::ConvertError(_, _) => {}
             KvError::StorageError(_, _, _, _) => {}
             KvError::EncodeError(_) => {}
@@ -193,6 +571,16 @@ impl From<KvError> for CommandResponse {
     }
 }
 
+impl From<Vec<Value>> for CommandResponse {
+    fn from(v: Vec<Value>) -> Self {
+        Self {
+            status: StatusCode::OK.as_u16() as _,
+            values: v,
+            ..Default::default()
+        }
+    }
+}
+
 impl From<Vec<Kvpair>> for CommandResponse {
     fn from(v: Vec<Kvpair>) -> Self {
         Self {
@@ -279,12 +667,170 @@ impl From<(Vec<Kvpair>, Vec<(String, KvError)>)> for CommandResponse {
     }
 }
 
+impl From<(Vec<Kvpair>, Option<String>)> for CommandResponse {
+    fn from(page: (Vec<Kvpair>, Option<String>)) -> Self {
+        Self {
+            status: StatusCode::OK.as_u16() as _,
+            pairs: page.0,
+            cursor: page.1.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Publication> for CommandResponse {
+    fn from(publication: Publication) -> Self {
+        Self {
+            status: StatusCode::OK.as_u16() as _,
+            publication: Some(publication),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Vec<CommandResponse>> for CommandResponse {
+    fn from(results: Vec<CommandResponse>) -> Self {
+        Self {
+            status: StatusCode::OK.as_u16() as _,
+            results,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ChangeEvent> for CommandResponse {
+    fn from(change: ChangeEvent) -> Self {
+        Self {
+            status: StatusCode::OK.as_u16() as _,
+            change: Some(change),
+            ..Default::default()
+        }
+    }
+}
+
+impl CommandResponse {
+    /// Turn a non-2xx response back into the `KvError` that produced it.
+    pub fn into_result(self) -> Result<Self, KvError> {
+        match StatusCode::from_u16(self.status as _) {
+            Ok(status) if status.is_success() => Ok(self),
+            _ => Err(KvError::from_response(self.status, &self.message)),
+        }
+    }
+}
+
+impl KvError {
+    fn from_response(status: u32, message: &str) -> Self {
+        let table_and_key = |rest: &str| {
+            rest.split_once(", key: ")
+                .map(|(t, k)| (t.to_string(), k.to_string()))
+        };
+
+        if let Some((t, k)) = message
+            .strip_prefix("Not found for table: ")
+            .and_then(table_and_key)
+        {
+            return KvError::NotFound(t, k);
+        }
+        if let Some((t, k)) = message
+            .strip_prefix("Compare and swap conflict for table: ")
+            .and_then(table_and_key)
+        {
+            return KvError::CasConflict(t, k);
+        }
+        if let Some((i, m)) = message
+            .strip_prefix("Transaction aborted by command ")
+            .and_then(|m| m.split_once(": "))
+        {
+            if let Ok(i) = i.parse() {
+                return KvError::TransactionAborted(i, m.into());
+            }
+        }
+        if let Some(t) = message.strip_prefix("Table not found: ") {
+            return KvError::TableNotFound(t.into());
+        }
+        if let Some(t) = message.strip_prefix("Table already exists: ") {
+            return KvError::TableExists(t.into());
+        }
+        if let Some((len, max)) = message
+            .strip_prefix("Frame of ")
+            .and_then(|m| m.strip_suffix(" bytes"))
+            .and_then(|m| m.split_once(" bytes exceeds the limit of "))
+        {
+            if let (Ok(len), Ok(max)) = (len.parse(), max.parse()) {
+                return KvError::FrameTooLarge(len, max);
+            }
+        }
+        if let Some(m) = message.strip_prefix("TLS error: ") {
+            return KvError::TlsError(m.into());
+        }
+        if let Some(m) = message.strip_prefix("I/O error: ") {
+            return KvError::IoError(m.into());
+        }
+        if let Some(m) = message.strip_prefix("Internal error: ") {
+            return KvError::Internal(m.into());
+        }
+
+        match StatusCode::from_u16(status as _) {
+            Ok(StatusCode::BAD_REQUEST) => {
+                let m = message.strip_prefix("Cannot parse command: `");
+                let m = m.and_then(|m| m.strip_suffix('`')).unwrap_or(message);
+                KvError::InvalidCommand(m.into())
+            }
+            _ => KvError::Internal(format!("{} (status {})", message, status)),
+        }
+    }
+}
+
 impl From<(String, Value)> for Kvpair {
     fn from(data: (String, Value)) -> Self {
         Kvpair::new(data.0, data.1)
     }
 }
 
+impl TryFrom<Value> for i64 {
+    type Error = KvError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v.value {
+            Some(value::Value::Integer(i)) => Ok(i),
+            _ => Err(KvError::ConvertError(v, "integer")),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = KvError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v.value {
+            Some(value::Value::Float(f)) => Ok(f),
+            _ => Err(KvError::ConvertError(v, "float")),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = KvError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v.value {
+            Some(value::Value::Bool(b)) => Ok(b),
+            _ => Err(KvError::ConvertError(v, "bool")),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = KvError;
+
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v.value {
+            Some(value::Value::String(s)) => Ok(s),
+            _ => Err(KvError::ConvertError(v, "string")),
+        }
+    }
+}
+
 impl TryFrom<Value> for Vec<u8> {
     type Error = KvError;
 
@@ -304,6 +850,73 @@ impl TryFrom<&[u8]> for Value {
     }
 }
 
+/// Binary values are base64 encoded wherever a value is written as JSON or text.
+pub(crate) fn encode_binary(b: &[u8]) -> String {
+    base64::encode(b)
+}
+
+pub(crate) fn decode_binary(s: &str) -> Result<Bytes, base64::DecodeError> {
+    base64::decode(s).map(Bytes::from)
+}
+
+/// Binary values become `{"binary": "<base64>"}`, an empty value `null`.
+impl From<&Value> for serde_json::Value {
+    fn from(v: &Value) -> Self {
+        match &v.value {
+            Some(value::Value::String(s)) => json!(s),
+            Some(value::Value::Binary(b)) => json!({ "binary": encode_binary(b) }),
+            Some(value::Value::Integer(i)) => json!(i),
+            Some(value::Value::Float(f)) => json!(f),
+            Some(value::Value::Bool(b)) => json!(b),
+            None => serde_json::Value::Null,
+        }
+    }
+}
+
+impl TryFrom<serde_json::Value> for Value {
+    type Error = KvError;
+
+    fn try_from(v: serde_json::Value) -> Result<Self, Self::Error> {
+        use serde_json::Value as Json;
+
+        let invalid = |v: &Json| KvError::InvalidCommand(format!("Unsupported JSON value: {}", v));
+        match &v {
+            Json::String(s) => Ok(s.as_str().into()),
+            Json::Bool(b) => Ok((*b).into()),
+            Json::Number(n) => match (n.as_i64(), n.as_f64()) {
+                (Some(i), _) => Ok(i.into()),
+                (None, Some(f)) => Ok(f.into()),
+                _ => Err(invalid(&v)),
+            },
+            Json::Object(o) if o.len() == 1 => match o.get("binary") {
+                Some(Json::String(b)) => decode_binary(b).map(Value::from).map_err(|_| invalid(&v)),
+                _ => Err(invalid(&v)),
+            },
+            _ => Err(invalid(&v)),
+        }
+    }
+}
+
+impl From<&CommandResponse> for serde_json::Value {
+    fn from(res: &CommandResponse) -> Self {
+        let pairs: Vec<_> = res
+            .pairs
+            .iter()
+            .map(
+                |p| json!({ "key": p.key, "value": p.value.as_ref().map(serde_json::Value::from) }),
+            )
+            .collect();
+        json!({
+            "status": res.status,
+            "message": res.message,
+            "values": res.values.iter().map(serde_json::Value::from).collect::<Vec<_>>(),
+            "pairs": pairs,
+            "cursor": res.cursor,
+            "results": res.results.iter().map(serde_json::Value::from).collect::<Vec<_>>(),
+        })
+    }
+}
+
 fn estimate_status_code_by_vec<T>(data: &Vec<T>) -> u32 {
     let status;
     if data.len() > 0 {
@@ -0,0 +1,510 @@
+use super::RespFrame;
+use crate::storage::glob_match;
+use crate::{value, CommandRequest, CommandResponse, KvError, Kvpair, Service, Storage, Value};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+/// Page size of `HSCAN` without `COUNT`, as in Redis.
+const DEFAULT_SCAN_COUNT: u32 = 10;
+
+pub(crate) enum Reply {
+    Frame(RespFrame),
+    /// Switch to protocol `version`, then send the frame.
+    Hello(u8, RespFrame),
+    /// Send the frame, then close the connection.
+    Quit(RespFrame),
+}
+
+/// Run a Redis command: hashes map to tables, fields to keys.
+pub(crate) fn execute<Store: Storage>(
+    service: &Service<Store>,
+    args: Vec<Bytes>,
+    version: u8,
+) -> Reply {
+    let mut args = Args::new(args);
+    let res = match args.name.as_str() {
+        "hello" => hello(&mut args, version),
+        "quit" => return Reply::Quit(RespFrame::ok()),
+        _ => run(service, &mut args).map(Reply::Frame),
+    };
+    res.unwrap_or_else(|e| Reply::Frame(RespFrame::error(format!("ERR {}", e))))
+}
+
+fn hello(args: &mut Args, version: u8) -> Result<Reply, KvError> {
+    let version = match args.len() {
+        0 => version,
+        // AUTH and SETNAME are accepted and ignored
+        _ => match args.integer()? {
+            v @ (2 | 3) => v as u8,
+            _ => {
+                let frame = RespFrame::error("NOPROTO unsupported protocol version");
+                return Ok(Reply::Frame(frame));
+            }
+        },
+    };
+    let info = vec![
+        ("server".into(), "kv-server".into()),
+        ("version".into(), env!("CARGO_PKG_VERSION").into()),
+        ("proto".into(), RespFrame::Integer(version as i64)),
+        ("mode".into(), "standalone".into()),
+        ("role".into(), "master".into()),
+        ("modules".into(), RespFrame::Array(vec![])),
+    ];
+    Ok(Reply::Hello(version, RespFrame::Map(info)))
+}
+
+fn run<Store: Storage>(service: &Service<Store>, args: &mut Args) -> Result<RespFrame, KvError> {
+    let call = |cmd| service.execute(cmd).into_result();
+
+    let frame = match args.name.as_str() {
+        "ping" => match args.len() {
+            0 => RespFrame::Simple("PONG".into()),
+            _ => {
+                args.exact(1)?;
+                RespFrame::Bulk(args.bytes())
+            }
+        },
+        "echo" => {
+            args.exact(1)?;
+            RespFrame::Bulk(args.bytes())
+        }
+        "select" => {
+            args.exact(1)?;
+            match args.integer()? {
+                0 => RespFrame::ok(),
+                _ => return Err(KvError::InvalidCommand("DB index is out of range".into())),
+            }
+        }
+        // client libraries probe these on connect
+        "command" => RespFrame::Array(vec![]),
+        "client" => RespFrame::ok(),
+
+        "hget" => {
+            args.exact(2)?;
+            match call(CommandRequest::new_hget(args.string()?, args.string()?)) {
+                Ok(res) => value_frame(first_value(res)),
+                Err(KvError::NotFound(_, _)) => RespFrame::Null,
+                Err(e) => return Err(e),
+            }
+        }
+        "hmget" => {
+            args.at_least(2)?;
+            let table = args.string()?;
+            let keys = args.strings()?;
+            // missing keys are left out of the pairs, so the status is no use here
+            let res = service.execute(CommandRequest::new_hmget(table, keys.clone()));
+            let found: HashMap<_, _> = res.pairs.into_iter().map(|p| (p.key, p.value)).collect();
+            let values = keys.iter().map(|k| match found.get(k) {
+                Some(Some(v)) => value_frame(v.clone()),
+                _ => RespFrame::Null,
+            });
+            RespFrame::Array(values.collect())
+        }
+        "hgetall" | "hkeys" | "hvals" => {
+            args.exact(1)?;
+            let pairs = call(CommandRequest::new_hgetall(args.string()?))?.pairs;
+            let frames = pairs
+                .into_iter()
+                .map(|p| (p.key.as_str().into(), pair_value(p)));
+            match args.name.as_str() {
+                "hkeys" => RespFrame::Array(frames.map(|(k, _)| k).collect()),
+                "hvals" => RespFrame::Array(frames.map(|(_, v)| v).collect()),
+                _ => RespFrame::Map(frames.collect()),
+            }
+        }
+        "hset" | "hmset" => {
+            if args.len() < 3 || args.len() % 2 != 1 {
+                return Err(args.arity_error());
+            }
+            let table = args.string()?;
+            let pairs = args.pairs()?;
+            let res = call(CommandRequest::new_hmset(table, pairs))?;
+            match args.name.as_str() {
+                // previous values come back, empty for new fields
+                "hset" => {
+                    let created = res.pairs.into_iter().filter(pair_is_empty);
+                    RespFrame::Integer(created.count() as i64)
+                }
+                _ => RespFrame::ok(),
+            }
+        }
+        "hsetnx" => {
+            args.exact(3)?;
+            let (table, key) = (args.string()?, args.string()?);
+            let value = to_value(args.bytes());
+            match call(CommandRequest::new_hcas(table, key, None, Some(value))) {
+                Ok(_) => RespFrame::Integer(1),
+                Err(KvError::CasConflict(_, _)) => RespFrame::Integer(0),
+                Err(e) => return Err(e),
+            }
+        }
+        "hdel" => {
+            args.at_least(2)?;
+            let res = call(CommandRequest::new_hmdel(args.string()?, args.strings()?))?;
+            let deleted = res.pairs.iter().filter(|p| p.value.is_some()).count();
+            RespFrame::Integer(deleted as i64)
+        }
+        "hexists" => {
+            args.exact(2)?;
+            let res = call(CommandRequest::new_hexist(args.string()?, args.string()?))?;
+            let exists: bool = first_value(res).try_into()?;
+            RespFrame::Integer(exists as i64)
+        }
+        "hlen" => {
+            args.exact(1)?;
+            let res = call(CommandRequest::new_table_len(args.string()?))?;
+            RespFrame::Integer(first_value(res).try_into()?)
+        }
+        "hincrby" => {
+            args.exact(3)?;
+            let (table, key) = (args.string()?, args.string()?);
+            let res = call(CommandRequest::new_hincrby(table, key, args.integer()?))?;
+            RespFrame::Integer(first_value(res).try_into()?)
+        }
+        "hincrbyfloat" => {
+            args.exact(3)?;
+            let (table, key) = (args.string()?, args.string()?);
+            let res = call(CommandRequest::new_hincrbyfloat(table, key, args.float()?))?;
+            value_frame(first_value(res))
+        }
+        "hscan" => hscan(service, args)?,
+
+        "keys" => {
+            args.exact(1)?;
+            let pattern = args.string()?;
+            let tables = list_tables(service)?.into_iter();
+            let tables = tables.filter(|t| glob_match(&pattern, t));
+            RespFrame::Array(tables.map(RespFrame::from).collect())
+        }
+        "dbsize" => {
+            args.exact(0)?;
+            RespFrame::Integer(list_tables(service)?.len() as i64)
+        }
+        "exists" => {
+            args.at_least(1)?;
+            let tables = list_tables(service)?;
+            let keys = args.strings()?;
+            RespFrame::Integer(keys.iter().filter(|k| tables.contains(k)).count() as i64)
+        }
+        "type" => {
+            args.exact(1)?;
+            let table = args.string()?;
+            match list_tables(service)?.contains(&table) {
+                true => RespFrame::Simple("hash".into()),
+                false => RespFrame::Simple("none".into()),
+            }
+        }
+        "del" | "unlink" => {
+            args.at_least(1)?;
+            let mut deleted = 0;
+            for table in args.strings()? {
+                let res = call(CommandRequest::new_drop_table(table))?;
+                deleted += bool::try_from(first_value(res))? as i64;
+            }
+            RespFrame::Integer(deleted)
+        }
+        "rename" => {
+            args.exact(2)?;
+            match call(CommandRequest::new_rename_table(
+                args.string()?,
+                args.string()?,
+            )) {
+                Ok(_) => RespFrame::ok(),
+                Err(KvError::TableNotFound(_)) => {
+                    return Err(KvError::InvalidCommand("no such key".into()))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        name => {
+            return Err(KvError::InvalidCommand(format!(
+                "unknown command '{}'",
+                name
+            )))
+        }
+    };
+    Ok(frame)
+}
+
+/// `HSCAN key cursor [MATCH pattern] [COUNT count] [NOVALUES]`. The cursor is
+/// the hex encoded last key of the previous page, `0` starts and ends a scan.
+fn hscan<Store: Storage>(service: &Service<Store>, args: &mut Args) -> Result<RespFrame, KvError> {
+    args.at_least(2)?;
+    let table = args.string()?;
+    let cursor = match args.string()?.as_str() {
+        "0" => String::new(),
+        cursor => hex::decode(cursor)
+            .ok()
+            .and_then(|key| String::from_utf8(key).ok())
+            .ok_or_else(|| KvError::InvalidCommand("invalid cursor".into()))?,
+    };
+
+    let (mut pattern, mut count, mut novalues) = (String::new(), DEFAULT_SCAN_COUNT, false);
+    while args.len() > 0 {
+        match args.string()?.to_lowercase().as_str() {
+            "match" if args.len() > 0 => pattern = args.string()?,
+            "count" if args.len() > 0 => match args.integer()? {
+                n @ 1..=0xffff_ffff => count = n as u32,
+                _ => return Err(KvError::InvalidCommand("syntax error".into())),
+            },
+            "novalues" => novalues = true,
+            _ => return Err(KvError::InvalidCommand("syntax error".into())),
+        }
+    }
+
+    let cmd = CommandRequest::new_hscan(table, cursor, count, String::new(), pattern);
+    let res = service.execute(cmd).into_result()?;
+    let cursor = match res.cursor.is_empty() {
+        true => "0".to_string(),
+        false => hex::encode(&res.cursor),
+    };
+    let mut items = Vec::new();
+    for pair in res.pairs {
+        items.push(RespFrame::from(pair.key.as_str()));
+        if !novalues {
+            items.push(pair_value(pair));
+        }
+    }
+    Ok(RespFrame::Array(vec![
+        cursor.into(),
+        RespFrame::Array(items),
+    ]))
+}
+
+fn list_tables<Store: Storage>(service: &Service<Store>) -> Result<Vec<String>, KvError> {
+    let res = service
+        .execute(CommandRequest::new_list_tables())
+        .into_result()?;
+    res.values.into_iter().map(String::try_from).collect()
+}
+
+fn first_value(res: CommandResponse) -> Value {
+    res.values.into_iter().next().unwrap_or_default()
+}
+
+fn pair_value(pair: Kvpair) -> RespFrame {
+    value_frame(pair.value.unwrap_or_default())
+}
+
+fn pair_is_empty(pair: &Kvpair) -> bool {
+    !matches!(&pair.value, Some(Value { value: Some(_) }))
+}
+
+/// Redis values are plain strings, so every value is sent as a bulk string.
+fn value_frame(v: Value) -> RespFrame {
+    match v.value {
+        Some(value::Value::String(s)) => s.into(),
+        Some(value::Value::Binary(b)) => RespFrame::Bulk(b),
+        Some(value::Value::Integer(i)) => i.to_string().into(),
+        Some(value::Value::Float(f)) => f.to_string().into(),
+        Some(value::Value::Bool(b)) => b.to_string().into(),
+        None => RespFrame::Null,
+    }
+}
+
+/// Numbers in their canonical form are stored as such, so `HINCRBY` works on
+/// them and they read back unchanged; anything else is a string, or binary
+/// when it isn't valid UTF-8.
+fn to_value(data: Bytes) -> Value {
+    let s = match std::str::from_utf8(&data) {
+        Ok(s) => s,
+        Err(_) => return data.into(),
+    };
+    match (s.parse::<i64>(), s.parse::<f64>()) {
+        (Ok(i), _) if i.to_string() == s => i.into(),
+        (_, Ok(f)) if f.is_finite() && f.to_string() == s => f.into(),
+        _ => s.into(),
+    }
+}
+
+/// Arguments of a command, consumed front to back once their count is checked.
+struct Args {
+    name: String,
+    args: std::vec::IntoIter<Bytes>,
+}
+
+impl Args {
+    fn new(args: Vec<Bytes>) -> Self {
+        let mut args = args.into_iter();
+        let name = args
+            .next()
+            .map(|name| String::from_utf8_lossy(&name).to_lowercase())
+            .unwrap_or_default();
+        Self { name, args }
+    }
+
+    fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    fn exact(&self, n: usize) -> Result<(), KvError> {
+        match self.len() == n {
+            true => Ok(()),
+            false => Err(self.arity_error()),
+        }
+    }
+
+    fn at_least(&self, n: usize) -> Result<(), KvError> {
+        match self.len() >= n {
+            true => Ok(()),
+            false => Err(self.arity_error()),
+        }
+    }
+
+    fn arity_error(&self) -> KvError {
+        KvError::InvalidCommand(format!(
+            "wrong number of arguments for '{}' command",
+            self.name
+        ))
+    }
+
+    fn bytes(&mut self) -> Bytes {
+        self.args.next().unwrap_or_default()
+    }
+
+    fn string(&mut self) -> Result<String, KvError> {
+        String::from_utf8(self.bytes().to_vec())
+            .map_err(|_| KvError::InvalidCommand("keys must be valid UTF-8".into()))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, KvError> {
+        (0..self.len()).map(|_| self.string()).collect()
+    }
+
+    fn pairs(&mut self) -> Result<Vec<Kvpair>, KvError> {
+        (0..self.len() / 2)
+            .map(|_| Ok(Kvpair::new(self.string()?, to_value(self.bytes()))))
+            .collect()
+    }
+
+    fn integer(&mut self) -> Result<i64, KvError> {
+        let err = || KvError::InvalidCommand("value is not an integer or out of range".into());
+        let s = self.string().map_err(|_| err())?;
+        s.parse().map_err(|_| err())
+    }
+
+    fn float(&mut self) -> Result<f64, KvError> {
+        let err = || KvError::InvalidCommand("value is not a valid float".into());
+        match self.string().map_err(|_| err())?.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(err()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemTable, ServiceInner};
+
+    fn run_cmd(service: &Service, cmd: &str) -> RespFrame {
+        let args = cmd.split(' ').map(|s| Bytes::from(s.to_string())).collect();
+        match execute(service, args, 2) {
+            Reply::Frame(frame) | Reply::Hello(_, frame) | Reply::Quit(frame) => frame,
+        }
+    }
+
+    fn bulks(items: &[&str]) -> RespFrame {
+        RespFrame::Array(items.iter().map(|s| (*s).into()).collect())
+    }
+
+    #[test]
+    fn hash_commands_should_work() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+
+        assert_eq!(
+            run_cmd(&service, "HSET t1 k1 v1 k2 2"),
+            RespFrame::Integer(2)
+        );
+        assert_eq!(run_cmd(&service, "hset t1 k1 v2"), RespFrame::Integer(0));
+        assert_eq!(run_cmd(&service, "HGET t1 k1"), "v2".into());
+        assert_eq!(run_cmd(&service, "HGET t1 k3"), RespFrame::Null);
+        assert_eq!(
+            run_cmd(&service, "HMGET t1 k3 k2"),
+            RespFrame::Array(vec![RespFrame::Null, "2".into()])
+        );
+        assert_eq!(run_cmd(&service, "HINCRBY t1 k2 3"), RespFrame::Integer(5));
+        assert_eq!(run_cmd(&service, "HINCRBYFLOAT t1 f 1.5"), "1.5".into());
+        assert_eq!(run_cmd(&service, "HEXISTS t1 k1"), RespFrame::Integer(1));
+        assert_eq!(run_cmd(&service, "HSETNX t1 k1 v3"), RespFrame::Integer(0));
+        assert_eq!(run_cmd(&service, "HDEL t1 k1 k3"), RespFrame::Integer(1));
+        assert_eq!(run_cmd(&service, "HLEN t1"), RespFrame::Integer(2));
+        let mut pairs = match run_cmd(&service, "HGETALL t1") {
+            RespFrame::Map(pairs) => pairs,
+            frame => panic!("expected a map, got {:?}", frame),
+        };
+        pairs.sort_by_key(|(k, _)| format!("{:?}", k));
+        assert_eq!(
+            pairs,
+            vec![("f".into(), "1.5".into()), ("k2".into(), "5".into())]
+        );
+        assert_eq!(
+            run_cmd(&service, "HMGET t2 k1"),
+            RespFrame::Array(vec![RespFrame::Null])
+        );
+    }
+
+    #[test]
+    fn table_commands_should_work() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+
+        run_cmd(&service, "HMSET t1 a 1 b 2 c 3");
+        run_cmd(&service, "HSET t2 a 1");
+        assert_eq!(run_cmd(&service, "KEYS t*"), bulks(&["t1", "t2"]));
+        assert_eq!(run_cmd(&service, "EXISTS t1 t3"), RespFrame::Integer(1));
+        assert_eq!(
+            run_cmd(&service, "TYPE t1"),
+            RespFrame::Simple("hash".into())
+        );
+
+        let page = run_cmd(&service, "HSCAN t1 0 COUNT 2");
+        let cursor = hex::encode("b");
+        let expected = RespFrame::Array(vec![cursor.as_str().into(), bulks(&["a", "1", "b", "2"])]);
+        assert_eq!(page, expected);
+        let page = run_cmd(&service, &format!("HSCAN t1 {} COUNT 2 NOVALUES", cursor));
+        assert_eq!(page, RespFrame::Array(vec!["0".into(), bulks(&["c"])]));
+
+        assert_eq!(run_cmd(&service, "RENAME t2 t3"), RespFrame::ok());
+        assert_eq!(run_cmd(&service, "DEL t1 t3 t4"), RespFrame::Integer(2));
+        assert_eq!(run_cmd(&service, "DBSIZE"), RespFrame::Integer(0));
+    }
+
+    #[test]
+    fn invalid_commands_should_reply_errors() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+
+        run_cmd(&service, "HSET t1 k1 v1");
+        let errors = [
+            "HGET t1",
+            "HSET t1 k1",
+            "HINCRBY t1 k1 1",
+            "HINCRBY t1 k1 x",
+            "RENAME t2 t3",
+            "FOO bar",
+        ];
+        for cmd in errors {
+            assert!(
+                matches!(run_cmd(&service, cmd), RespFrame::Error(e) if e.starts_with("ERR ")),
+                "{}",
+                cmd
+            );
+        }
+    }
+
+    #[test]
+    fn hello_should_negotiate_protocol() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+
+        let args = vec![Bytes::from("HELLO"), Bytes::from("3")];
+        assert!(matches!(
+            execute(&service, args, 2),
+            Reply::Hello(3, RespFrame::Map(_))
+        ));
+        let args = vec![Bytes::from("HELLO"), Bytes::from("4")];
+        assert!(matches!(
+            execute(&service, args, 2),
+            Reply::Frame(RespFrame::Error(e)) if e.starts_with("NOPROTO")
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::KvError;
+use bytes::{BufMut, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Most elements accepted in a single command.
+const MAX_ARGS: usize = 1024 * 1024;
+
+/// A reply in the RESP protocol, encoded according to the negotiated version.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RespFrame {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Bytes),
+    Null,
+    Array(Vec<RespFrame>),
+    /// A RESP3 map, sent as a flat array of keys and values to RESP2 clients.
+    Map(Vec<(RespFrame, RespFrame)>),
+}
+
+impl RespFrame {
+    pub fn ok() -> Self {
+        Self::Simple("OK".into())
+    }
+
+    pub fn error(msg: impl AsRef<str>) -> Self {
+        // a newline would end the reply early
+        Self::Error(msg.as_ref().trim_end().replace(['\r', '\n'], " "))
+    }
+}
+
+impl From<&str> for RespFrame {
+    fn from(s: &str) -> Self {
+        Self::Bulk(Bytes::copy_from_slice(s.as_bytes()))
+    }
+}
+
+impl From<String> for RespFrame {
+    fn from(s: String) -> Self {
+        Self::Bulk(s.into())
+    }
+}
+
+/// Codec reading commands, each an array of bulk strings or an inline line,
+/// and writing `RespFrame` replies.
+#[derive(Clone, Debug)]
+pub struct RespCodec {
+    /// Protocol version replies are encoded in, 2 until the client says `HELLO 3`.
+    pub version: u8,
+    max_frame_size: usize,
+}
+
+impl RespCodec {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self {
+            version: 2,
+            max_frame_size,
+        }
+    }
+
+    fn check_size(&self, len: usize) -> Result<(), KvError> {
+        match len > self.max_frame_size {
+            true => Err(KvError::FrameTooLarge(len, self.max_frame_size)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = Vec<Bytes>;
+    type Error = KvError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let parsed = match src.first() {
+                None => return Ok(None),
+                Some(b'*') => parse_multibulk(src, self.max_frame_size)?,
+                Some(_) => parse_inline(src),
+            };
+            let (ranges, len) = match parsed {
+                Some(parsed) => parsed,
+                None => {
+                    self.check_size(src.len())?;
+                    return Ok(None);
+                }
+            };
+
+            let frame = src.split_to(len).freeze();
+            // empty lines are skipped, as Redis does
+            if !ranges.is_empty() {
+                return Ok(Some(
+                    ranges.into_iter().map(|(s, e)| frame.slice(s..e)).collect(),
+                ));
+            }
+        }
+    }
+}
+
+impl Encoder<RespFrame> for RespCodec {
+    type Error = KvError;
+
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        encode(&item, self.version, dst);
+        Ok(())
+    }
+}
+
+fn encode(frame: &RespFrame, version: u8, dst: &mut BytesMut) {
+    match frame {
+        RespFrame::Simple(s) => put_line(dst, b'+', s.as_bytes()),
+        RespFrame::Error(s) => put_line(dst, b'-', s.as_bytes()),
+        RespFrame::Integer(n) => put_line(dst, b':', n.to_string().as_bytes()),
+        RespFrame::Bulk(b) => {
+            put_line(dst, b'$', b.len().to_string().as_bytes());
+            dst.put_slice(b);
+            dst.put_slice(b"\r\n");
+        }
+        RespFrame::Null if version >= 3 => dst.put_slice(b"_\r\n"),
+        RespFrame::Null => dst.put_slice(b"$-1\r\n"),
+        RespFrame::Array(items) => {
+            put_line(dst, b'*', items.len().to_string().as_bytes());
+            items.iter().for_each(|item| encode(item, version, dst));
+        }
+        RespFrame::Map(pairs) => {
+            match version >= 3 {
+                true => put_line(dst, b'%', pairs.len().to_string().as_bytes()),
+                false => put_line(dst, b'*', (pairs.len() * 2).to_string().as_bytes()),
+            }
+            for (k, v) in pairs {
+                encode(k, version, dst);
+                encode(v, version, dst);
+            }
+        }
+    }
+}
+
+fn put_line(dst: &mut BytesMut, prefix: u8, line: &[u8]) {
+    dst.put_u8(prefix);
+    dst.put_slice(line);
+    dst.put_slice(b"\r\n");
+}
+
+/// Arguments as ranges into `src`, plus the length of the command, or `None` if incomplete.
+type Parsed = Option<(Vec<(usize, usize)>, usize)>;
+
+/// `*<n>\r\n` followed by n `$<len>\r\n<data>\r\n` elements.
+fn parse_multibulk(src: &[u8], max_frame_size: usize) -> Result<Parsed, KvError> {
+    let (count, mut pos) = match read_line(src, 0) {
+        Some((line, next)) => (parse_len(&line[1..], "multibulk length")?, next),
+        None => return Ok(None),
+    };
+    if count > MAX_ARGS {
+        return Err(protocol_error("invalid multibulk length"));
+    }
+
+    let mut ranges = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (line, next) = match read_line(src, pos) {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        if line.first() != Some(&b'$') {
+            return Err(protocol_error(format!(
+                "expected '$', got '{}'",
+                String::from_utf8_lossy(&line[..1.min(line.len())])
+            )));
+        }
+        let len = parse_len(&line[1..], "bulk length")?;
+        if len > max_frame_size {
+            return Err(KvError::FrameTooLarge(len, max_frame_size));
+        }
+        if src.len() < next + len + 2 {
+            return Ok(None);
+        }
+        if &src[next + len..next + len + 2] != b"\r\n" {
+            return Err(protocol_error("bulk string not terminated by CRLF"));
+        }
+        ranges.push((next, next + len));
+        pos = next + len + 2;
+    }
+    Ok(Some((ranges, pos)))
+}
+
+/// A line of whitespace separated arguments, as typed into telnet.
+fn parse_inline(src: &[u8]) -> Parsed {
+    let end = src.iter().position(|b| *b == b'\n')?;
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (i, b) in src[..end].iter().enumerate() {
+        match (b.is_ascii_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                ranges.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((s, end));
+    }
+    Some((ranges, end + 1))
+}
+
+/// The line starting at `pos` without its CRLF, and the position after it.
+fn read_line(src: &[u8], pos: usize) -> Option<(&[u8], usize)> {
+    let end = src[pos..].windows(2).position(|w| w == b"\r\n")? + pos;
+    Some((&src[pos..end], end + 2))
+}
+
+fn parse_len(s: &[u8], what: &str) -> Result<usize, KvError> {
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| protocol_error(format!("invalid {}", what)))
+}
+
+fn protocol_error(msg: impl Into<String>) -> KvError {
+    KvError::FrameError(format!("Protocol error: {}", msg.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(data: &[u8]) -> Result<Vec<Vec<Bytes>>, KvError> {
+        let mut codec = RespCodec::new(1024);
+        let mut buf = BytesMut::from(data);
+        let mut commands = Vec::new();
+        while let Some(cmd) = codec.decode(&mut buf)? {
+            commands.push(cmd);
+        }
+        Ok(commands)
+    }
+
+    fn encode_with(frame: RespFrame, version: u8) -> BytesMut {
+        let mut codec = RespCodec::new(1024);
+        codec.version = version;
+        let mut buf = BytesMut::new();
+        codec.encode(frame, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn resp_codec_should_decode_pipelined_commands() {
+        let data = b"*3\r\n$4\r\nHGET\r\n$2\r\nt1\r\n$5\r\nk\r\n 1\r\nPING\r\n\r\nHGET t1  k2\r\n*1\r\n$4\r\nPI";
+        let commands = decode_all(data).unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                vec![
+                    Bytes::from("HGET"),
+                    Bytes::from("t1"),
+                    Bytes::from("k\r\n 1")
+                ],
+                vec![Bytes::from("PING")],
+                vec![Bytes::from("HGET"), Bytes::from("t1"), Bytes::from("k2")],
+            ]
+        );
+    }
+
+    #[test]
+    fn resp_codec_should_reject_invalid_or_oversized_commands() {
+        assert!(matches!(
+            decode_all(b"*1\r\n:1\r\n"),
+            Err(KvError::FrameError(_))
+        ));
+        assert!(matches!(decode_all(b"*x\r\n"), Err(KvError::FrameError(_))));
+        assert!(matches!(
+            decode_all(b"*1\r\n$2048\r\n"),
+            Err(KvError::FrameTooLarge(2048, 1024))
+        ));
+        assert!(matches!(
+            decode_all(&[b'x'; 2048]),
+            Err(KvError::FrameTooLarge(2048, 1024))
+        ));
+    }
+
+    #[test]
+    fn resp_codec_should_encode_by_version() {
+        let frame = RespFrame::Map(vec![("k1".into(), RespFrame::Null)]);
+        assert_eq!(
+            &encode_with(frame.clone(), 2)[..],
+            b"*2\r\n$2\r\nk1\r\n$-1\r\n"
+        );
+        assert_eq!(&encode_with(frame, 3)[..], b"%1\r\n$2\r\nk1\r\n_\r\n");
+
+        let frame = RespFrame::Array(vec![RespFrame::Integer(-1), RespFrame::ok()]);
+        assert_eq!(&encode_with(frame, 2)[..], b"*2\r\n:-1\r\n+OK\r\n");
+        let frame = RespFrame::error("ERR bad\nthing\n");
+        assert_eq!(&encode_with(frame, 2)[..], b"-ERR bad thing\r\n");
+    }
+}
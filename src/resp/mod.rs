@@ -0,0 +1,109 @@
+mod command;
+mod frame;
+
+pub use frame::{RespCodec, RespFrame};
+
+use crate::{KvError, Service, Storage};
+use command::Reply;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+
+/// Server side of a connection speaking the Redis protocol, RESP2 or RESP3.
+pub struct RespServerStream<S, Store> {
+    inner: Framed<S, RespCodec>,
+    service: Service<Store>,
+}
+
+impl<S, Store> RespServerStream<S, Store>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    Store: Storage,
+{
+    pub fn new(stream: S, service: Service<Store>, max_frame_size: usize) -> Self {
+        Self {
+            inner: Framed::new(stream, RespCodec::new(max_frame_size)),
+            service,
+        }
+    }
+
+    /// Answer commands in order until the client quits or hangs up.
+    pub async fn process(mut self) -> Result<(), KvError> {
+        while let Some(args) = self.inner.next().await {
+            let args = match args {
+                Ok(args) => args,
+                // the rest of the stream can't be parsed reliably
+                Err(e) => {
+                    self.inner
+                        .send(RespFrame::error(format!("ERR {}", e)))
+                        .await?;
+                    return Err(e);
+                }
+            };
+
+            let version = self.inner.codec().version;
+            match command::execute(&self.service, args, version) {
+                Reply::Frame(frame) => self.inner.send(frame).await?,
+                Reply::Hello(version, frame) => {
+                    self.inner.codec_mut().version = version;
+                    self.inner.send(frame).await?;
+                }
+                Reply::Quit(frame) => {
+                    self.inner.send(frame).await?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemTable, ServiceInner};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    fn start_server(max_frame_size: usize) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(4096);
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        tokio::spawn(RespServerStream::new(server, service, max_frame_size).process());
+        client
+    }
+
+    async fn roundtrip(client: &mut DuplexStream, request: &[u8], expected: &[u8]) {
+        client.write_all(request).await.unwrap();
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            String::from_utf8_lossy(expected)
+        );
+    }
+
+    #[tokio::test]
+    async fn resp_stream_should_answer_pipelined_commands() {
+        let mut client = start_server(1024);
+
+        let request =
+            b"*4\r\n$4\r\nHSET\r\n$2\r\nt1\r\n$2\r\nk1\r\n$2\r\nv1\r\nHGET t1 k1\r\nHGET t1 k2\r\n";
+        roundtrip(&mut client, request, b":1\r\n$2\r\nv1\r\n$-1\r\n").await;
+
+        let expected = b"%6\r\n$6\r\nserver\r\n$9\r\nkv-server\r\n";
+        roundtrip(&mut client, b"HELLO 3\r\n", expected).await;
+        let mut rest = Vec::new();
+        client.write_all(b"HGET t1 k2\r\nQUIT\r\n").await.unwrap();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.ends_with(b"_\r\n+OK\r\n"));
+    }
+
+    #[tokio::test]
+    async fn resp_stream_should_close_on_protocol_errors() {
+        let mut client = start_server(1024);
+
+        let mut reply = Vec::new();
+        client.write_all(b"*1\r\n$2048\r\n").await.unwrap();
+        client.read_to_end(&mut reply).await.unwrap();
+        assert!(reply.starts_with(b"-ERR Frame of 2048 bytes"));
+    }
+}
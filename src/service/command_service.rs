@@ -1,37 +1,71 @@
+use crate::command_request::RequestData;
 use crate::error::KvError;
 use crate::*;
+use std::ops::Bound;
+use std::time::Duration;
+
+/// Page size used by `Hscan` when the request does not set a limit.
+const DEFAULT_SCAN_LIMIT: usize = 100;
 
 impl CommandService for Hset {
     fn execute(self, store: &dyn Storage) -> CommandResponse {
-        match self.pair {
-            None => Value::default().into(),
-            Some(v) => match store.set(&self.table, v.key, v.value.unwrap_or_default()) {
-                Ok(Some(v)) => v.into(),
-                Ok(None) => Value::default().into(),
-                Err(e) => e.into(),
-            },
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hset {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
+        let pair = match self.pair {
+            None => return (Value::default().into(), vec![]),
+            Some(pair) => pair,
+        };
+        let value = pair.value.unwrap_or_default();
+        match set_with_ttl(
+            store,
+            &self.table,
+            pair.key.clone(),
+            value.clone(),
+            self.ttl_ms,
+        ) {
+            Ok(old) => {
+                let change = ChangeEvent::set(&self.table, pair.key, old.clone(), value);
+                (old.unwrap_or_default().into(), vec![change])
+            }
+            Err(e) => (e.into(), vec![]),
         }
     }
 }
 
 impl CommandService for Hmset {
     fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hmset {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
         let mut v1 = Vec::new();
         let mut v2 = Vec::new();
+        let mut changes = Vec::new();
 
         for pair in self.pairs {
-            match store.set(
+            let value = pair.value.unwrap_or_default();
+            match set_with_ttl(
+                store,
                 &self.table,
                 pair.key.clone(),
-                pair.value.unwrap_or_default(),
+                value.clone(),
+                self.ttl_ms,
             ) {
-                Ok(Some(v)) => v1.push((pair.key, v)),
-                Ok(None) => v1.push((pair.key, Value::default())),
+                Ok(old) => {
+                    v1.push((pair.key.clone(), old.clone().unwrap_or_default()));
+                    changes.push(ChangeEvent::set(&self.table, pair.key, old, value));
+                }
                 Err(e) => v2.push((pair.key, e)),
             }
         }
 
-        (v1, v2).into()
+        ((v1, v2).into(), changes)
     }
 }
 
@@ -73,34 +107,49 @@ impl CommandService for Hgetall {
 
 impl CommandService for Hdel {
     fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hdel {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
         match store.del(&self.table, &self.key) {
-            Ok(Some(_)) => {
+            Ok(Some(old)) => {
+                let change = ChangeEvent::del(&self.table, self.key.clone(), old);
                 let pair = Kvpair {
                     key: self.key,
                     value: Some(1.into()),
                 };
-                pair.into()
+                (pair.into(), vec![change])
             }
             Ok(None) => {
                 let pair = Kvpair {
                     key: self.key,
                     value: None,
                 };
-                pair.into()
+                (pair.into(), vec![])
             }
-            Err(e) => e.into(),
+            Err(e) => (e.into(), vec![]),
         }
     }
 }
 
 impl CommandService for Hmdel {
     fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hmdel {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
         let mut v1 = Vec::new();
         let mut v2 = Vec::new();
+        let mut changes = Vec::new();
 
         for key in self.keys {
             match store.del(&self.table, &key) {
-                Ok(Some(_)) => {
+                Ok(Some(old)) => {
+                    changes.push(ChangeEvent::del(&self.table, key.clone(), old));
                     let pair = Kvpair {
                         key,
                         value: Some(1.into()),
@@ -115,7 +164,7 @@ impl CommandService for Hmdel {
             }
         }
 
-        (v1, v2).into()
+        ((v1, v2).into(), changes)
     }
 }
 
@@ -144,6 +193,323 @@ impl CommandService for Hmexist {
     }
 }
 
+impl CommandService for Expire {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.expire(&self.table, &self.key, Duration::from_millis(self.ttl_ms)) {
+            Ok(true) => true.into(),
+            Ok(false) => KvError::NotFound(self.table, self.key).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Ttl {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.contains(&self.table, &self.key) {
+            Ok(true) => match store.ttl(&self.table, &self.key) {
+                Ok(Some(ttl)) => Value::from(ttl.as_millis() as i64).into(),
+                Ok(None) => Value::from(-1).into(),
+                Err(e) => e.into(),
+            },
+            Ok(false) => KvError::NotFound(self.table, self.key).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Persist {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.contains(&self.table, &self.key) {
+            Ok(true) => match store.persist(&self.table, &self.key) {
+                Ok(b) => b.into(),
+                Err(e) => e.into(),
+            },
+            Ok(false) => KvError::NotFound(self.table, self.key).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Hincrby {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hincrby {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
+        incr(store, &self.table, self.key, self.delta.into())
+    }
+}
+
+impl CommandService for Hincrbyfloat {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hincrbyfloat {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
+        incr(store, &self.table, self.key, self.delta.into())
+    }
+}
+
+impl CommandService for Hcas {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Hcas {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
+        let (expected, value) = (self.expected.clone(), self.value.clone());
+        match store.compare_and_swap(&self.table, &self.key, self.expected, self.value) {
+            Ok(Ok(())) => {
+                // the old value is the expected one, nothing changed if both are absent
+                let change = match (expected, value) {
+                    (old, Some(new)) => Some(ChangeEvent::set(&self.table, self.key, old, new)),
+                    (Some(old), None) => Some(ChangeEvent::del(&self.table, self.key, old)),
+                    (None, None) => None,
+                };
+                (true.into(), change.into_iter().collect())
+            }
+            Ok(Err(current)) => {
+                let mut res: CommandResponse = KvError::CasConflict(self.table, self.key).into();
+                res.values = vec![current.unwrap_or_default()];
+                (res, vec![])
+            }
+            Err(e) => (e.into(), vec![]),
+        }
+    }
+}
+
+impl CommandService for Hscan {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        let limit = match self.limit {
+            0 => DEFAULT_SCAN_LIMIT,
+            n => n as usize,
+        };
+        match store.scan(
+            &self.table,
+            &self.cursor,
+            limit,
+            &self.prefix,
+            &self.pattern,
+        ) {
+            Ok(page) => page.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Hrange {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        let start = bound(&self.start, self.start_inclusive);
+        let end = bound(&self.end, self.end_inclusive);
+        let limit = match self.limit {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        match store.range(&self.table, start, end, self.reverse, limit) {
+            Ok(v) => v.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for ListTables {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.tables() {
+            Ok(v) => v.into_iter().map(Value::from).collect::<Vec<_>>().into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for TableLen {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.table_len(&self.table) {
+            Ok(n) => Value::from(n as i64).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for DropTable {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.drop_table(&self.table) {
+            Ok(b) => b.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for RenameTable {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match store.rename_table(&self.from, &self.to) {
+            Ok(()) => true.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Backup {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match backup(store, &self.path) {
+            Ok(n) => Value::from(n as i64).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandService for Restore {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        match restore(store, &self.path) {
+            Ok(n) => Value::from(n as i64).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+/// An empty key leaves that side of a range unbounded.
+fn bound(key: &str, inclusive: bool) -> Bound<&str> {
+    match (key.is_empty(), inclusive) {
+        (true, _) => Bound::Unbounded,
+        (false, true) => Bound::Included(key),
+        (false, false) => Bound::Excluded(key),
+    }
+}
+
+impl CommandService for Transaction {
+    fn execute(self, store: &dyn Storage) -> CommandResponse {
+        self.apply(store).0
+    }
+}
+
+impl MutationService for Transaction {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>) {
+        let mut tables = Vec::new();
+        for (i, cmd) in self.commands.iter().enumerate() {
+            match cmd.request_data.as_ref().and_then(transaction_table) {
+                Some(table) => tables.push(table.to_string()),
+                None => {
+                    let msg = format!("Command {} cannot run in a transaction", i);
+                    return (KvError::InvalidCommand(msg).into(), vec![]);
+                }
+            }
+        }
+        tables.sort();
+        tables.dedup();
+
+        let mut results = Vec::new();
+        let mut changes = Vec::new();
+        let res = store.transaction(&tables, &mut |tx| {
+            // the store may retry, so start over every time
+            results.clear();
+            changes.clear();
+            for (i, cmd) in self.commands.iter().enumerate() {
+                let (res, mut cmd_changes) = apply_in_transaction(cmd.clone(), tx);
+                if aborts(cmd, &res) {
+                    return Err(KvError::TransactionAborted(i, res.message));
+                }
+                results.push(res);
+                changes.append(&mut cmd_changes);
+            }
+            Ok(())
+        });
+
+        match res {
+            Ok(()) => (results.into(), changes),
+            Err(e) => (e.into(), vec![]),
+        }
+    }
+}
+
+/// The table of a command that may run in a transaction.
+fn transaction_table(data: &RequestData) -> Option<&str> {
+    let table = match data {
+        RequestData::Hget(param) => &param.table,
+        RequestData::Hmget(param) => &param.table,
+        RequestData::Hset(param) => &param.table,
+        RequestData::Hmset(param) => &param.table,
+        RequestData::Hdel(param) => &param.table,
+        RequestData::Hmdel(param) => &param.table,
+        RequestData::Hexist(param) => &param.table,
+        RequestData::Hmexist(param) => &param.table,
+        RequestData::Expire(param) => &param.table,
+        RequestData::Ttl(param) => &param.table,
+        RequestData::Persist(param) => &param.table,
+        RequestData::Hincrby(param) => &param.table,
+        RequestData::Hincrbyfloat(param) => &param.table,
+        RequestData::Hcas(param) => &param.table,
+        _ => return None,
+    };
+    Some(table)
+}
+
+/// Whether a response aborts the transaction: a read never does, a write does when it
+/// failed as a whole or for some of its keys.
+fn aborts(cmd: &CommandRequest, res: &CommandResponse) -> bool {
+    let read = matches!(
+        cmd.request_data,
+        Some(
+            RequestData::Hget(_)
+                | RequestData::Hmget(_)
+                | RequestData::Hexist(_)
+                | RequestData::Hmexist(_)
+                | RequestData::Ttl(_)
+        )
+    );
+    let failed = !(200..300).contains(&res.status) || !res.message.is_empty();
+    failed && !read
+}
+
+fn apply_in_transaction(
+    cmd: CommandRequest,
+    tx: &dyn Storage,
+) -> (CommandResponse, Vec<ChangeEvent>) {
+    match cmd.request_data {
+        Some(RequestData::Hset(param)) => param.apply(tx),
+        Some(RequestData::Hmset(param)) => param.apply(tx),
+        Some(RequestData::Hdel(param)) => param.apply(tx),
+        Some(RequestData::Hmdel(param)) => param.apply(tx),
+        Some(RequestData::Hincrby(param)) => param.apply(tx),
+        Some(RequestData::Hincrbyfloat(param)) => param.apply(tx),
+        Some(RequestData::Hcas(param)) => param.apply(tx),
+        _ => (dispatch(cmd, tx), vec![]),
+    }
+}
+
+/// Add `delta` to the key. The store only returns the sum, so the change has no old value.
+fn incr(
+    store: &dyn Storage,
+    table: &str,
+    key: String,
+    delta: Value,
+) -> (CommandResponse, Vec<ChangeEvent>) {
+    match store.incr(table, &key, delta) {
+        Ok(v) => (
+            v.clone().into(),
+            vec![ChangeEvent::set(table, key, None, v)],
+        ),
+        Err(e) => (e.into(), vec![]),
+    }
+}
+
+/// Set the value and, when `ttl_ms` is non-zero, the expiry of the key.
+fn set_with_ttl(
+    store: &dyn Storage,
+    table: &str,
+    key: String,
+    value: Value,
+    ttl_ms: u64,
+) -> Result<Option<Value>, KvError> {
+    match ttl_ms {
+        0 => store.set(table, key, value),
+        ms => store.set_with_ttl(table, key, value, Duration::from_millis(ms)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,6 +723,348 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hset_with_ttl_should_expire() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hset_with_ttl("t1", "k1", "v1".into(), 10);
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[Value::default()], &[]);
+
+        let cmd = CommandRequest::new_hget("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &["v1".into()], &[]);
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        let cmd = CommandRequest::new_hget("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 404, "Not found");
+    }
+
+    #[test]
+    fn hmset_with_ttl_should_expire() {
+        let store = MemTable::new();
+
+        let pairs = vec![Kvpair::new("k1", 1.into()), Kvpair::new("k2", 2.into())];
+        let cmd = CommandRequest::new_hmset_with_ttl("t1", pairs, 10);
+        dispatch(cmd, &store);
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        let cmd = CommandRequest::new_hgetall("t1");
+        let res = dispatch(cmd, &store);
+        assert_eq!(res.pairs, vec![]);
+    }
+
+    #[test]
+    fn expire_ttl_persist_should_work() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hset("t1", "k1", 1.into());
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_ttl("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[(-1).into()], &[]);
+
+        let cmd = CommandRequest::new_expire("t1", "k1", 10_000);
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_ttl("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_eq!(res.status, 200);
+        assert!(matches!(
+            res.values[0].value,
+            Some(value::Value::Integer(ttl)) if ttl > 0 && ttl <= 10_000
+        ));
+
+        let cmd = CommandRequest::new_persist("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_ttl("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[(-1).into()], &[]);
+    }
+
+    #[test]
+    fn expire_ttl_persist_with_non_exist_key_should_return_404() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_expire("t1", "k1", 10);
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 404, "Not found");
+
+        let cmd = CommandRequest::new_ttl("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 404, "Not found");
+
+        let cmd = CommandRequest::new_persist("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 404, "Not found");
+    }
+
+    #[test]
+    fn hincrby_should_work() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hincrby("t1", "k1", 10);
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[10.into()], &[]);
+
+        let cmd = CommandRequest::new_hincrby("t1", "k1", -3);
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[7.into()], &[]);
+
+        let cmd = CommandRequest::new_hget("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[7.into()], &[]);
+    }
+
+    #[test]
+    fn hincrbyfloat_should_work() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hset("t1", "k1", 1.into());
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_hincrbyfloat("t1", "k1", 0.5);
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[1.5.into()], &[]);
+    }
+
+    #[test]
+    fn hincrby_with_non_numeric_value_should_fail() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hset("t1", "k1", "v1".into());
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_hincrby("t1", "k1", 1);
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 500, "Cannot convert value");
+    }
+
+    #[test]
+    fn hcas_should_work() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hcas("t1", "k1", None, Some(1.into()));
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_hcas("t1", "k1", Some(1.into()), Some(2.into()));
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_hget("t1", "k1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[2.into()], &[]);
+    }
+
+    #[test]
+    fn hcas_conflict_should_return_current_value() {
+        let store = MemTable::new();
+
+        let cmd = CommandRequest::new_hset("t1", "k1", 1.into());
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_hcas("t1", "k1", Some(3.into()), Some(2.into()));
+        let res = dispatch(cmd, &store);
+        assert_eq!(res.status, 409);
+        assert!(res.message.contains("conflict"));
+        assert_eq!(res.values, vec![1.into()]);
+    }
+
+    #[test]
+    fn hscan_should_work() {
+        let store = MemTable::new();
+
+        let pairs = vec![
+            Kvpair::new("k1", 1.into()),
+            Kvpair::new("k2", 2.into()),
+            Kvpair::new("k3", 3.into()),
+        ];
+        let cmd = CommandRequest::new_hmset("t1", pairs);
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_hscan("t1", "", 2, "", "");
+        let res = dispatch(cmd, &store);
+        assert_eq!(res.cursor, "k2");
+        assert_res_ok(
+            res,
+            &[],
+            &[Kvpair::new("k1", 1.into()), Kvpair::new("k2", 2.into())],
+        );
+
+        let cmd = CommandRequest::new_hscan("t1", "k2", 2, "", "");
+        let res = dispatch(cmd, &store);
+        assert_eq!(res.cursor, "");
+        assert_res_ok(res, &[], &[Kvpair::new("k3", 3.into())]);
+
+        let cmd = CommandRequest::new_hscan("t1", "", 0, "", "*2");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[], &[Kvpair::new("k2", 2.into())]);
+    }
+
+    #[test]
+    fn hrange_should_work() {
+        let store = MemTable::new();
+
+        let pairs = vec![
+            Kvpair::new("k1", 1.into()),
+            Kvpair::new("k2", 2.into()),
+            Kvpair::new("k3", 3.into()),
+            Kvpair::new("k4", 4.into()),
+        ];
+        let cmd = CommandRequest::new_hmset("t1", pairs);
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_hrange("t1", "k2", "k4", false, 0);
+        let res = dispatch(cmd, &store);
+        assert_eq!(
+            res.pairs,
+            vec![Kvpair::new("k2", 2.into()), Kvpair::new("k3", 3.into())]
+        );
+
+        let cmd = CommandRequest::new_hrange("t1", "", "", true, 3);
+        let res = dispatch(cmd, &store);
+        assert_eq!(
+            res.pairs,
+            vec![
+                Kvpair::new("k4", 4.into()),
+                Kvpair::new("k3", 3.into()),
+                Kvpair::new("k2", 2.into())
+            ]
+        );
+    }
+
+    #[test]
+    fn table_management_should_work() {
+        let store = MemTable::new();
+
+        let pairs = vec![Kvpair::new("k1", 1.into()), Kvpair::new("k2", 2.into())];
+        let cmd = CommandRequest::new_hmset("t1", pairs);
+        dispatch(cmd, &store);
+        let cmd = CommandRequest::new_hset("t2", "k1", 1.into());
+        dispatch(cmd, &store);
+
+        let cmd = CommandRequest::new_list_tables();
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &["t1".into(), "t2".into()], &[]);
+
+        let cmd = CommandRequest::new_table_len("t1");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[2.into()], &[]);
+
+        let cmd = CommandRequest::new_rename_table("t1", "t2");
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 409, "already exists");
+
+        let cmd = CommandRequest::new_drop_table("t2");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_rename_table("t1", "t2");
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &[true.into()], &[]);
+
+        let cmd = CommandRequest::new_rename_table("t1", "t3");
+        let res = dispatch(cmd, &store);
+        assert_res_error(res, 404, "Table not found");
+
+        let cmd = CommandRequest::new_list_tables();
+        let res = dispatch(cmd, &store);
+        assert_res_ok(res, &["t2".into()], &[]);
+    }
+
+    #[test]
+    fn backup_and_restore_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kv.backup");
+        let path = path.to_str().unwrap();
+        let store = MemTable::new();
+        let pairs = vec![Kvpair::new("a", 1.into()), Kvpair::new("b", 2.into())];
+        dispatch(CommandRequest::new_hmset("t1", pairs), &store);
+
+        let res = dispatch(CommandRequest::new_backup(path), &store);
+        assert_res_ok(res, &[2.into()], &[]);
+        let res = dispatch(CommandRequest::new_restore(path), &store);
+        assert_res_error(res, 409, "already exists");
+
+        let store = MemTable::new();
+        let res = dispatch(CommandRequest::new_restore(path), &store);
+        assert_res_ok(res, &[2.into()], &[]);
+        let res = dispatch(CommandRequest::new_hget("t1", "b"), &store);
+        assert_res_ok(res, &[2.into()], &[]);
+    }
+
+    #[test]
+    fn transaction_should_apply_all_or_nothing() {
+        let store = MemTable::new();
+
+        let cmds = vec![
+            CommandRequest::new_hset("t1", "a", 1.into()),
+            CommandRequest::new_hincrby("t2", "n", 2),
+            CommandRequest::new_hget("t1", "a"),
+        ];
+        let res = dispatch(CommandRequest::new_transaction(cmds), &store);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.results.len(), 3);
+        assert_eq!(res.results[2].values, vec![1.into()]);
+
+        let cmds = vec![
+            CommandRequest::new_hset("t1", "a", 2.into()),
+            CommandRequest::new_hcas("t1", "a", Some(5.into()), Some(6.into())),
+        ];
+        let res = dispatch(CommandRequest::new_transaction(cmds), &store);
+        assert_res_error(res, 409, "Transaction aborted by command 1");
+        let res = dispatch(CommandRequest::new_hget("t1", "a"), &store);
+        assert_res_ok(res, &[1.into()], &[]);
+
+        let cmds = vec![CommandRequest::new_list_tables()];
+        let res = dispatch(CommandRequest::new_transaction(cmds), &store);
+        assert_res_error(res, 400, "Command 0 cannot run in a transaction");
+    }
+
+    #[test]
+    fn transaction_reads_should_not_abort() {
+        let store = MemTable::new();
+
+        let cmds = vec![
+            CommandRequest::new_hget("t1", "missing"),
+            CommandRequest::new_hmget("t1", vec!["missing".into()]),
+            CommandRequest::new_hset("t1", "a", 1.into()),
+        ];
+        let res = dispatch(CommandRequest::new_transaction(cmds), &store);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.results[0].status, 404);
+        assert_eq!(res.results.len(), 3);
+        let res = dispatch(CommandRequest::new_hget("t1", "a"), &store);
+        assert_res_ok(res, &[1.into()], &[]);
+    }
+
+    #[test]
+    fn transaction_writes_with_key_errors_should_abort() {
+        let hmset = CommandRequest::new_hmset("t1", vec![Kvpair::new("a", 1.into())]);
+        let ok = vec![("a".to_string(), Value::default())];
+        let partial: CommandResponse = (
+            ok.clone(),
+            vec![("b".into(), KvError::Internal("x".into()))],
+        )
+            .into();
+        assert_eq!(partial.status, 200);
+        assert!(aborts(&hmset, &partial));
+        assert!(!aborts(&hmset, &(ok, vec![]).into()));
+
+        let hget = CommandRequest::new_hget("t1", "a");
+        let missing: CommandResponse = KvError::NotFound("t1".into(), "a".into()).into();
+        assert!(!aborts(&hget, &missing));
+        assert!(aborts(&CommandRequest::new_hdel("t1", "a"), &missing));
+    }
+
     fn dispatch(cmd: CommandRequest, store: &dyn Storage) -> CommandResponse {
         match cmd.request_data.unwrap() {
             RequestData::Hget(v) => v.execute(store),
@@ -368,6 +1076,22 @@ mod tests {
             RequestData::Hmdel(v) => v.execute(store),
             RequestData::Hexist(v) => v.execute(store),
             RequestData::Hmexist(v) => v.execute(store),
+            RequestData::Expire(v) => v.execute(store),
+            RequestData::Ttl(v) => v.execute(store),
+            RequestData::Persist(v) => v.execute(store),
+            RequestData::Hincrby(v) => v.execute(store),
+            RequestData::Hincrbyfloat(v) => v.execute(store),
+            RequestData::Hcas(v) => v.execute(store),
+            RequestData::Hscan(v) => v.execute(store),
+            RequestData::Hrange(v) => v.execute(store),
+            RequestData::ListTables(v) => v.execute(store),
+            RequestData::TableLen(v) => v.execute(store),
+            RequestData::DropTable(v) => v.execute(store),
+            RequestData::RenameTable(v) => v.execute(store),
+            RequestData::Transaction(v) => v.execute(store),
+            RequestData::Backup(v) => v.execute(store),
+            RequestData::Restore(v) => v.execute(store),
+            _ => unreachable!("not a storage command"),
         }
     }
 }
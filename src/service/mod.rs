@@ -1,17 +1,30 @@
 use crate::command_request::RequestData;
 use crate::storage::Storage;
 use crate::*;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::debug;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
 
 mod command_service;
+mod topic;
+
+pub use topic::{Broker, Subscriber};
 
 pub trait CommandService {
     fn execute(self, store: &dyn Storage) -> CommandResponse;
 }
 
+/// Commands that change keys, reporting each change they made for watchers.
+pub trait MutationService {
+    fn apply(self, store: &dyn Storage) -> (CommandResponse, Vec<ChangeEvent>);
+}
+
 pub struct ServiceInner<Store> {
     store: Store,
+    broker: Arc<Broker>,
+    backup_dir: Option<PathBuf>,
     on_received: Vec<fn(&CommandRequest)>,
     on_executed: Vec<fn(&CommandResponse)>,
     on_before_send: Vec<fn(&mut CommandResponse)>,
@@ -22,6 +35,8 @@ impl<Store: Storage> ServiceInner<Store> {
     pub fn new(store: Store) -> Self {
         Self {
             store,
+            broker: Arc::default(),
+            backup_dir: None,
             on_received: Vec::new(),
             on_executed: Vec::new(),
             on_before_send: Vec::new(),
@@ -29,6 +44,12 @@ impl<Store: Storage> ServiceInner<Store> {
         }
     }
 
+    /// Allow backups and restores of files under `dir`.
+    pub fn backup_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.backup_dir = Some(dir.into());
+        self
+    }
+
     pub fn fn_received(mut self, f: fn(&CommandRequest)) -> Self {
         self.on_received.push(f);
         self
@@ -66,7 +87,22 @@ impl<Store: Storage> Service<Store> {
     pub fn execute(&self, cmd: CommandRequest) -> CommandResponse {
         debug!("Got Request: {:?}", cmd);
         self.inner.on_received.notify(&cmd);
-        let mut res = dispatch(cmd, &self.inner.store);
+        let id = cmd.id;
+        let mut res = match cmd.request_data {
+            Some(RequestData::Publish(param)) => self.inner.broker.publish(param),
+            Some(RequestData::Hset(param)) => self.mutate(param),
+            Some(RequestData::Hmset(param)) => self.mutate(param),
+            Some(RequestData::Hdel(param)) => self.mutate(param),
+            Some(RequestData::Hmdel(param)) => self.mutate(param),
+            Some(RequestData::Hincrby(param)) => self.mutate(param),
+            Some(RequestData::Hincrbyfloat(param)) => self.mutate(param),
+            Some(RequestData::Hcas(param)) => self.mutate(param),
+            Some(RequestData::Transaction(param)) => self.mutate(param),
+            Some(RequestData::Backup(param)) => self.in_backup_dir(&param.path, backup),
+            Some(RequestData::Restore(param)) => self.in_backup_dir(&param.path, restore),
+            _ => dispatch(cmd, &self.inner.store),
+        };
+        res.id = id;
         debug!("Executed Response: {:?}", res);
         self.inner.on_executed.notify(&res);
         self.inner.on_before_send.notify(&mut res);
@@ -76,6 +112,76 @@ impl<Store: Storage> Service<Store> {
         }
         res
     }
+
+    /// Subscriptions and watches of a new connection.
+    pub fn subscriber(&self) -> Subscriber {
+        Subscriber::new(Arc::clone(&self.inner.broker))
+    }
+
+    fn mutate(&self, param: impl MutationService) -> CommandResponse {
+        let (res, changes) = param.apply(&self.inner.store);
+        self.inner.broker.notify(changes);
+        res
+    }
+
+    /// Run a backup or restore on a file the client named under the backup directory.
+    fn in_backup_dir(
+        &self,
+        path: &str,
+        f: fn(&dyn Storage, PathBuf) -> Result<usize, KvError>,
+    ) -> CommandResponse {
+        let dir = match &self.inner.backup_dir {
+            Some(dir) => dir,
+            None => return KvError::InvalidCommand("Server has no backup_dir".into()).into(),
+        };
+        match backup_path(dir, path).and_then(|path| f(&self.inner.store, path)) {
+            Ok(n) => Value::from(n as i64).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl<Store: Storage + Send + Sync + 'static> Service<Store> {
+    /// Spawn a background task which reclaims expired keys every `period`.
+    pub fn spawn_reaper(&self, period: Duration) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let inner = Arc::clone(&inner);
+                match tokio::task::spawn_blocking(move || inner.store.purge_expired()).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(n)) => debug!("Purged {} expired keys", n),
+                    Ok(Err(e)) => warn!("Failed to purge expired keys: {}", e),
+                    Err(e) => warn!("Purge task failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+impl Service<MemTable> {
+    /// Spawn a background task which snapshots the store every `period` in which
+    /// its write-ahead log grew past `max_len` bytes.
+    pub fn spawn_compactor(&self, period: Duration, max_len: u64) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if inner.store.wal_len().unwrap_or_default() <= max_len {
+                    continue;
+                }
+                let inner = Arc::clone(&inner);
+                match tokio::task::spawn_blocking(move || inner.store.snapshot()).await {
+                    Ok(Ok(())) => debug!("Compacted the write-ahead log"),
+                    Ok(Err(e)) => warn!("Failed to snapshot the store: {}", e),
+                    Err(e) => warn!("Snapshot task failed: {}", e),
+                }
+            }
+        })
+    }
 }
 
 impl<Store: Storage> From<ServiceInner<Store>> for Service<Store> {
@@ -121,6 +227,32 @@ pub fn dispatch(cmd: CommandRequest, store: &dyn Storage) -> CommandResponse {
         Some(RequestData::Hmexist(param)) => param.execute(store),
         Some(RequestData::Hdel(param)) => param.execute(store),
         Some(RequestData::Hmdel(param)) => param.execute(store),
+        Some(RequestData::Expire(param)) => param.execute(store),
+        Some(RequestData::Ttl(param)) => param.execute(store),
+        Some(RequestData::Persist(param)) => param.execute(store),
+        Some(RequestData::Hincrby(param)) => param.execute(store),
+        Some(RequestData::Hincrbyfloat(param)) => param.execute(store),
+        Some(RequestData::Hcas(param)) => param.execute(store),
+        Some(RequestData::Hscan(param)) => param.execute(store),
+        Some(RequestData::Hrange(param)) => param.execute(store),
+        Some(RequestData::ListTables(param)) => param.execute(store),
+        Some(RequestData::TableLen(param)) => param.execute(store),
+        Some(RequestData::DropTable(param)) => param.execute(store),
+        Some(RequestData::RenameTable(param)) => param.execute(store),
+        Some(RequestData::Transaction(param)) => param.execute(store),
+        Some(RequestData::Backup(_) | RequestData::Restore(_)) => {
+            KvError::InvalidCommand("Backups need the backup_dir of the service".into()).into()
+        }
+        Some(
+            RequestData::Subscribe(_)
+            | RequestData::Unsubscribe(_)
+            | RequestData::Psubscribe(_)
+            | RequestData::Punsubscribe(_)
+            | RequestData::Watch(_)
+            | RequestData::Unwatch(_)
+            | RequestData::Publish(_),
+        ) => KvError::InvalidCommand("Pub/sub and watches need a connection to the service".into())
+            .into(),
         None => KvError::InvalidCommand("Request has not data".into()).into(),
     }
 }
@@ -149,6 +281,30 @@ mod tests {
         assert_res_ok(res, &["v1".into()], &[]);
     }
 
+    #[test]
+    fn backups_should_stay_in_the_backup_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let res = service.execute(CommandRequest::new_backup("kv.backup"));
+        assert_res_error(res, 400, "Server has no backup_dir");
+
+        let service: Service = ServiceInner::new(MemTable::new())
+            .backup_dir(dir.path().join("backups"))
+            .into();
+        service.execute(CommandRequest::new_hset("t1", "k1", "v1".into()));
+        let outside = dir.path().join("kv.backup");
+        let res = service.execute(CommandRequest::new_backup(outside.to_str().unwrap()));
+        assert_res_error(res, 400, "must be relative to the backup directory");
+        let res = service.execute(CommandRequest::new_backup("../kv.backup"));
+        assert_res_error(res, 400, "must be relative to the backup directory");
+        assert!(!outside.exists());
+
+        std::fs::create_dir(dir.path().join("backups")).unwrap();
+        let res = service.execute(CommandRequest::new_backup("kv.backup"));
+        assert_res_ok(res, &[1.into()], &[]);
+        assert!(dir.path().join("backups/kv.backup").exists());
+    }
+
     #[test]
     fn event_registration_should_work() {
         fn a(cmd: &CommandRequest) {
@@ -177,10 +333,89 @@ mod tests {
 
         let res = service.execute(CommandRequest::new_hset("t1", "k1", "v1".into()));
 
-        assert_eq!(res.status, StatusCode::CREATED.as_u16() as _);
+        assert_eq!(res.status, StatusCode::CREATED.as_u16() as u32);
         assert_eq!(res.message, "");
         assert_eq!(res.values, vec![Value::default()]);
     }
+
+    #[tokio::test]
+    async fn reaper_should_purge_expired_keys() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+
+        let res = service.execute(CommandRequest::new_hset_with_ttl(
+            "t1",
+            "k1",
+            "v1".into(),
+            10,
+        ));
+        assert_res_ok(res, &[Value::default()], &[]);
+        service.execute(CommandRequest::new_hset("t1", "k2", "v2".into()));
+
+        let handle = service.spawn_reaper(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.abort();
+
+        let res = service.execute(CommandRequest::new_hget("t1", "k1"));
+        assert_res_error(res, 404, "Not found");
+        let res = service.execute(CommandRequest::new_hget("t1", "k2"));
+        assert_res_ok(res, &["v2".into()], &[]);
+    }
+
+    #[tokio::test]
+    async fn writes_should_notify_watchers() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let mut subscriber = service.subscriber();
+        subscriber.execute(CommandRequest::new_watch("t1", "", ""));
+
+        let pairs = vec![Kvpair::new("a", 1.into())];
+        service.execute(CommandRequest::new_hmset("t1", pairs));
+        // only keys that existed are reported as deleted
+        let keys = vec!["a".to_string(), "b".to_string()];
+        service.execute(CommandRequest::new_hmdel("t1", keys));
+
+        let res = subscriber.recv().await.unwrap();
+        let change = ChangeEvent::set("t1", "a".into(), None, 1.into());
+        assert_eq!(res.change, Some(change));
+        let res = subscriber.recv().await.unwrap();
+        assert_eq!(
+            res.change,
+            Some(ChangeEvent::del("t1", "a".into(), 1.into()))
+        );
+
+        // committed transactions report their changes too
+        let cmds = vec![CommandRequest::new_hset("t1", "c", 3.into())];
+        service.execute(CommandRequest::new_transaction(cmds));
+        let res = subscriber.recv().await.unwrap();
+        let change = ChangeEvent::set("t1", "c".into(), None, 3.into());
+        assert_eq!(res.change, Some(change));
+    }
+
+    #[tokio::test]
+    async fn increments_and_swaps_should_notify_watchers() {
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let mut subscriber = service.subscriber();
+        subscriber.execute(CommandRequest::new_watch("t1", "", ""));
+
+        service.execute(CommandRequest::new_hincrby("t1", "n", 2));
+        let res = subscriber.recv().await.unwrap();
+        let change = ChangeEvent::set("t1", "n".into(), None, 2.into());
+        assert_eq!(res.change, Some(change));
+
+        // a failed swap changes nothing, so only the second one is reported
+        service.execute(CommandRequest::new_hcas("t1", "n", Some(1.into()), None));
+        service.execute(CommandRequest::new_hcas("t1", "n", Some(2.into()), None));
+        let res = subscriber.recv().await.unwrap();
+        assert_eq!(
+            res.change,
+            Some(ChangeEvent::del("t1", "n".into(), 2.into()))
+        );
+
+        let cmds = vec![CommandRequest::new_hcas("t1", "k", None, Some("v".into()))];
+        service.execute(CommandRequest::new_transaction(cmds));
+        let res = subscriber.recv().await.unwrap();
+        let change = ChangeEvent::set("t1", "k".into(), None, "v".into());
+        assert_eq!(res.change, Some(change));
+    }
 }
 
 #[cfg(test)]
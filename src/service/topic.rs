@@ -0,0 +1,343 @@
+use crate::command_request::RequestData;
+use crate::storage::glob_match;
+use crate::{
+    ChangeEvent, CommandRequest, CommandResponse, KvError, Publication, Publish, Unwatch, Value,
+    Watch,
+};
+use dashmap::DashMap;
+use futures::future;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Publications buffered per subscriber; later ones are dropped until it catches up.
+const MAX_PENDING_PUBLICATIONS: usize = 1024;
+/// Key changes buffered on the bus; watchers falling further behind miss some.
+const MAX_PENDING_CHANGES: usize = 1024;
+
+type Subscribers = DashMap<String, HashMap<u64, mpsc::Sender<CommandResponse>>>;
+
+/// Routes published messages to the connections subscribed to them, and key
+/// changes to the connections watching them.
+pub struct Broker {
+    next_id: AtomicU64,
+    channels: Subscribers,
+    patterns: Subscribers,
+    changes: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for Broker {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::default(),
+            channels: Subscribers::default(),
+            patterns: Subscribers::default(),
+            changes: broadcast::channel(MAX_PENDING_CHANGES).0,
+        }
+    }
+}
+
+impl Broker {
+    /// Put changes on the bus for the watchers to filter.
+    pub fn notify(&self, changes: Vec<ChangeEvent>) {
+        if self.changes.receiver_count() == 0 {
+            return;
+        }
+        for change in changes {
+            // fails only when the last watcher went away meanwhile
+            let _ = self.changes.send(change);
+        }
+    }
+
+    /// Deliver `data` to every subscriber of the channel, returning how many got it.
+    pub fn publish(&self, param: Publish) -> CommandResponse {
+        let Publish { channel, data } = param;
+        let data = data.unwrap_or_default();
+        let mut receivers = 0;
+
+        if let Some(subscribers) = self.channels.get(&channel) {
+            for tx in subscribers.values() {
+                let publication = Publication {
+                    channel: channel.clone(),
+                    pattern: String::new(),
+                    data: Some(data.clone()),
+                };
+                receivers += deliver(tx, publication) as i64;
+            }
+        }
+        for entry in self.patterns.iter() {
+            if !glob_match(entry.key(), &channel) {
+                continue;
+            }
+            for tx in entry.values() {
+                let publication = Publication {
+                    channel: channel.clone(),
+                    pattern: entry.key().clone(),
+                    data: Some(data.clone()),
+                };
+                receivers += deliver(tx, publication) as i64;
+            }
+        }
+        Value::from(receivers).into()
+    }
+}
+
+fn deliver(tx: &mpsc::Sender<CommandResponse>, publication: Publication) -> bool {
+    match tx.try_send(publication.into()) {
+        Ok(()) => true,
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            warn!("subscriber is lagging behind, dropping publication");
+            false
+        }
+        // the connection is going away and unsubscribes on drop
+        Err(mpsc::error::TrySendError::Closed(_)) => false,
+    }
+}
+
+/// Subscriptions and watches of one connection, whose messages are read
+/// with `recv`. Everything is unsubscribed when it is dropped.
+pub struct Subscriber {
+    id: u64,
+    broker: Arc<Broker>,
+    tx: mpsc::Sender<CommandResponse>,
+    rx: mpsc::Receiver<CommandResponse>,
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+    watches: Vec<Watch>,
+    // only listens to the bus while something is watched
+    changes: Option<broadcast::Receiver<ChangeEvent>>,
+}
+
+impl Subscriber {
+    pub fn new(broker: Arc<Broker>) -> Self {
+        let (tx, rx) = mpsc::channel(MAX_PENDING_PUBLICATIONS);
+        Self {
+            id: broker.next_id.fetch_add(1, Ordering::Relaxed),
+            broker,
+            tx,
+            rx,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            watches: Vec::new(),
+            changes: None,
+        }
+    }
+
+    /// Next publication or watched change for the connection.
+    pub async fn recv(&mut self) -> Option<CommandResponse> {
+        loop {
+            tokio::select! {
+                res = self.rx.recv() => return res,
+                change = next_change(&mut self.changes) => match change {
+                    Ok(change) if self.watches.iter().any(|w| is_watched(w, &change)) => {
+                        return Some(change.into());
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => warn!("watcher is lagging behind, missed {} changes", n),
+                    Err(RecvError::Closed) => self.changes = None,
+                },
+            }
+        }
+    }
+
+    /// Run a subscription or watch command, answering with the number of
+    /// channels, patterns or watches the connection has now.
+    pub fn execute(&mut self, cmd: CommandRequest) -> CommandResponse {
+        let id = cmd.id;
+        let mut res = match cmd.request_data {
+            Some(RequestData::Subscribe(param)) => {
+                self.subscribe(param.channels, false);
+                self.count()
+            }
+            Some(RequestData::Unsubscribe(param)) => {
+                self.unsubscribe(param.channels, false);
+                self.count()
+            }
+            Some(RequestData::Psubscribe(param)) => {
+                self.subscribe(param.patterns, true);
+                self.count()
+            }
+            Some(RequestData::Punsubscribe(param)) => {
+                self.unsubscribe(param.patterns, true);
+                self.count()
+            }
+            Some(RequestData::Watch(param)) => self.watch(param),
+            Some(RequestData::Unwatch(param)) => {
+                let Unwatch { table, key, prefix } = param;
+                self.watches.retain(|w| {
+                    !table.is_empty() && (&w.table, &w.key, &w.prefix) != (&table, &key, &prefix)
+                });
+                if self.watches.is_empty() {
+                    self.changes = None;
+                }
+                Value::from(self.watches.len() as i64).into()
+            }
+            _ => KvError::InvalidCommand("Not a subscription command".into()).into(),
+        };
+        res.id = id;
+        res
+    }
+
+    fn subscribe(&mut self, names: Vec<String>, pattern: bool) {
+        let (subscribed, subscribers) = match pattern {
+            true => (&mut self.patterns, &self.broker.patterns),
+            false => (&mut self.channels, &self.broker.channels),
+        };
+        for name in names {
+            if subscribed.insert(name.clone()) {
+                subscribers
+                    .entry(name)
+                    .or_default()
+                    .insert(self.id, self.tx.clone());
+            }
+        }
+    }
+
+    /// Drop the given subscriptions, or all of them when `names` is empty.
+    fn unsubscribe(&mut self, names: Vec<String>, pattern: bool) {
+        let (subscribed, subscribers) = match pattern {
+            true => (&mut self.patterns, &self.broker.patterns),
+            false => (&mut self.channels, &self.broker.channels),
+        };
+        let names: Vec<String> = match names.is_empty() {
+            true => subscribed.drain().collect(),
+            false => names
+                .into_iter()
+                .filter(|name| subscribed.remove(name))
+                .collect(),
+        };
+        for name in names {
+            if let Some(mut entry) = subscribers.get_mut(&name) {
+                entry.remove(&self.id);
+            }
+            subscribers.remove_if(&name, |_, ids| ids.is_empty());
+        }
+    }
+
+    fn count(&self) -> CommandResponse {
+        Value::from((self.channels.len() + self.patterns.len()) as i64).into()
+    }
+
+    fn watch(&mut self, param: Watch) -> CommandResponse {
+        if param.table.is_empty() {
+            return KvError::InvalidCommand("Watch needs a table".into()).into();
+        }
+        if !param.key.is_empty() && !param.prefix.is_empty() {
+            return KvError::InvalidCommand("Watch takes either a key or a prefix".into()).into();
+        }
+        if !self.watches.contains(&param) {
+            self.watches.push(param);
+        }
+        if self.changes.is_none() {
+            self.changes = Some(self.broker.changes.subscribe());
+        }
+        Value::from(self.watches.len() as i64).into()
+    }
+}
+
+async fn next_change(
+    changes: &mut Option<broadcast::Receiver<ChangeEvent>>,
+) -> Result<ChangeEvent, RecvError> {
+    match changes {
+        Some(changes) => changes.recv().await,
+        None => future::pending().await,
+    }
+}
+
+fn is_watched(watch: &Watch, change: &ChangeEvent) -> bool {
+    watch.table == change.table
+        && match (watch.key.is_empty(), watch.prefix.is_empty()) {
+            (false, _) => watch.key == change.key,
+            (true, false) => change.key.starts_with(&watch.prefix),
+            (true, true) => true,
+        }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.unsubscribe(Vec::new(), false);
+        self.unsubscribe(Vec::new(), true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publication(res: CommandResponse) -> Publication {
+        res.publication.unwrap()
+    }
+
+    #[test]
+    fn publish_should_reach_channel_and_pattern_subscribers() {
+        let broker = Arc::new(Broker::default());
+        let mut s1 = Subscriber::new(broker.clone());
+        let mut s2 = Subscriber::new(broker.clone());
+
+        let res = s1.execute(CommandRequest::new_subscribe(vec!["news".into()]));
+        assert_eq!(res.values, vec![1.into()]);
+        let res = s2.execute(CommandRequest::new_psubscribe(vec!["n*".into()]));
+        assert_eq!(res.values, vec![1.into()]);
+
+        let res = broker.publish(Publish {
+            channel: "news".into(),
+            data: Some("hello".into()),
+        });
+        assert_eq!(res.values, vec![2.into()]);
+
+        let p1 = publication(s1.rx.try_recv().unwrap());
+        assert_eq!((p1.channel.as_str(), p1.pattern.as_str()), ("news", ""));
+        let p2 = publication(s2.rx.try_recv().unwrap());
+        assert_eq!((p2.channel.as_str(), p2.pattern.as_str()), ("news", "n*"));
+        assert_eq!(p2.data, Some("hello".into()));
+
+        let res = broker.publish(Publish {
+            channel: "other".into(),
+            data: None,
+        });
+        assert_eq!(res.values, vec![0.into()]);
+    }
+
+    #[test]
+    fn unsubscribe_and_drop_should_remove_subscriptions() {
+        let broker = Arc::new(Broker::default());
+        let mut s1 = Subscriber::new(broker.clone());
+
+        let channels = vec!["a".to_string(), "b".to_string()];
+        s1.execute(CommandRequest::new_subscribe(channels));
+        let res = s1.execute(CommandRequest::new_unsubscribe(vec!["a".into()]));
+        assert_eq!(res.values, vec![1.into()]);
+        assert!(!broker.channels.contains_key("a"));
+
+        s1.execute(CommandRequest::new_psubscribe(vec!["*".into()]));
+        drop(s1);
+        assert!(broker.channels.is_empty());
+        assert!(broker.patterns.is_empty());
+    }
+
+    #[tokio::test]
+    async fn watch_should_only_deliver_matching_changes() {
+        let broker = Arc::new(Broker::default());
+        let mut s1 = Subscriber::new(broker.clone());
+
+        let res = s1.execute(CommandRequest::new_watch("t1", "k1", "k"));
+        assert_eq!(res.status, 400);
+        let res = s1.execute(CommandRequest::new_watch("t1", "", "user:"));
+        assert_eq!(res.values, vec![1.into()]);
+
+        let deleted = ChangeEvent::del("t1", "user:1".into(), 2.into());
+        broker.notify(vec![
+            ChangeEvent::set("t1", "post:1".into(), None, 1.into()),
+            ChangeEvent::set("t2", "user:1".into(), None, 1.into()),
+            deleted.clone(),
+        ]);
+        assert_eq!(s1.recv().await.unwrap().change, Some(deleted));
+
+        let res = s1.execute(CommandRequest::new_unwatch("", "", ""));
+        assert_eq!(res.values, vec![0.into()]);
+        assert_eq!(broker.changes.receiver_count(), 0);
+    }
+}
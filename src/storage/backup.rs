@@ -0,0 +1,156 @@
+use crate::storage::wal::{read_record, write_snapshot};
+use crate::storage::Storage;
+use crate::wal_op::Op;
+use crate::{KvError, WalOp, WalRecord};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+
+/// Dump every table of a store to `path`, returning the number of keys. Backups share
+/// the snapshot format of `MemTable`, so they load into any store.
+pub fn backup(store: &dyn Storage, path: impl AsRef<Path>) -> Result<usize, KvError> {
+    let ops = store.dump()?.map(|put| {
+        put.map(|put| WalOp {
+            op: Some(Op::Put(put)),
+        })
+    });
+    write_snapshot(path.as_ref(), WalRecord::default(), ops)
+}
+
+/// Load a backup into a store without tables, returning the number of keys. Keys which
+/// expired since the backup was taken are skipped.
+pub fn restore(store: &dyn Storage, path: impl AsRef<Path>) -> Result<usize, KvError> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path)?);
+    // the header only matters to the log of a snapshot
+    if read_record(&mut reader)?.is_none() {
+        return Err(KvError::Internal(format!(
+            "Empty backup {}",
+            path.display()
+        )));
+    }
+
+    let mut ops = Vec::new().into_iter();
+    let mut puts = std::iter::from_fn(|| loop {
+        match ops.next() {
+            Some(Op::Put(put)) => return Some(Ok(put)),
+            Some(_) => return Some(Err(KvError::Internal("Backups only hold puts".into()))),
+            None => {}
+        }
+        match read_record(&mut reader) {
+            Ok(Some(record)) => {
+                let next: Vec<_> = record.ops.into_iter().filter_map(|op| op.op).collect();
+                ops = next.into_iter();
+            }
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+    });
+    store.load(&mut puts)
+}
+
+/// Resolve a backup `path` sent by a client under `dir`, refusing absolute paths and
+/// `..` so it cannot reach any other file of the server.
+pub fn backup_path(dir: impl AsRef<Path>, path: &str) -> Result<PathBuf, KvError> {
+    let relative = Path::new(path);
+    let inside = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || !inside {
+        return Err(KvError::InvalidCommand(format!(
+            "Backup path {} must be relative to the backup directory, without ..",
+            path
+        )));
+    }
+    Ok(dir.as_ref().join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FsyncPolicy, Kvpair, MemTable, SledDb, Value};
+    use bytes::Bytes;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn fill(store: &dyn Storage) {
+        let values: Vec<Value> = vec![
+            "s".into(),
+            Bytes::from_static(b"\x00\xff").into(),
+            42.into(),
+            1.5.into(),
+            true.into(),
+        ];
+        for (i, value) in values.into_iter().enumerate() {
+            store.set("t1", format!("k{}", i), value).unwrap();
+        }
+        store.set("t2", "ttl".into(), 1.into()).unwrap();
+        store.expire("t2", "ttl", Duration::from_secs(60)).unwrap();
+        store.set("t2", "gone".into(), 2.into()).unwrap();
+        store
+            .expire("t2", "gone", Duration::from_millis(1))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    fn assert_restored(store: &dyn Storage) {
+        let mut pairs = store.get_all("t1").unwrap();
+        pairs.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(pairs.len(), 5);
+        assert_eq!(
+            pairs[1],
+            Kvpair::new("k1", Bytes::from_static(b"\x00\xff").into())
+        );
+        assert_eq!(pairs[3], Kvpair::new("k3", 1.5.into()));
+        assert_eq!(
+            store.get_all("t2").unwrap(),
+            vec![Kvpair::new("ttl", 1.into())]
+        );
+        assert!(store.ttl("t2", "ttl").unwrap().is_some());
+    }
+
+    #[test]
+    fn backup_path_should_stay_in_the_backup_dir() {
+        assert_eq!(
+            backup_path("/var/kv", "daily/kv.backup"),
+            Ok(PathBuf::from("/var/kv/daily/kv.backup"))
+        );
+        for path in ["/etc/passwd", "../kv.backup", "daily/../../kv.backup", ""] {
+            let res = backup_path("/var/kv", path);
+            assert!(matches!(res, Err(KvError::InvalidCommand(_))), "{}", path);
+        }
+    }
+
+    #[test]
+    fn backup_should_restore_across_backends() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.backup");
+
+        let mem = MemTable::new();
+        fill(&mem);
+        assert_eq!(backup(&mem, &path), Ok(6));
+        let sled = SledDb::new(dir.path().join("sled"));
+        assert_eq!(restore(&sled, &path), Ok(6));
+        assert_restored(&sled);
+
+        assert_eq!(backup(&sled, &path), Ok(6));
+        let mem = MemTable::new();
+        assert_eq!(restore(&mem, &path), Ok(6));
+        assert_restored(&mem);
+
+        assert_eq!(restore(&mem, &path), Err(KvError::TableExists("t1".into())));
+    }
+
+    #[test]
+    fn restore_should_load_memtable_snapshots() {
+        let dir = tempdir().unwrap();
+        let wal = dir.path().join("kv.wal");
+        let store = MemTable::with_wal(&wal, FsyncPolicy::Never).unwrap();
+        fill(&store);
+        store.snapshot().unwrap();
+
+        let mem = MemTable::new();
+        assert_eq!(restore(&mem, dir.path().join("kv.wal.snapshot")), Ok(6));
+        assert_restored(&mem);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::storage::{expired_by, now_ms, remaining, Storage};
+use crate::KvError;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Keys handled between two progress reports.
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// How far the expiry of a copied key may be off, the time between reading both ttls.
+const EXPIRY_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// How far a `copy_store` has got.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// Tables keys were copied from
+    pub tables: usize,
+    /// Keys copied
+    pub keys: usize,
+    /// Keys found with the same value and expiry in both stores
+    pub verified: usize,
+}
+
+/// Copy every live key with its expiry from `src` into `dst`, which must not have tables,
+/// then compare each key of `src` with `dst` if `verify` is set. `progress` is called every
+/// `PROGRESS_INTERVAL` keys and at the end of each pass; `src` should not change meanwhile.
+pub fn copy_store(
+    src: &dyn Storage,
+    dst: &dyn Storage,
+    verify: bool,
+    mut progress: impl FnMut(&CopyProgress),
+) -> Result<CopyProgress, KvError> {
+    let mut state = CopyProgress::default();
+    let mut tables = HashSet::new();
+    let now = now_ms();
+    let mut puts = src.dump()?.inspect(|put| {
+        if let Ok(put) = put {
+            if tables.insert(put.table.clone()) {
+                state.tables += 1;
+            }
+            if !expired_by(put, now) {
+                state.keys += 1;
+                if state.keys.is_multiple_of(PROGRESS_INTERVAL) {
+                    progress(&state);
+                }
+            }
+        }
+    });
+    // keys expiring while loading were counted but skipped
+    state.keys = dst.load(&mut puts)?;
+    progress(&state);
+    if verify {
+        verify_copy(src, dst, &mut state, &mut progress)?;
+    }
+    Ok(state)
+}
+
+/// Check that every key of `src` has the same value in `dst` and an expiry no further
+/// than `EXPIRY_TOLERANCE` apart.
+fn verify_copy(
+    src: &dyn Storage,
+    dst: &dyn Storage,
+    state: &mut CopyProgress,
+    progress: &mut impl FnMut(&CopyProgress),
+) -> Result<(), KvError> {
+    for put in src.dump()? {
+        let put = put?;
+        let same_expiry = match (dst.ttl(&put.table, &put.key)?, put.expire_at) {
+            (None, 0) => true,
+            (Some(ttl), at) if at > 0 => {
+                let expected = remaining(at);
+                ttl.max(expected) - ttl.min(expected) <= EXPIRY_TOLERANCE
+            }
+            _ => false,
+        };
+        let copied = same_expiry && dst.get(&put.table, &put.key)? == put.value;
+        // a key may expire in one store just before the other
+        let expired = expired_by(&put, now_ms());
+        if !copied && !expired {
+            return Err(KvError::Internal(format!(
+                "Copy of table: {}, key: {} differs from the source",
+                put.table, put.key
+            )));
+        }
+        state.verified += 1;
+        if state.verified.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(state);
+        }
+    }
+    progress(state);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Kvpair, MemTable, SledDb};
+    use tempfile::tempdir;
+
+    #[test]
+    fn copy_store_should_copy_across_backends() {
+        let dir = tempdir().unwrap();
+        let mem = MemTable::new();
+        for i in 0..PROGRESS_INTERVAL + 1 {
+            mem.set("t1", format!("k{}", i), (i as i64).into()).unwrap();
+        }
+        mem.set("t2", "ttl".into(), "v".into()).unwrap();
+        mem.expire("t2", "ttl", Duration::from_secs(60)).unwrap();
+
+        let sled = SledDb::new(dir.path());
+        let mut reports = Vec::new();
+        let state = copy_store(&mem, &sled, true, |p| reports.push(p.clone())).unwrap();
+        let expected = CopyProgress {
+            tables: 2,
+            keys: PROGRESS_INTERVAL + 2,
+            verified: PROGRESS_INTERVAL + 2,
+        };
+        assert_eq!(state, expected);
+        assert_eq!(reports.len(), 4);
+        assert_eq!(reports.last(), Some(&expected));
+
+        assert_eq!(sled.table_len("t1"), Ok(PROGRESS_INTERVAL + 1));
+        assert_eq!(
+            sled.get_all("t2").unwrap(),
+            vec![Kvpair::new("ttl", "v".into())]
+        );
+        assert!(sled.ttl("t2", "ttl").unwrap().is_some());
+
+        let res = copy_store(&sled, &mem, false, |_| {});
+        assert_eq!(res, Err(KvError::TableExists("t1".into())));
+    }
+
+    #[test]
+    fn verify_should_compare_expiries() {
+        let src = MemTable::new();
+        src.set("t1", "k".into(), 1.into()).unwrap();
+        src.expire("t1", "k", Duration::from_secs(60)).unwrap();
+        let dst = MemTable::new();
+        copy_store(&src, &dst, false, |_| {}).unwrap();
+
+        let mut state = CopyProgress::default();
+        assert!(verify_copy(&src, &dst, &mut state, &mut |_| {}).is_ok());
+        dst.expire("t1", "k", Duration::from_secs(3600)).unwrap();
+        let res = verify_copy(&src, &dst, &mut state, &mut |_| {});
+        assert!(matches!(res, Err(KvError::Internal(_))));
+    }
+}
@@ -1,12 +1,206 @@
 use crate::error::KvError;
-use crate::storage::Storage;
-use crate::{Kvpair, StorageIter, Value};
-use dashmap::mapref::one::Ref;
+use crate::storage::wal::Wal;
+use crate::storage::{
+    deadline_after, expired_by, glob_match, incr_value, is_empty_range, now_ms, paginate,
+    remaining, FsyncPolicy, Storage, TxStorage, TxView,
+};
+use crate::wal_op::Op;
+use crate::{DropTable, Hdel, Kvpair, RenameTable, StorageIter, Value, WalOp, WalPut, WalRecord};
+use dashmap::mapref::entry::{Entry, OccupiedEntry, VacantEntry};
 use dashmap::DashMap;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, VecDeque};
+use std::ops::{Bound, Deref};
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Debug, Default)]
 pub struct MemTable {
-    tables: DashMap<String, DashMap<String, Value>>,
+    tables: DashMap<String, Arc<Table>>,
+    /// Shared by single operations, held exclusively by transactions and table operations.
+    lock: Arc<RwLock<()>>,
+    wal: Option<Wal>,
+}
+
+/// Clones only the data, the clone does not write to the log.
+impl Clone for MemTable {
+    fn clone(&self) -> Self {
+        let _guard = self.shared();
+        Self {
+            tables: self.copy_tables().into_iter().collect(),
+            lock: Arc::default(),
+            wal: None,
+        }
+    }
+}
+
+/// The records of a table, with their keys also kept in order for scans and ranges.
+#[derive(Debug, Default)]
+struct Table {
+    records: DashMap<String, Record>,
+    /// Changed while the entry of the key is held, so it matches `records`.
+    keys: RwLock<BTreeSet<String>>,
+}
+
+/// Keys of the index read at once by a `Walk`.
+const WALK_BATCH: usize = 64;
+
+/// The live records of a table between two bounds, in key order or in reverse. The index
+/// is read a batch of keys at a time, so writers never wait for a whole walk.
+struct Walk<T> {
+    table: T,
+    /// Shared for each batch when the walk outlives the caller's guard.
+    lock: Option<Arc<RwLock<()>>>,
+    start: Bound<String>,
+    end: Bound<String>,
+    reverse: bool,
+    batch: VecDeque<(String, Record)>,
+    done: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Record {
+    value: Value,
+    expire_at: Option<u64>,
+}
+
+impl Record {
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            expire_at: None,
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expire_at, Some(t) if t <= now)
+    }
+}
+
+impl Table {
+    fn index(&self) -> RwLockWriteGuard<'_, BTreeSet<String>> {
+        self.keys.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, key: String, record: Record) -> Option<Record> {
+        match self.records.entry(key) {
+            Entry::Occupied(mut e) => Some(e.insert(record)),
+            Entry::Vacant(e) => {
+                self.fill(e, record);
+                None
+            }
+        }
+    }
+
+    fn remove(&self, key: &str) -> Option<Record> {
+        match self.records.entry(key.into()) {
+            Entry::Occupied(e) => Some(self.take(e)),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Insert into a vacant entry of this table, indexing the key before it is released.
+    fn fill(&self, e: VacantEntry<'_, String, Record, RandomState>, record: Record) {
+        let key = e.key().clone();
+        let _entry = e.insert(record);
+        self.index().insert(key);
+    }
+
+    /// Remove an occupied entry of this table and its key from the index.
+    fn take(&self, e: OccupiedEntry<'_, String, Record, RandomState>) -> Record {
+        self.index().remove(e.key());
+        e.remove()
+    }
+}
+
+impl Clone for Table {
+    fn clone(&self) -> Self {
+        let records = self.records.clone();
+        let keys = records.iter().map(|e| e.key().clone()).collect();
+        Self {
+            records,
+            keys: RwLock::new(keys),
+        }
+    }
+}
+
+impl<T: Deref<Target = Table>> Walk<T> {
+    fn new(table: T, start: Bound<&str>, end: Bound<&str>, reverse: bool) -> Self {
+        Self {
+            table,
+            lock: None,
+            start: start.map(String::from),
+            end: end.map(String::from),
+            reverse,
+            batch: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Read each batch under the shared side of `lock`, so it never sees a transaction
+    /// half done.
+    fn locked(mut self, lock: Arc<RwLock<()>>) -> Self {
+        self.lock = Some(lock);
+        self
+    }
+
+    fn fill(&mut self) {
+        let lock = self.lock.clone();
+        let _guard = lock
+            .as_ref()
+            .map(|l| l.read().unwrap_or_else(PoisonError::into_inner));
+        let start = self.start.as_ref().map(String::as_str);
+        let end = self.end.as_ref().map(String::as_str);
+        let keys: Vec<String> = match is_empty_range(start, end) {
+            true => Vec::new(),
+            false => {
+                let index = self
+                    .table
+                    .keys
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                let range = index.range::<str, _>((start, end));
+                match self.reverse {
+                    true => range.rev().take(WALK_BATCH).cloned().collect(),
+                    false => range.take(WALK_BATCH).cloned().collect(),
+                }
+            }
+        };
+        self.done = keys.len() < WALK_BATCH;
+        if let Some(last) = keys.last() {
+            let next = Bound::Excluded(last.clone());
+            match self.reverse {
+                true => self.end = next,
+                false => self.start = next,
+            }
+        }
+
+        let now = now_ms();
+        for key in keys {
+            let record = self.table.records.get(&key).map(|r| r.value().clone());
+            if let Some(record) = record.filter(|r| !r.is_expired(now)) {
+                self.batch.push_back((key, record));
+            }
+        }
+    }
+}
+
+impl<T: Deref<Target = Table>> Iterator for Walk<T> {
+    type Item = (String, Record);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.batch.pop_front() {
+                return Some(item);
+            }
+            if self.done {
+                return None;
+            }
+            self.fill();
+        }
+    }
 }
 
 impl MemTable {
@@ -14,57 +208,730 @@ impl MemTable {
         Self::default()
     }
 
-    fn get_or_create(&self, name: &str) -> Ref<String, DashMap<String, Value>> {
-        match self.tables.get(name) {
+    /// Open a MemTable that survives restarts by logging every change to `path`,
+    /// restoring what the log already holds.
+    pub fn with_wal(path: impl AsRef<Path>, fsync: FsyncPolicy) -> Result<Self, KvError> {
+        let mut store = Self::new();
+        let wal = Wal::open(path, fsync, |record| store.replay(record))?;
+        store.wal = Some(wal);
+        Ok(store)
+    }
+
+    /// Write all tables to the snapshot next to the write-ahead log and cut what it
+    /// contains from the log, so replaying stays short. Writers only wait for the log
+    /// position to be taken; keys they change during the walk are settled by replaying
+    /// the log from that position on top of the snapshot.
+    pub fn snapshot(&self) -> Result<(), KvError> {
+        let wal = match &self.wal {
+            Some(wal) => wal,
             None => {
-                let entry = self.tables.entry(name.into()).or_default();
-                entry.downgrade()
+                return Err(KvError::InvalidCommand(
+                    "MemTable has no write-ahead log".into(),
+                ))
+            }
+        };
+        let _compacting = wal.compacting();
+        let (tables, position) = {
+            let _guard = self.exclusive();
+            (self.table_handles(), wal.position())
+        };
+        let lock = Some(Arc::clone(&self.lock));
+        let ops = live_puts(tables, lock).map(|put| WalOp {
+            op: Some(Op::Put(put)),
+        });
+        wal.compact(position, ops)
+    }
+
+    /// Flush the write-ahead log to disk now, whatever its fsync policy.
+    pub fn sync(&self) -> Result<(), KvError> {
+        match &self.wal {
+            Some(wal) => wal.sync(),
+            None => Ok(()),
+        }
+    }
+
+    /// Size of the write-ahead log in bytes, if there is one.
+    pub fn wal_len(&self) -> Option<u64> {
+        self.wal.as_ref().map(|wal| wal.len())
+    }
+
+    fn shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.lock.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Append a change to the log before it is made, if there is one.
+    fn log(&self, op: impl FnOnce() -> Op) -> Result<(), KvError> {
+        match &self.wal {
+            Some(wal) => wal.append(WalOp { op: Some(op()) }),
+            None => Ok(()),
+        }
+    }
+
+    fn log_put(&self, table: &str, key: &str, record: &Record) -> Result<(), KvError> {
+        self.log(|| {
+            Op::Put(WalPut {
+                table: table.into(),
+                key: key.into(),
+                value: Some(record.value.clone()),
+                expire_at: record.expire_at.unwrap_or_default(),
+            })
+        })
+    }
+
+    fn log_del(&self, table: &str, key: &str) -> Result<(), KvError> {
+        self.log(|| {
+            Op::Del(Hdel {
+                table: table.into(),
+                key: key.into(),
+            })
+        })
+    }
+
+    /// Insert a record, returning the live value it replaced.
+    fn put(&self, table: &str, key: &str, record: Record) -> Result<Option<Value>, KvError> {
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        // the entry stays locked until the change is logged and made
+        let old = match table.records.entry(key.into()) {
+            Entry::Occupied(mut e) => {
+                self.log_put(name, key, &record)?;
+                Some(e.insert(record))
+            }
+            Entry::Vacant(e) => {
+                self.log_put(name, key, &record)?;
+                table.fill(e, record);
+                None
+            }
+        };
+        Ok(old.filter(|r| !r.is_expired(now)).map(|r| r.value))
+    }
+
+    fn replay(&self, record: WalRecord) {
+        let now = now_ms();
+        for op in record.ops.into_iter().filter_map(|op| op.op) {
+            match op {
+                Op::Put(put) => {
+                    let table = self.get_or_create(&put.table);
+                    let record = Record {
+                        value: put.value.unwrap_or_default(),
+                        expire_at: (put.expire_at > 0).then_some(put.expire_at),
+                    };
+                    if record.is_expired(now) {
+                        table.remove(&put.key);
+                    } else {
+                        table.insert(put.key, record);
+                    }
+                }
+                Op::Del(del) => {
+                    self.get_or_create(&del.table).remove(&del.key);
+                }
+                Op::DropTable(drop) => {
+                    self.tables.remove(&drop.table);
+                }
+                Op::RenameTable(rename) => {
+                    if let Some((_, table)) = self.tables.remove(&rename.from) {
+                        self.tables.insert(rename.to, table);
+                    }
+                }
             }
-            Some(table) => table,
         }
     }
+
+    fn len(&self, table: &str) -> usize {
+        self.tables
+            .get(table)
+            .map_or(0, |t| MemTable::live_len(t.value()))
+    }
+
+    /// Number of live keys in a table.
+    fn live_len(table: &Table) -> usize {
+        let now = now_ms();
+        table
+            .records
+            .iter()
+            .filter(|e| !e.value().is_expired(now))
+            .count()
+    }
+
+    fn get_or_create(&self, name: &str) -> Arc<Table> {
+        match self.tables.get(name) {
+            None => Arc::clone(&self.tables.entry(name.into()).or_default()),
+            Some(table) => Arc::clone(&table),
+        }
+    }
+
+    /// The tables as they are now, shared with the store.
+    fn table_handles(&self) -> Vec<(String, Arc<Table>)> {
+        self.tables
+            .iter()
+            .map(|t| (t.key().clone(), Arc::clone(t.value())))
+            .collect()
+    }
+
+    /// A copy of the tables, not shared with the store.
+    fn copy_tables(&self) -> Vec<(String, Arc<Table>)> {
+        self.tables
+            .iter()
+            .map(|t| (t.key().clone(), Arc::new(Table::clone(t.value()))))
+            .collect()
+    }
+}
+
+/// The puts recreating the live keys of the tables, walked under `lock` if given.
+fn live_puts(
+    tables: Vec<(String, Arc<Table>)>,
+    lock: Option<Arc<RwLock<()>>>,
+) -> impl Iterator<Item = WalPut> {
+    tables.into_iter().flat_map(move |(name, table)| {
+        let walk = Walk::new(table, Bound::Unbounded, Bound::Unbounded, false);
+        let walk = match &lock {
+            Some(lock) => walk.locked(Arc::clone(lock)),
+            None => walk,
+        };
+        walk.map(move |(key, r)| WalPut {
+            table: name.clone(),
+            key,
+            value: Some(r.value),
+            expire_at: r.expire_at.unwrap_or_default(),
+        })
+    })
+}
+
+/// Look up a live record, lazily evicting it if it has expired.
+fn get_live(table: &Table, key: &str) -> Option<Record> {
+    let now = now_ms();
+    let record = table.records.get(key).map(|r| r.value().clone());
+    match record {
+        Some(r) if r.is_expired(now) => {
+            if let Entry::Occupied(e) = table.records.entry(key.into()) {
+                if e.get().is_expired(now) {
+                    table.take(e);
+                }
+            }
+            None
+        }
+        r => r,
+    }
 }
 
 impl Storage for MemTable {
     fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
-        let table = self.get_or_create(table);
-        Ok(table.get(key).map(|v| v.value().clone()))
+        let _guard = self.shared();
+        TxStorage::get(self, table, key)
     }
 
     fn set(&self, table: &str, key: String, value: Value) -> Result<Option<Value>, KvError> {
-        let table = self.get_or_create(table);
-        Ok(table.insert(key, value))
+        let _guard = self.shared();
+        TxStorage::set(self, table, &key, value)
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: String,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        let _guard = self.shared();
+        TxStorage::set_with_ttl(self, table, &key, value, ttl)
     }
 
     fn contains(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        let _guard = self.shared();
         let table = self.get_or_create(table);
-        Ok(table.contains_key(key))
+        Ok(get_live(&table, key).is_some())
     }
 
     fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
-        let table = self.get_or_create(table);
-        Ok(table.remove(key).map(|(_, v)| v))
+        let _guard = self.shared();
+        TxStorage::del(self, table, key)
     }
 
     fn get_all(&self, table: &str) -> Result<Vec<Kvpair>, KvError> {
+        let _guard = self.shared();
         let table = self.get_or_create(table);
+        let now = now_ms();
         Ok(table
+            .records
             .iter()
-            .map(|e| Kvpair::new(e.key(), e.value().clone()))
+            .filter(|e| !e.value().is_expired(now))
+            .map(|e| Kvpair::new(e.key(), e.value().value.clone()))
             .collect())
     }
 
     fn get_iter(&self, table: &str) -> Result<Box<dyn Iterator<Item = Kvpair>>, KvError> {
+        let _guard = self.shared();
         let table = self.get_or_create(table);
-        let iter = StorageIter::new(table.clone().into_iter());
-        Ok(Box::new(iter))
+        let now = now_ms();
+        let iter = table
+            .records
+            .clone()
+            .into_iter()
+            .filter(move |(_, r)| !r.is_expired(now))
+            .map(|(k, r)| (k, r.value));
+        Ok(Box::new(StorageIter::new(iter)))
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        let _guard = self.shared();
+        TxStorage::expire(self, table, key, ttl)
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        let _guard = self.shared();
+        TxStorage::ttl(self, table, key)
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        let _guard = self.shared();
+        TxStorage::persist(self, table, key)
+    }
+
+    fn purge_expired(&self) -> Result<usize, KvError> {
+        let _guard = self.shared();
+        let now = now_ms();
+        let mut count = 0;
+        for table in self.tables.iter() {
+            table.records.retain(|k, r| {
+                let expired = r.is_expired(now);
+                if expired {
+                    table.index().remove(k);
+                    count += 1;
+                }
+                !expired
+            });
+        }
+        Ok(count)
+    }
+
+    fn incr(&self, table: &str, key: &str, delta: Value) -> Result<Value, KvError> {
+        let _guard = self.shared();
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        let value = match table.records.entry(key.into()) {
+            Entry::Occupied(mut e) if e.get().is_expired(now) => {
+                let value = incr_value(None, &delta)?;
+                let record = Record::new(value.clone());
+                self.log_put(name, key, &record)?;
+                e.insert(record);
+                value
+            }
+            Entry::Occupied(mut e) => {
+                let value = incr_value(Some(e.get().value.clone()), &delta)?;
+                let record = Record {
+                    value: value.clone(),
+                    expire_at: e.get().expire_at,
+                };
+                self.log_put(name, key, &record)?;
+                e.insert(record);
+                value
+            }
+            Entry::Vacant(e) => {
+                let value = incr_value(None, &delta)?;
+                let record = Record::new(value.clone());
+                self.log_put(name, key, &record)?;
+                table.fill(e, record);
+                value
+            }
+        };
+        Ok(value)
+    }
+
+    fn compare_and_swap(
+        &self,
+        table: &str,
+        key: &str,
+        expected: Option<Value>,
+        new: Option<Value>,
+    ) -> Result<Result<(), Option<Value>>, KvError> {
+        let _guard = self.shared();
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        let res = match table.records.entry(key.into()) {
+            Entry::Occupied(e) if e.get().is_expired(now) => match (expected, new) {
+                (None, Some(v)) => {
+                    let record = Record::new(v);
+                    self.log_put(name, key, &record)?;
+                    e.replace_entry(record);
+                    Ok(())
+                }
+                (None, None) => {
+                    table.take(e);
+                    Ok(())
+                }
+                (Some(_), _) => Err(None),
+            },
+            Entry::Occupied(mut e) => match (expected, new) {
+                (Some(expected), Some(v)) if e.get().value == expected => {
+                    let record = Record {
+                        value: v,
+                        expire_at: e.get().expire_at,
+                    };
+                    self.log_put(name, key, &record)?;
+                    e.insert(record);
+                    Ok(())
+                }
+                (Some(expected), None) if e.get().value == expected => {
+                    self.log_del(name, key)?;
+                    table.take(e);
+                    Ok(())
+                }
+                _ => Err(Some(e.get().value.clone())),
+            },
+            Entry::Vacant(e) => match (expected, new) {
+                (None, Some(v)) => {
+                    let record = Record::new(v);
+                    self.log_put(name, key, &record)?;
+                    table.fill(e, record);
+                    Ok(())
+                }
+                (None, None) => Ok(()),
+                (Some(_), _) => Err(None),
+            },
+        };
+        Ok(res)
+    }
+
+    fn scan(
+        &self,
+        table: &str,
+        cursor: &str,
+        limit: usize,
+        prefix: &str,
+        pattern: &str,
+    ) -> Result<(Vec<Kvpair>, Option<String>), KvError> {
+        let _guard = self.shared();
+        let table = self.get_or_create(table);
+        // resume after the cursor, or at the prefix when the cursor sorts before it
+        let start = match cursor.is_empty() || cursor < prefix {
+            true => Bound::Included(prefix),
+            false => Bound::Excluded(cursor),
+        };
+        let pairs = Walk::new(table, start, Bound::Unbounded, false)
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .filter(|(k, _)| glob_match(pattern, k))
+            .take(limit + 1)
+            .map(|(k, r)| Kvpair::new(k, r.value))
+            .collect();
+        Ok(paginate(pairs, limit))
+    }
+
+    fn range(
+        &self,
+        table: &str,
+        start: Bound<&str>,
+        end: Bound<&str>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Vec<Kvpair>, KvError> {
+        let _guard = self.shared();
+        if is_empty_range(start, end) {
+            return Ok(vec![]);
+        }
+
+        let table = self.get_or_create(table);
+        let pairs = Walk::new(table, start, end, reverse)
+            .take(limit)
+            .map(|(k, r)| Kvpair::new(k, r.value))
+            .collect();
+        Ok(pairs)
+    }
+
+    fn tables(&self) -> Result<Vec<String>, KvError> {
+        let _guard = self.shared();
+        let mut names: Vec<_> = self
+            .tables
+            .iter()
+            .filter(|t| MemTable::live_len(t.value()) > 0)
+            .map(|t| t.key().clone())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn table_len(&self, table: &str) -> Result<usize, KvError> {
+        let _guard = self.shared();
+        Ok(self.len(table))
+    }
+
+    fn drop_table(&self, table: &str) -> Result<bool, KvError> {
+        let _guard = self.exclusive();
+        if self.tables.contains_key(table) {
+            self.log(|| {
+                Op::DropTable(DropTable {
+                    table: table.into(),
+                })
+            })?;
+        }
+        let removed = self.tables.remove(table);
+        Ok(matches!(removed, Some((_, t)) if MemTable::live_len(&t) > 0))
+    }
+
+    fn rename_table(&self, from: &str, to: &str) -> Result<(), KvError> {
+        let _guard = self.exclusive();
+        if self.len(from) == 0 {
+            return Err(KvError::TableNotFound(from.into()));
+        }
+        if from == to {
+            return Ok(());
+        }
+        if self.len(to) > 0 {
+            return Err(KvError::TableExists(to.into()));
+        }
+
+        self.log(|| {
+            Op::RenameTable(RenameTable {
+                from: from.into(),
+                to: to.into(),
+            })
+        })?;
+        match self.tables.remove(from) {
+            Some((_, table)) => {
+                self.tables.insert(to.into(), table);
+                Ok(())
+            }
+            None => Err(KvError::TableNotFound(from.into())),
+        }
+    }
+
+    fn transaction(
+        &self,
+        tables: &[String],
+        f: &mut dyn FnMut(&dyn Storage) -> Result<(), KvError>,
+    ) -> Result<(), KvError> {
+        let _guard = self.exclusive();
+        if let Some(wal) = &self.wal {
+            wal.begin();
+        }
+        let tx = MemTx {
+            store: self,
+            undo: RefCell::default(),
+        };
+        let mut res = f(&TxView::new(&tx, tables));
+        if let Some(wal) = &self.wal {
+            res = res.and_then(|_| wal.commit());
+            wal.abort();
+        }
+        if res.is_err() {
+            tx.rollback();
+        }
+        res
+    }
+
+    fn dump(&self) -> Result<Box<dyn Iterator<Item = Result<WalPut, KvError>>>, KvError> {
+        // writers only wait for the tables to be listed and for the batch being read
+        let tables = {
+            let _guard = self.exclusive();
+            self.table_handles()
+        };
+        let lock = Some(Arc::clone(&self.lock));
+        Ok(Box::new(live_puts(tables, lock).map(Ok)))
+    }
+
+    fn load(
+        &self,
+        puts: &mut dyn Iterator<Item = Result<WalPut, KvError>>,
+    ) -> Result<usize, KvError> {
+        let _guard = self.exclusive();
+        let table = self
+            .tables
+            .iter()
+            .filter(|t| MemTable::live_len(t.value()) > 0)
+            .map(|t| t.key().clone())
+            .min();
+        if let Some(table) = table {
+            return Err(KvError::TableExists(table));
+        }
+
+        let now = now_ms();
+        let mut count = 0;
+        for put in puts {
+            let put = put?;
+            if expired_by(&put, now) {
+                continue;
+            }
+            let record = Record {
+                value: put.value.unwrap_or_default(),
+                expire_at: Some(put.expire_at).filter(|&t| t > 0),
+            };
+            self.put(&put.table, &put.key, record)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// The primitives without locking; callers hold the lock.
+impl TxStorage for MemTable {
+    fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        let table = self.get_or_create(table);
+        Ok(get_live(&table, key).map(|r| r.value))
+    }
+
+    fn set(&self, table: &str, key: &str, value: Value) -> Result<Option<Value>, KvError> {
+        self.put(table, key, Record::new(value))
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: &str,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        let record = Record {
+            value,
+            expire_at: Some(deadline_after(ttl)),
+        };
+        self.put(table, key, record)
+    }
+
+    fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        let old = match table.records.entry(key.into()) {
+            Entry::Occupied(e) => {
+                self.log_del(name, key)?;
+                Some(table.take(e))
+            }
+            Entry::Vacant(_) => None,
+        };
+        Ok(old.filter(|r| !r.is_expired(now)).map(|r| r.value))
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        let updated = match table.records.get_mut(key) {
+            Some(mut r) if !r.is_expired(now) => {
+                let record = Record {
+                    value: r.value.clone(),
+                    expire_at: Some(deadline_after(ttl)),
+                };
+                self.log_put(name, key, &record)?;
+                *r = record;
+                true
+            }
+            _ => false,
+        };
+        Ok(updated)
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        let table = self.get_or_create(table);
+        Ok(get_live(&table, key).and_then(|r| r.expire_at.map(remaining)))
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        let name = table;
+        let table = self.get_or_create(table);
+        let now = now_ms();
+        let updated = match table.records.get_mut(key) {
+            Some(mut r) if !r.is_expired(now) && r.expire_at.is_some() => {
+                let record = Record::new(r.value.clone());
+                self.log_put(name, key, &record)?;
+                *r = record;
+                true
+            }
+            _ => false,
+        };
+        Ok(updated)
+    }
+}
+
+/// A transaction over a locked `MemTable`, remembering how to undo its changes.
+struct MemTx<'a> {
+    store: &'a MemTable,
+    undo: RefCell<Vec<(String, String, Option<Record>)>>,
+}
+
+impl MemTx<'_> {
+    fn save(&self, table: &str, key: &str) {
+        let record = self
+            .store
+            .tables
+            .get(table)
+            .and_then(|t| t.records.get(key).map(|r| r.value().clone()));
+        self.undo
+            .borrow_mut()
+            .push((table.into(), key.into(), record));
+    }
+
+    /// Restore every saved record, the oldest state of a key last.
+    fn rollback(self) {
+        for (table, key, record) in self.undo.into_inner().into_iter().rev() {
+            let table = self.store.get_or_create(&table);
+            match record {
+                Some(record) => table.insert(key, record),
+                None => table.remove(&key),
+            };
+        }
+    }
+}
+
+impl TxStorage for MemTx<'_> {
+    fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        TxStorage::get(self.store, table, key)
+    }
+
+    fn set(&self, table: &str, key: &str, value: Value) -> Result<Option<Value>, KvError> {
+        self.save(table, key);
+        TxStorage::set(self.store, table, key, value)
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: &str,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        self.save(table, key);
+        TxStorage::set_with_ttl(self.store, table, key, value, ttl)
+    }
+
+    fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        self.save(table, key);
+        TxStorage::del(self.store, table, key)
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        self.save(table, key);
+        TxStorage::expire(self.store, table, key, ttl)
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        TxStorage::ttl(self.store, table, key)
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        self.save(table, key);
+        TxStorage::persist(self.store, table, key)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::storage::memory::MemTable;
-    use crate::{test_basi_interface, test_get_all, test_get_iter};
+    use crate::{
+        test_basi_interface, test_compare_and_swap, test_expire, test_get_all, test_get_iter,
+        test_incr, test_purge_expired, test_range, test_scan, test_table_management,
+        test_transaction,
+    };
+    use crate::{FsyncPolicy, KvError, Kvpair, Storage};
+    use std::ops::Bound;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
 
     #[test]
     fn mem_table_basic_interface_should_work() {
@@ -83,4 +950,241 @@ mod tests {
         let store = MemTable::new();
         test_get_iter(&store);
     }
+
+    #[test]
+    fn mem_table_expire_should_work() {
+        let store = MemTable::new();
+        test_expire(&store);
+    }
+
+    #[test]
+    fn mem_table_purge_expired_should_work() {
+        let store = MemTable::new();
+        test_purge_expired(&store);
+    }
+
+    #[test]
+    fn mem_table_incr_should_work() {
+        let store = MemTable::new();
+        test_incr(&store);
+    }
+
+    #[test]
+    fn mem_table_compare_and_swap_should_work() {
+        let store = MemTable::new();
+        test_compare_and_swap(&store);
+    }
+
+    #[test]
+    fn mem_table_scan_should_work() {
+        let store = MemTable::new();
+        test_scan(&store);
+    }
+
+    #[test]
+    fn mem_table_scan_should_page_past_one_batch() {
+        let store = MemTable::new();
+        for i in 0..300 {
+            store.set("t", format!("a{:03}", i), i.into()).unwrap();
+        }
+        store.set("t", "b".into(), 0.into()).unwrap();
+
+        let (mut seen, mut cursor) = (Vec::new(), String::new());
+        loop {
+            let (pairs, next) = store.scan("t", &cursor, 40, "a", "*5").unwrap();
+            seen.extend(pairs.into_iter().map(|p| p.key));
+            match next {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        let expected: Vec<_> = (0..300)
+            .filter(|i| i % 10 == 5)
+            .map(|i| format!("a{:03}", i))
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn mem_table_range_should_work() {
+        let store = MemTable::new();
+        test_range(&store);
+    }
+
+    #[test]
+    fn mem_table_range_should_walk_past_one_batch() {
+        let store = MemTable::new();
+        for i in 0..200 {
+            store.set("t", format!("k{:03}", i), i.into()).unwrap();
+        }
+        store.del("t", "k100").unwrap();
+
+        let pairs = store
+            .range("t", Bound::Excluded("k010"), Bound::Unbounded, false, 150)
+            .unwrap();
+        let keys: Vec<_> = pairs.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys.len(), 150);
+        assert_eq!((keys[0], keys[88], keys[89]), ("k011", "k099", "k101"));
+
+        let pairs = store
+            .range("t", Bound::Unbounded, Bound::Included("k150"), true, 100)
+            .unwrap();
+        let keys: Vec<_> = pairs.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!((keys[0], keys[49], keys[50]), ("k150", "k101", "k099"));
+        assert_eq!(keys[99], "k050");
+    }
+
+    #[test]
+    fn mem_table_table_management_should_work() {
+        let store = MemTable::new();
+        test_table_management(&store);
+    }
+
+    #[test]
+    fn mem_table_transaction_should_work() {
+        let store = MemTable::new();
+        test_transaction(&store);
+    }
+
+    #[test]
+    fn mem_table_with_wal_should_work() {
+        let dir = tempdir().unwrap();
+        let tests: [fn(&MemTable); 6] = [
+            |s| test_basi_interface(s),
+            |s| test_expire(s),
+            |s| test_incr(s),
+            |s| test_compare_and_swap(s),
+            |s| test_table_management(s),
+            |s| test_transaction(s),
+        ];
+        for (i, test) in tests.iter().enumerate() {
+            let path = dir.path().join(format!("{}.wal", i));
+            test(&MemTable::with_wal(path, FsyncPolicy::Never).unwrap());
+        }
+    }
+
+    #[test]
+    fn mem_table_should_be_restored_from_wal() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+        let store = MemTable::with_wal(&path, FsyncPolicy::Always).unwrap();
+        store.set("t1", "a".into(), 1.into()).unwrap();
+        store.set("t1", "b".into(), 2.into()).unwrap();
+        store.set("t1", "gone".into(), 3.into()).unwrap();
+        store.del("t1", "gone").unwrap();
+        store.incr("t1", "a", 10.into()).unwrap();
+        store.expire("t1", "b", Duration::from_secs(60)).unwrap();
+        store.set("t2", "c".into(), "x".into()).unwrap();
+        store.rename_table("t2", "t3").unwrap();
+        store.set("t4", "d".into(), "y".into()).unwrap();
+        store.drop_table("t4").unwrap();
+        store.set("t5", "e".into(), 5.into()).unwrap();
+        store.expire("t5", "e", Duration::from_millis(1)).unwrap();
+
+        let cas = store.compare_and_swap("t1", "c", None, Some(4.into()));
+        assert_eq!(cas.unwrap(), Ok(()));
+        // only committed transactions are logged
+        let tables = vec!["t1".to_string()];
+        let res = store.transaction(&tables, &mut |tx| {
+            tx.set("t1", "tx".into(), 7.into())?;
+            Err(KvError::Internal("abort".into()))
+        });
+        assert!(res.is_err());
+        drop(store);
+        thread::sleep(Duration::from_millis(5));
+
+        let store = MemTable::with_wal(&path, FsyncPolicy::Always).unwrap();
+        assert_eq!(store.get("t1", "a").unwrap(), Some(11.into()));
+        assert_eq!(store.get("t1", "b").unwrap(), Some(2.into()));
+        assert!(store.ttl("t1", "b").unwrap().is_some());
+        assert_eq!(store.get("t1", "c").unwrap(), Some(4.into()));
+        assert_eq!(store.get("t1", "gone").unwrap(), None);
+        assert_eq!(store.get("t1", "tx").unwrap(), None);
+        assert_eq!(store.tables().unwrap(), vec!["t1", "t3"]);
+        assert_eq!(store.get("t3", "c").unwrap(), Some("x".into()));
+    }
+
+    #[test]
+    fn mem_table_should_be_restored_from_snapshot_and_wal() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+        let store = MemTable::with_wal(&path, FsyncPolicy::Never).unwrap();
+        assert!(MemTable::new().snapshot().is_err());
+
+        store.set("t1", "a".into(), 1.into()).unwrap();
+        store.set("t1", "b".into(), 2.into()).unwrap();
+        store.expire("t1", "b", Duration::from_secs(60)).unwrap();
+        store.set("t2", "c".into(), 3.into()).unwrap();
+        store.snapshot().unwrap();
+        assert!(store.wal_len().unwrap() < 32);
+
+        store.del("t1", "a").unwrap();
+        store.set("t2", "d".into(), 4.into()).unwrap();
+        drop(store);
+
+        let store = MemTable::with_wal(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(store.get("t1", "a").unwrap(), None);
+        assert_eq!(store.get("t1", "b").unwrap(), Some(2.into()));
+        assert!(store.ttl("t1", "b").unwrap().is_some());
+        let mut pairs = store.get_all("t2").unwrap();
+        pairs.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(
+            pairs,
+            vec![Kvpair::new("c", 3.into()), Kvpair::new("d", 4.into())]
+        );
+    }
+
+    #[test]
+    fn mem_table_dump_should_not_stop_writers() {
+        let store = MemTable::new();
+        for i in 0..200 {
+            store.set("t", format!("k{:03}", i), i.into()).unwrap();
+        }
+
+        let mut puts = store.dump().unwrap();
+        let first = puts.next().unwrap().unwrap();
+        assert_eq!(first.key, "k000");
+        // neither a write nor a table operation waits for the rest of the dump
+        store.set("t", "k199".into(), 0.into()).unwrap();
+        store.rename_table("t", "u").unwrap();
+        let rest: Vec<_> = puts.map(|put| put.unwrap()).collect();
+        assert_eq!(rest.len(), 199);
+        assert_eq!(rest[198].value, Some(0.into()));
+    }
+
+    #[test]
+    fn mem_table_snapshot_should_not_stop_writers() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+        let store = MemTable::with_wal(&path, FsyncPolicy::Never).unwrap();
+        for i in 0..1000 {
+            store.set("t", format!("k{}", i), i.into()).unwrap();
+        }
+
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                for i in 0..2000 {
+                    let key = format!("k{}", i % 1000);
+                    match i % 3 {
+                        0 => store.del("t", &key).map(|_| ()),
+                        _ => store.set("t", key, (i * 2).into()).map(|_| ()),
+                    }
+                    .unwrap();
+                }
+            });
+            while !writer.is_finished() {
+                store.snapshot().unwrap();
+            }
+        });
+
+        let sorted = |store: &MemTable| {
+            let mut pairs = store.get_all("t").unwrap();
+            pairs.sort_by(|a, b| a.key.cmp(&b.key));
+            pairs
+        };
+        let expected = sorted(&store);
+        drop(store);
+        let store = MemTable::with_wal(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(sorted(&store), expected);
+    }
 }
@@ -1,17 +1,34 @@
+mod backup;
+mod copy;
 mod memory;
 mod sleddb;
+mod wal;
 
+pub use backup::{backup, backup_path, restore};
+pub use copy::{copy_store, CopyProgress};
 pub use memory::*;
 pub use sleddb::*;
+pub use wal::FsyncPolicy;
 
 use crate::error::KvError;
-use crate::{Kvpair, Value};
+use crate::{Kvpair, Value, WalPut};
+use std::ops::Bound;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub trait Storage {
     fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError>;
 
     fn set(&self, table: &str, key: String, value: Value) -> Result<Option<Value>, KvError>;
 
+    /// Set a value together with its time-to-live, so the key is never seen without it.
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: String,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError>;
+
     fn contains(&self, table: &str, key: &str) -> Result<bool, KvError>;
 
     fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError>;
@@ -19,6 +36,393 @@ pub trait Storage {
     fn get_all(&self, table: &str) -> Result<Vec<Kvpair>, KvError>;
 
     fn get_iter(&self, table: &str) -> Result<Box<dyn Iterator<Item = Kvpair>>, KvError>;
+
+    /// Set a time-to-live on an existing key, returns false if the key does not exist.
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError>;
+
+    /// Remaining time-to-live of a key, `None` if the key has no expiry or does not exist.
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError>;
+
+    /// Remove the time-to-live of a key, returns false if the key had no expiry.
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError>;
+
+    /// Reclaim all expired keys, returns how many keys were removed.
+    fn purge_expired(&self) -> Result<usize, KvError>;
+
+    /// Atomically add `delta` to a numeric value and return the new value, a missing key counts as 0.
+    fn incr(&self, table: &str, key: &str, delta: Value) -> Result<Value, KvError>;
+
+    /// Replace the value of a key only if it currently equals `expected` (`None` means absent),
+    /// a `new` of `None` deletes the key. On conflict the current value is returned as `Err`.
+    fn compare_and_swap(
+        &self,
+        table: &str,
+        key: &str,
+        expected: Option<Value>,
+        new: Option<Value>,
+    ) -> Result<Result<(), Option<Value>>, KvError>;
+
+    /// Return up to `limit` pairs whose keys sort after `cursor`, filtered by key `prefix` and
+    /// glob `pattern`, with the cursor to resume from (`None` once the table is exhausted).
+    fn scan(
+        &self,
+        table: &str,
+        cursor: &str,
+        limit: usize,
+        prefix: &str,
+        pattern: &str,
+    ) -> Result<(Vec<Kvpair>, Option<String>), KvError>;
+
+    /// Return up to `limit` pairs whose keys fall between `start` and `end`, in key order
+    /// or in reverse key order.
+    fn range(
+        &self,
+        table: &str,
+        start: Bound<&str>,
+        end: Bound<&str>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Vec<Kvpair>, KvError>;
+
+    /// Names of all tables holding at least one key, in name order.
+    fn tables(&self) -> Result<Vec<String>, KvError>;
+
+    fn table_len(&self, table: &str) -> Result<usize, KvError>;
+
+    /// Remove a table with all of its keys, returns false if the table did not exist.
+    fn drop_table(&self, table: &str) -> Result<bool, KvError>;
+
+    /// Move all keys of `from` into `to`, which must not exist yet.
+    fn rename_table(&self, from: &str, to: &str) -> Result<(), KvError>;
+
+    /// Run `f` over `tables` so that either all of its changes land or none do, the latter
+    /// whenever `f` fails. The store handed to `f` only supports single-key operations on
+    /// those tables, and `f` may be run more than once.
+    fn transaction(
+        &self,
+        tables: &[String],
+        f: &mut dyn FnMut(&dyn Storage) -> Result<(), KvError>,
+    ) -> Result<(), KvError>;
+
+    /// Every live key of every table with its expiry, each given as the put which
+    /// recreates it. Writers carry on meanwhile, so a key is read at some moment during
+    /// the dump; keys of tables created after it started may be missing.
+    fn dump(&self) -> Result<Box<dyn Iterator<Item = Result<WalPut, KvError>>>, KvError>;
+
+    /// Recreate dumped keys in a store without tables, keeping writers out until all are
+    /// in. Keys which expired meanwhile are skipped; returns the number of keys loaded.
+    fn load(
+        &self,
+        puts: &mut dyn Iterator<Item = Result<WalPut, KvError>>,
+    ) -> Result<usize, KvError>;
+}
+
+/// Single-key operations a store provides inside a transaction; see `TxView`.
+pub(crate) trait TxStorage {
+    fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError>;
+
+    fn set(&self, table: &str, key: &str, value: Value) -> Result<Option<Value>, KvError>;
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: &str,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError>;
+
+    fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError>;
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError>;
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError>;
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError>;
+}
+
+/// The `Storage` handed to transactions, built on the primitives of a `TxStorage` and
+/// limited to the tables the transaction was opened for.
+pub(crate) struct TxView<'a> {
+    tx: &'a dyn TxStorage,
+    tables: &'a [String],
+}
+
+impl<'a> TxView<'a> {
+    pub fn new(tx: &'a dyn TxStorage, tables: &'a [String]) -> Self {
+        Self { tx, tables }
+    }
+
+    fn check(&self, table: &str) -> Result<(), KvError> {
+        match self.tables.iter().any(|t| t == table) {
+            true => Ok(()),
+            false => Err(KvError::InvalidCommand(format!(
+                "Table {} is not part of the transaction",
+                table
+            ))),
+        }
+    }
+
+    /// Set a value, keeping the expiry of the key.
+    fn replace(&self, table: &str, key: &str, value: Value) -> Result<(), KvError> {
+        let ttl = self.ttl(table, key)?;
+        self.tx.set(table, key, value)?;
+        if let Some(ttl) = ttl {
+            self.tx.expire(table, key, ttl)?;
+        }
+        Ok(())
+    }
+}
+
+fn unsupported<T>(op: &str) -> Result<T, KvError> {
+    Err(KvError::InvalidCommand(format!(
+        "{} is not supported in a transaction",
+        op
+    )))
+}
+
+impl Storage for TxView<'_> {
+    fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        self.check(table)?;
+        self.tx.get(table, key)
+    }
+
+    fn set(&self, table: &str, key: String, value: Value) -> Result<Option<Value>, KvError> {
+        self.check(table)?;
+        self.tx.set(table, &key, value)
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: String,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        self.check(table)?;
+        self.tx.set_with_ttl(table, &key, value, ttl)
+    }
+
+    fn contains(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        Ok(self.get(table, key)?.is_some())
+    }
+
+    fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        self.check(table)?;
+        self.tx.del(table, key)
+    }
+
+    fn get_all(&self, _table: &str) -> Result<Vec<Kvpair>, KvError> {
+        unsupported("get_all")
+    }
+
+    fn get_iter(&self, _table: &str) -> Result<Box<dyn Iterator<Item = Kvpair>>, KvError> {
+        unsupported("get_iter")
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        self.check(table)?;
+        self.tx.expire(table, key, ttl)
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        self.check(table)?;
+        self.tx.ttl(table, key)
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        self.check(table)?;
+        self.tx.persist(table, key)
+    }
+
+    fn purge_expired(&self) -> Result<usize, KvError> {
+        unsupported("purge_expired")
+    }
+
+    fn incr(&self, table: &str, key: &str, delta: Value) -> Result<Value, KvError> {
+        let value = incr_value(self.get(table, key)?, &delta)?;
+        self.replace(table, key, value.clone())?;
+        Ok(value)
+    }
+
+    fn compare_and_swap(
+        &self,
+        table: &str,
+        key: &str,
+        expected: Option<Value>,
+        new: Option<Value>,
+    ) -> Result<Result<(), Option<Value>>, KvError> {
+        let current = self.get(table, key)?;
+        if current != expected {
+            return Ok(Err(current));
+        }
+        match new {
+            Some(value) => self.replace(table, key, value)?,
+            None => {
+                self.tx.del(table, key)?;
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    fn scan(
+        &self,
+        _table: &str,
+        _cursor: &str,
+        _limit: usize,
+        _prefix: &str,
+        _pattern: &str,
+    ) -> Result<(Vec<Kvpair>, Option<String>), KvError> {
+        unsupported("scan")
+    }
+
+    fn range(
+        &self,
+        _table: &str,
+        _start: Bound<&str>,
+        _end: Bound<&str>,
+        _reverse: bool,
+        _limit: usize,
+    ) -> Result<Vec<Kvpair>, KvError> {
+        unsupported("range")
+    }
+
+    fn tables(&self) -> Result<Vec<String>, KvError> {
+        unsupported("tables")
+    }
+
+    fn table_len(&self, _table: &str) -> Result<usize, KvError> {
+        unsupported("table_len")
+    }
+
+    fn drop_table(&self, _table: &str) -> Result<bool, KvError> {
+        unsupported("drop_table")
+    }
+
+    fn rename_table(&self, _from: &str, _to: &str) -> Result<(), KvError> {
+        unsupported("rename_table")
+    }
+
+    fn transaction(
+        &self,
+        _tables: &[String],
+        _f: &mut dyn FnMut(&dyn Storage) -> Result<(), KvError>,
+    ) -> Result<(), KvError> {
+        unsupported("transaction")
+    }
+
+    fn dump(&self) -> Result<Box<dyn Iterator<Item = Result<WalPut, KvError>>>, KvError> {
+        unsupported("dump")
+    }
+
+    fn load(
+        &self,
+        _puts: &mut dyn Iterator<Item = Result<WalPut, KvError>>,
+    ) -> Result<usize, KvError> {
+        unsupported("load")
+    }
+}
+
+/// True if no key can fall between `start` and `end`.
+pub(crate) fn is_empty_range(start: Bound<&str>, end: Bound<&str>) -> bool {
+    match (start, end) {
+        (Bound::Included(s), Bound::Included(e)) => s > e,
+        (Bound::Included(s), Bound::Excluded(e))
+        | (Bound::Excluded(s), Bound::Included(e))
+        | (Bound::Excluded(s), Bound::Excluded(e)) => s >= e,
+        _ => false,
+    }
+}
+
+/// Cut a key ordered result holding up to `limit + 1` pairs into a page and its next cursor.
+pub(crate) fn paginate(mut pairs: Vec<Kvpair>, limit: usize) -> (Vec<Kvpair>, Option<String>) {
+    if pairs.len() <= limit {
+        return (pairs, None);
+    }
+    pairs.truncate(limit);
+    let cursor = pairs.last().map(|p| p.key.clone());
+    (pairs, cursor)
+}
+
+/// Match a key against a glob pattern supporting `*` and `?`, an empty pattern matches all.
+pub(crate) fn glob_match(pattern: &str, key: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let k: Vec<char> = key.chars().collect();
+    if p.is_empty() {
+        return true;
+    }
+
+    let (mut pi, mut ki) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ki < k.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ki));
+                pi += 1;
+            }
+            Some(c) if *c == '?' || *c == k[ki] => {
+                pi += 1;
+                ki += 1;
+            }
+            _ => match star {
+                Some((sp, sk)) => {
+                    pi = sp + 1;
+                    ki = sk + 1;
+                    star = Some((sp, sk + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Add `delta` to the current value. Integers stay integers unless a float is added to them.
+pub(crate) fn incr_value(current: Option<Value>, delta: &Value) -> Result<Value, KvError> {
+    use crate::value::Value::{Float, Integer};
+
+    let current = current.unwrap_or_else(|| 0.into());
+    match (&current.value, &delta.value) {
+        (Some(Integer(a)), Some(Integer(b))) => {
+            a.checked_add(*b).map(Into::into).ok_or_else(|| {
+                KvError::InvalidCommand(format!("Increment {} by {} would overflow", a, b))
+            })
+        }
+        (Some(Integer(a)), Some(Float(b))) => float_value(*a as f64 + b),
+        (Some(Float(a)), Some(Float(b))) => float_value(a + b),
+        (_, Some(Integer(_))) => Err(KvError::ConvertError(current, "integer")),
+        (_, Some(Float(_))) => Err(KvError::ConvertError(current, "float")),
+        _ => Err(KvError::ConvertError(delta.clone(), "number")),
+    }
+}
+
+fn float_value(f: f64) -> Result<Value, KvError> {
+    match f.is_finite() {
+        true => Ok(f.into()),
+        false => Err(KvError::InvalidCommand(
+            "Increment would produce NaN or Infinity".into(),
+        )),
+    }
+}
+
+/// Current unix time in milliseconds, used as the clock for key expiration.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+pub(crate) fn deadline_after(ttl: Duration) -> u64 {
+    now_ms().saturating_add(ttl.as_millis() as u64)
+}
+
+/// Whether a dumped key expired by `now`, so it is not worth loading.
+pub(crate) fn expired_by(put: &WalPut, now: u64) -> bool {
+    put.expire_at > 0 && put.expire_at <= now
+}
+
+pub(crate) fn remaining(deadline: u64) -> Duration {
+    Duration::from_millis(deadline.saturating_sub(now_ms()))
 }
 
 pub struct StorageIter<T> {
@@ -101,3 +505,347 @@ pub fn test_get_iter(store: &dyn Storage) {
         ]
     )
 }
+
+#[cfg(test)]
+pub fn test_expire(store: &dyn Storage) {
+    let _v = store.set("t1", "k1".into(), "v1".into());
+    let _v = store.set("t1", "k2".into(), "v2".into());
+
+    assert_eq!(store.ttl("t1", "k1"), Ok(None));
+    assert_eq!(
+        store.expire("t1", "k1", Duration::from_millis(20)),
+        Ok(true)
+    );
+    assert_eq!(
+        store.expire("t1", "k3", Duration::from_millis(20)),
+        Ok(false)
+    );
+    assert!(store.ttl("t1", "k1").unwrap().unwrap() <= Duration::from_millis(20));
+
+    assert_eq!(
+        store.expire("t1", "k2", Duration::from_millis(20)),
+        Ok(true)
+    );
+    assert_eq!(store.persist("t1", "k2"), Ok(true));
+    assert_eq!(store.persist("t1", "k2"), Ok(false));
+    assert_eq!(store.ttl("t1", "k2"), Ok(None));
+
+    std::thread::sleep(Duration::from_millis(30));
+
+    assert_eq!(store.get("t1", "k1"), Ok(None));
+    assert_eq!(store.contains("t1", "k1"), Ok(false));
+    assert_eq!(store.ttl("t1", "k1"), Ok(None));
+    assert_eq!(store.get("t1", "k2"), Ok(Some("v2".into())));
+
+    // set on an expired key returns no previous value and clears the expiry
+    let _v = store.set("t1", "k3".into(), "v3".into());
+    assert_eq!(
+        store.expire("t1", "k3", Duration::from_millis(10)),
+        Ok(true)
+    );
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(store.set("t1", "k3".into(), "v4".into()), Ok(None));
+    assert_eq!(store.ttl("t1", "k3"), Ok(None));
+
+    // a value set with a ttl carries it from the start, replacing the old one
+    let ttl = Duration::from_millis(10);
+    assert_eq!(
+        store.set_with_ttl("t2", "k1".into(), "v1".into(), ttl),
+        Ok(None)
+    );
+    assert!(store.ttl("t2", "k1").unwrap().unwrap() <= ttl);
+    let ttl = Duration::from_secs(60);
+    let old = store.set_with_ttl("t2", "k1".into(), "v2".into(), ttl);
+    assert_eq!(old, Ok(Some("v1".into())));
+    assert!(store.ttl("t2", "k1").unwrap().unwrap() > Duration::from_millis(10));
+
+    let data: Vec<_> = store.get_iter("t1").unwrap().collect();
+    assert_eq!(data.len(), 2);
+}
+
+#[cfg(test)]
+pub fn test_purge_expired(store: &dyn Storage) {
+    let _v = store.set("t1", "k1".into(), "v1".into());
+    let _v = store.set("t1", "k2".into(), "v2".into());
+    let _v = store.set("t2", "k1".into(), "v1".into());
+
+    assert_eq!(
+        store.expire("t1", "k1", Duration::from_millis(10)),
+        Ok(true)
+    );
+    assert_eq!(
+        store.expire("t2", "k1", Duration::from_millis(10)),
+        Ok(true)
+    );
+    assert_eq!(store.purge_expired(), Ok(0));
+
+    std::thread::sleep(Duration::from_millis(20));
+
+    assert_eq!(store.purge_expired(), Ok(2));
+    assert_eq!(
+        store.get_all("t1"),
+        Ok(vec![Kvpair::new("k2", "v2".into())])
+    );
+    assert_eq!(store.get_all("t2"), Ok(vec![]));
+}
+
+#[cfg(test)]
+pub fn test_incr(store: &(dyn Storage + Sync)) {
+    assert_eq!(store.incr("t1", "k1", 5.into()), Ok(5.into()));
+    assert_eq!(store.incr("t1", "k1", (-2).into()), Ok(3.into()));
+    assert_eq!(store.incr("t1", "k1", 1.5.into()), Ok(4.5.into()));
+    assert_eq!(store.get("t1", "k1"), Ok(Some(4.5.into())));
+    assert_eq!(store.incr("t1", "k2", 0.5.into()), Ok(0.5.into()));
+
+    assert_eq!(
+        store.incr("t1", "k1", 1.into()),
+        Err(KvError::ConvertError(4.5.into(), "integer"))
+    );
+
+    let _v = store.set("t1", "k3".into(), "v3".into());
+    assert_eq!(
+        store.incr("t1", "k3", 1.into()),
+        Err(KvError::ConvertError("v3".into(), "integer"))
+    );
+    assert_eq!(store.get("t1", "k3"), Ok(Some("v3".into())));
+
+    assert!(store.incr("t1", "k4", 1.0.into()).is_ok());
+    assert!(store.incr("t1", "k5", "1".into()).is_err());
+    assert_eq!(store.contains("t1", "k5"), Ok(false));
+
+    // a lapsed key counts up from nothing and loses its deadline
+    store.set("t1", "lapsed".into(), 10.into()).unwrap();
+    store
+        .expire("t1", "lapsed", Duration::from_millis(1))
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(store.incr("t1", "lapsed", 1.into()), Ok(1.into()));
+    assert_eq!(store.ttl("t1", "lapsed"), Ok(None));
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    store.incr("t1", "counter", 1.into()).unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(store.get("t1", "counter"), Ok(Some(400.into())));
+}
+
+#[cfg(test)]
+pub fn test_compare_and_swap(store: &dyn Storage) {
+    let v = store.compare_and_swap("t1", "k1", None, Some("v1".into()));
+    assert_eq!(v, Ok(Ok(())));
+    let v = store.compare_and_swap("t1", "k1", None, Some("v2".into()));
+    assert_eq!(v, Ok(Err(Some("v1".into()))));
+    let v = store.compare_and_swap("t1", "k1", Some("v0".into()), Some("v2".into()));
+    assert_eq!(v, Ok(Err(Some("v1".into()))));
+    let v = store.compare_and_swap("t1", "k1", Some("v1".into()), Some("v2".into()));
+    assert_eq!(v, Ok(Ok(())));
+    assert_eq!(store.get("t1", "k1"), Ok(Some("v2".into())));
+
+    let v = store.compare_and_swap("t1", "k2", Some("v1".into()), Some("v2".into()));
+    assert_eq!(v, Ok(Err(None)));
+    assert_eq!(store.contains("t1", "k2"), Ok(false));
+
+    let v = store.compare_and_swap("t1", "k1", Some("v2".into()), None);
+    assert_eq!(v, Ok(Ok(())));
+    assert_eq!(store.contains("t1", "k1"), Ok(false));
+
+    // an expired key compares as absent
+    let _v = store.set("t1", "k3".into(), "v3".into());
+    let _v = store.expire("t1", "k3", Duration::from_millis(10));
+    std::thread::sleep(Duration::from_millis(20));
+    let v = store.compare_and_swap("t1", "k3", None, Some("v4".into()));
+    assert_eq!(v, Ok(Ok(())));
+    assert_eq!(store.ttl("t1", "k3"), Ok(None));
+
+    // a swap keeps the deadline of a live key, and a lapsed one no longer matches
+    let _v = store.set("t1", "k5".into(), "v5".into());
+    let _v = store.expire("t1", "k5", Duration::from_secs(60));
+    let v = store.compare_and_swap("t1", "k5", Some("v5".into()), Some("v6".into()));
+    assert_eq!(v, Ok(Ok(())));
+    assert!(store.ttl("t1", "k5").unwrap().is_some());
+    let _v = store.expire("t1", "k5", Duration::from_millis(10));
+    std::thread::sleep(Duration::from_millis(20));
+    let v = store.compare_and_swap("t1", "k5", Some("v6".into()), Some("v7".into()));
+    assert_eq!(v, Ok(Err(None)));
+    assert_eq!(store.get("t1", "k5"), Ok(None));
+}
+
+#[cfg(test)]
+pub fn test_scan(store: &dyn Storage) {
+    for i in 0..5 {
+        let _v = store.set("t1", format!("user-{}", i), i.into());
+        let _v = store.set("t1", format!("item-{}", i), i.into());
+    }
+    let _v = store.set("t2", "user-9".into(), 9.into());
+
+    let (pairs, cursor) = store.scan("t1", "", 3, "", "").unwrap();
+    let keys: Vec<_> = pairs.iter().map(|p| p.key.as_str()).collect();
+    assert_eq!(keys, vec!["item-0", "item-1", "item-2"]);
+    assert_eq!(cursor, Some("item-2".into()));
+
+    let mut cursor = String::new();
+    let mut keys = Vec::new();
+    loop {
+        let (pairs, next) = store.scan("t1", &cursor, 2, "user-", "").unwrap();
+        keys.extend(pairs.into_iter().map(|p| p.key));
+        match next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    assert_eq!(keys, vec!["user-0", "user-1", "user-2", "user-3", "user-4"]);
+
+    let (pairs, cursor) = store.scan("t1", "", 10, "", "*-9").unwrap();
+    assert!(pairs.is_empty());
+    assert_eq!(cursor, None);
+
+    let (pairs, cursor) = store.scan("t1", "item-1", 10, "", "*-?").unwrap();
+    assert_eq!(pairs.len(), 8);
+    assert_eq!(pairs[0], Kvpair::new("item-2", 2.into()));
+    assert_eq!(cursor, None);
+
+    let (pairs, _) = store.scan("t1", "", 10, "user", "*3").unwrap();
+    assert_eq!(pairs, vec![Kvpair::new("user-3", 3.into())]);
+}
+
+#[cfg(test)]
+pub fn test_range(store: &dyn Storage) {
+    for i in 0..6 {
+        let _v = store.set("t1", format!("2022-01-0{}", i), i.into());
+    }
+    let _v = store.set("t0", "2022-01-03".into(), 9.into());
+    let _v = store.set("t2", "2022-01-03".into(), 9.into());
+
+    let keys = |pairs: Vec<Kvpair>| -> Vec<String> { pairs.into_iter().map(|p| p.key).collect() };
+
+    let v = store.range(
+        "t1",
+        Bound::Included("2022-01-01"),
+        Bound::Excluded("2022-01-04"),
+        false,
+        usize::MAX,
+    );
+    assert_eq!(
+        keys(v.unwrap()),
+        vec!["2022-01-01", "2022-01-02", "2022-01-03"]
+    );
+
+    let v = store.range(
+        "t1",
+        Bound::Excluded("2022-01-01"),
+        Bound::Included("2022-01-04"),
+        true,
+        usize::MAX,
+    );
+    assert_eq!(
+        keys(v.unwrap()),
+        vec!["2022-01-04", "2022-01-03", "2022-01-02"]
+    );
+
+    let v = store.range("t1", Bound::Unbounded, Bound::Unbounded, false, 2);
+    assert_eq!(
+        v,
+        Ok(vec![
+            Kvpair::new("2022-01-00", 0.into()),
+            Kvpair::new("2022-01-01", 1.into())
+        ])
+    );
+
+    let v = store.range(
+        "t1",
+        Bound::Included("2022-01-04"),
+        Bound::Unbounded,
+        true,
+        usize::MAX,
+    );
+    assert_eq!(keys(v.unwrap()), vec!["2022-01-05", "2022-01-04"]);
+
+    let v = store.range(
+        "t1",
+        Bound::Included("2022-01-04"),
+        Bound::Excluded("2022-01-01"),
+        false,
+        10,
+    );
+    assert_eq!(v, Ok(vec![]));
+}
+
+#[cfg(test)]
+pub fn test_table_management(store: &dyn Storage) {
+    let _v = store.set("t1", "k1".into(), "v1".into());
+    let _v = store.set("t1", "k2".into(), "v2".into());
+    let _v = store.set("t2", "k1".into(), "v1".into());
+    let _v = store.set("t", "k1".into(), "v1".into());
+    let _v = store.get("t3", "k1");
+
+    assert_eq!(
+        store.tables(),
+        Ok(vec!["t".into(), "t1".into(), "t2".into()])
+    );
+    assert_eq!(store.table_len("t1"), Ok(2));
+    assert_eq!(store.table_len("t3"), Ok(0));
+
+    assert_eq!(store.drop_table("t"), Ok(true));
+    assert_eq!(store.drop_table("t"), Ok(false));
+    assert_eq!(store.tables(), Ok(vec!["t1".into(), "t2".into()]));
+    assert_eq!(store.get("t1", "k1"), Ok(Some("v1".into())));
+
+    let _v = store.expire("t1", "k2", Duration::from_millis(10));
+    assert_eq!(
+        store.rename_table("t1", "t2"),
+        Err(KvError::TableExists("t2".into()))
+    );
+    assert_eq!(
+        store.rename_table("t4", "t5"),
+        Err(KvError::TableNotFound("t4".into()))
+    );
+    assert_eq!(store.rename_table("t1", "t4"), Ok(()));
+    assert_eq!(store.tables(), Ok(vec!["t2".into(), "t4".into()]));
+    assert_eq!(store.get("t4", "k1"), Ok(Some("v1".into())));
+    assert_eq!(store.get("t1", "k1"), Ok(None));
+    assert!(store.ttl("t4", "k2").unwrap().is_some());
+
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(store.table_len("t4"), Ok(1));
+}
+
+#[cfg(test)]
+pub fn test_transaction(store: &dyn Storage) {
+    store.set("t1", "a".into(), 1.into()).unwrap();
+    store.expire("t1", "a", Duration::from_secs(60)).unwrap();
+    let tables = vec!["t1".to_string(), "t2".to_string()];
+
+    let res = store.transaction(&tables, &mut |tx| {
+        tx.set("t1", "a".into(), 2.into())?;
+        tx.set("t2", "b".into(), 3.into())?;
+        tx.del("t1", "a")?;
+        Err(KvError::Internal("abort".into()))
+    });
+    assert_eq!(res, Err(KvError::Internal("abort".into())));
+    assert_eq!(store.get("t1", "a"), Ok(Some(1.into())));
+    assert!(store.ttl("t1", "a").unwrap().is_some());
+    assert_eq!(store.get("t2", "b"), Ok(None));
+
+    let res = store.transaction(&tables, &mut |tx| {
+        tx.set("t3", "c".into(), 1.into())?;
+        Ok(())
+    });
+    assert!(matches!(res, Err(KvError::InvalidCommand(_))));
+
+    let res = store.transaction(&tables, &mut |tx| {
+        assert_eq!(tx.incr("t1", "a", 1.into())?, 2.into());
+        tx.compare_and_swap("t2", "b", None, Some(3.into()))?
+            .map_err(|_| KvError::Internal("conflict".into()))?;
+        assert_eq!(tx.get("t2", "b")?, Some(3.into()));
+        Ok(())
+    });
+    assert_eq!(res, Ok(()));
+    assert_eq!(store.get("t1", "a"), Ok(Some(2.into())));
+    assert!(store.ttl("t1", "a").unwrap().is_some());
+    assert_eq!(store.get("t2", "b"), Ok(Some(3.into())));
+}
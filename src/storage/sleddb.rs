@@ -1,60 +1,733 @@
-use crate::{KvError, Kvpair, Storage, StorageIter, Value};
-use sled::{Db, Error, IVec};
+use crate::storage::{
+    deadline_after, expired_by, glob_match, incr_value, is_empty_range, now_ms, paginate,
+    remaining, TxStorage, TxView,
+};
+use crate::{KvError, Kvpair, Storage, StorageIter, Value, WalPut};
+use sled::transaction::{
+    ConflictableTransactionError, TransactionError, TransactionalTree, UnabortableTransactionError,
+};
+use sled::{Batch, Db, Error, IVec, Transactional, Tree};
+use std::cell::RefCell;
+use std::ops::Bound;
 use std::path::Path;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Every table lives in its own tree named `table/<name>`, so user tables never clash with
+/// sled's default tree or the internal ones.
+const TABLE_TREE_PREFIX: &[u8] = b"table/";
+
+/// Key expirations of all tables, keyed by `expire_key(table, key)`.
+const EXPIRE_TREE: &str = "__kv_expire";
 
 #[derive(Debug)]
-pub struct SledDb(Db);
+pub struct SledDb {
+    db: Db,
+    expires: Tree,
+    /// Shared by writes, held exclusively while loading all tables or opening them to dump.
+    lock: RwLock<()>,
+}
 
 impl SledDb {
     pub fn new(path: impl AsRef<Path>) -> Self {
-        Self(sled::open(path).unwrap())
+        let db = sled::open(path).unwrap();
+        let expires = db.open_tree(EXPIRE_TREE).unwrap();
+        let store = Self {
+            db,
+            expires,
+            lock: RwLock::default(),
+        };
+        store.migrate().unwrap();
+        store
+    }
+
+    fn shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.lock.write().unwrap_or_else(PoisonError::into_inner)
     }
 
-    fn get_full_key(table: &str, key: &str) -> String {
-        format!("{}:{}", table, key)
+    /// The tree of a table, created if missing; only writes should call this.
+    fn tree(&self, table: &str) -> Result<Tree, KvError> {
+        Ok(self.db.open_tree(tree_name(table))?)
     }
 
-    fn get_table_prefix(table: &str) -> String {
-        format!("{}:", table)
+    /// The tree of a table if it exists, so reads never leave empty trees behind.
+    fn find_tree(&self, table: &str) -> Result<Option<Tree>, KvError> {
+        let name = tree_name(table);
+        match self.db.tree_names().iter().any(|n| n == &name) {
+            true => Ok(Some(self.db.open_tree(name)?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Move keys of the legacy layout, where all tables shared the default tree as `table:key`,
+    /// into per-table trees. The table name ends at the first ':' of a legacy key.
+    fn migrate(&self) -> Result<usize, KvError> {
+        let mut count = 0;
+        for item in self.db.iter() {
+            let (k, v) = item?;
+            let (table, key) = match split_legacy_key(&k) {
+                Some(v) => v,
+                None => {
+                    warn!("Skip migrating malformed key {:?}", k);
+                    continue;
+                }
+            };
+
+            self.tree(table)?.insert(key, v)?;
+            if let Some(deadline) = self.expires.remove(&k)? {
+                self.expires.insert(expire_key(table, key), deadline)?;
+            }
+            self.db.remove(&k)?;
+            count += 1;
+        }
+
+        if count > 0 {
+            self.db.flush()?;
+            info!("Migrated {} keys into per-table trees", count);
+        }
+        Ok(count)
+    }
+
+    fn has_live_keys(&self, table: &str) -> Result<bool, KvError> {
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(false),
+        };
+        for item in tree.iter() {
+            let (k, _) = item?;
+            if !self.is_expired(table, &k)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn is_expired(&self, table: &str, key: &[u8]) -> Result<bool, KvError> {
+        Ok(is_expired(
+            self.expires.get(expire_key(table, key))?.as_ref(),
+        ))
+    }
+
+    /// Remove the key if its deadline has passed, returns true if the key is expired.
+    fn evict_if_expired(&self, tree: &Tree, table: &str, key: &[u8]) -> Result<bool, KvError> {
+        let name = expire_key(table, key);
+        match self.expires.get(&name)? {
+            Some(deadline) if decode_deadline(&deadline) <= now_ms() => {
+                transaction(tree, &self.expires, |data, expires| {
+                    if let Some(deadline) = expires.get(&name)? {
+                        if decode_deadline(&deadline) <= now_ms() {
+                            expires.remove(name.as_slice())?;
+                            data.remove(key)?;
+                        }
+                    }
+                    Ok(())
+                })?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 }
 
 impl Storage for SledDb {
     fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
-        let name = SledDb::get_full_key(table, key);
-        let res = self.0.get(name.as_bytes())?.map(|v| v.as_ref().try_into());
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        if self.evict_if_expired(&tree, table, key.as_bytes())? {
+            return Ok(None);
+        }
+        let res = tree.get(key)?.map(|v| v.as_ref().try_into());
         flip(res)
     }
 
     fn set(&self, table: &str, key: String, value: Value) -> Result<Option<Value>, KvError> {
-        let name = SledDb::get_full_key(table, &key);
+        let _guard = self.shared();
+        let tree = self.tree(table)?;
+        let name = expire_key(table, key.as_bytes());
         let data: Vec<u8> = value.try_into()?;
 
-        let res = self.0.insert(name, data)?.map(|v| v.as_ref().try_into());
-        flip(res)
+        let old = transaction(&tree, &self.expires, |tree, expires| {
+            let deadline = expires.remove(name.as_slice())?;
+            let old = tree.insert(key.as_bytes(), data.as_slice())?;
+            Ok(old.filter(|_| !is_expired(deadline.as_ref())))
+        })?;
+        flip(old.map(|v| v.as_ref().try_into()))
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: String,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        let _guard = self.shared();
+        let tree = self.tree(table)?;
+        let name = expire_key(table, key.as_bytes());
+        let data: Vec<u8> = value.try_into()?;
+        let deadline = deadline_after(ttl).to_be_bytes();
+
+        let old = transaction(&tree, &self.expires, |tree, expires| {
+            let old_deadline = expires.insert(name.as_slice(), &deadline)?;
+            let old = tree.insert(key.as_bytes(), data.as_slice())?;
+            Ok(old.filter(|_| !is_expired(old_deadline.as_ref())))
+        })?;
+        flip(old.map(|v| v.as_ref().try_into()))
     }
 
     fn contains(&self, table: &str, key: &str) -> Result<bool, KvError> {
-        let name = SledDb::get_full_key(table, key);
-        Ok(self.0.contains_key(name)?)
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(false),
+        };
+        if self.evict_if_expired(&tree, table, key.as_bytes())? {
+            return Ok(false);
+        }
+        Ok(tree.contains_key(key)?)
     }
 
     fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
-        let name = SledDb::get_full_key(table, key);
-        let res = self.0.remove(name)?.map(|v| v.as_ref().try_into());
-        flip(res)
+        let _guard = self.shared();
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        let name = expire_key(table, key.as_bytes());
+        let old = transaction(&tree, &self.expires, |tree, expires| {
+            let deadline = expires.remove(name.as_slice())?;
+            let old = tree.remove(key.as_bytes())?;
+            Ok(old.filter(|_| !is_expired(deadline.as_ref())))
+        })?;
+        flip(old.map(|v| v.as_ref().try_into()))
     }
 
     fn get_all(&self, table: &str) -> Result<Vec<Kvpair>, KvError> {
-        let prefix = SledDb::get_table_prefix(table);
-        let res = self.0.scan_prefix(prefix).map(|v| v.into()).collect();
-        Ok(res)
+        Ok(self.get_iter(table)?.collect())
     }
 
     fn get_iter(&self, table: &str) -> Result<Box<dyn Iterator<Item = Kvpair>>, KvError> {
-        let prefix = SledDb::get_table_prefix(table);
-        let iter = StorageIter::new(self.0.scan_prefix(prefix));
-        Ok(Box::new(iter))
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(Box::new(std::iter::empty())),
+        };
+        let expires = self.expires.clone();
+        let table = table.to_string();
+        let iter = tree.iter().filter(move |v| match v {
+            Ok((k, _)) => {
+                let deadline = expires.get(expire_key(&table, k)).ok().flatten();
+                !is_expired(deadline.as_ref())
+            }
+            Err(_) => true,
+        });
+        Ok(Box::new(StorageIter::new(iter)))
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        let _guard = self.shared();
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(false),
+        };
+        if self.evict_if_expired(&tree, table, key.as_bytes())? {
+            return Ok(false);
+        }
+        let name = expire_key(table, key.as_bytes());
+        let deadline = deadline_after(ttl).to_be_bytes();
+        transaction(&tree, &self.expires, |tree, expires| {
+            if tree.get(key)?.is_none() {
+                return Ok(false);
+            }
+            expires.insert(name.as_slice(), &deadline)?;
+            Ok(true)
+        })
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        if self.evict_if_expired(&tree, table, key.as_bytes())? {
+            return Ok(None);
+        }
+        Ok(self
+            .expires
+            .get(expire_key(table, key.as_bytes()))?
+            .map(|v| remaining(decode_deadline(&v))))
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        let _guard = self.shared();
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(false),
+        };
+        if self.evict_if_expired(&tree, table, key.as_bytes())? {
+            return Ok(false);
+        }
+        let name = expire_key(table, key.as_bytes());
+        Ok(self.expires.remove(name)?.is_some())
+    }
+
+    fn purge_expired(&self) -> Result<usize, KvError> {
+        let _guard = self.shared();
+        let now = now_ms();
+        let mut count = 0;
+        for entry in self.expires.iter() {
+            let (name, deadline) = entry?;
+            if decode_deadline(&deadline) > now {
+                continue;
+            }
+            if let Some((table, key)) = split_expire_key(&name) {
+                match self.find_tree(table)? {
+                    Some(tree) if self.evict_if_expired(&tree, table, key)? => count += 1,
+                    Some(_) => {}
+                    // the table was dropped, only its expiry was left
+                    None => {
+                        self.expires.remove(&name)?;
+                    }
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    fn incr(&self, table: &str, key: &str, delta: Value) -> Result<Value, KvError> {
+        let _guard = self.shared();
+        let tree = self.tree(table)?;
+        let name = expire_key(table, key.as_bytes());
+
+        transaction(&tree, &self.expires, |tree, expires| {
+            // a lapsed key counts up from nothing, without its old deadline
+            let old = match is_expired(expires.get(&name)?.as_ref()) {
+                true => {
+                    expires.remove(name.as_slice())?;
+                    None
+                }
+                false => tree.get(key)?,
+            };
+            let value = old
+                .map(|v| Value::try_from(v.as_ref()))
+                .transpose()
+                .and_then(|v| incr_value(v, &delta))
+                .map_err(ConflictableTransactionError::Abort)?;
+            let data: Vec<u8> = value
+                .clone()
+                .try_into()
+                .map_err(ConflictableTransactionError::Abort)?;
+            tree.insert(key.as_bytes(), data)?;
+            Ok(value)
+        })
+    }
+
+    fn compare_and_swap(
+        &self,
+        table: &str,
+        key: &str,
+        expected: Option<Value>,
+        new: Option<Value>,
+    ) -> Result<Result<(), Option<Value>>, KvError> {
+        let _guard = self.shared();
+        let tree = self.tree(table)?;
+        let name = expire_key(table, key.as_bytes());
+        let expected: Option<Vec<u8>> = expected.map(TryInto::try_into).transpose()?;
+        let new: Option<Vec<u8>> = new.map(TryInto::try_into).transpose()?;
+
+        let res = transaction(&tree, &self.expires, |tree, expires| {
+            // a lapsed key is gone, along with its deadline
+            let current = match is_expired(expires.get(&name)?.as_ref()) {
+                true => {
+                    expires.remove(name.as_slice())?;
+                    tree.remove(key.as_bytes())?;
+                    None
+                }
+                false => tree.get(key)?,
+            };
+            if current.as_deref() != expected.as_deref() {
+                return Ok(Err(current));
+            }
+            match &new {
+                Some(v) => {
+                    tree.insert(key.as_bytes(), v.as_slice())?;
+                }
+                None => {
+                    expires.remove(name.as_slice())?;
+                    tree.remove(key.as_bytes())?;
+                }
+            }
+            Ok(Ok(()))
+        })?;
+        match res {
+            Ok(()) => Ok(Ok(())),
+            Err(current) => Ok(Err(flip(current.map(|v| v.as_ref().try_into()))?)),
+        }
+    }
+
+    fn scan(
+        &self,
+        table: &str,
+        cursor: &str,
+        limit: usize,
+        prefix: &str,
+        pattern: &str,
+    ) -> Result<(Vec<Kvpair>, Option<String>), KvError> {
+        let lower = match cursor.is_empty() || cursor < prefix {
+            true => Bound::Included(prefix),
+            false => Bound::Excluded(cursor),
+        };
+
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok((vec![], None)),
+        };
+        let mut pairs = Vec::new();
+        for item in tree.range::<&str, _>((lower, Bound::Unbounded)) {
+            let (k, v) = item?;
+            if !k.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            if self.is_expired(table, &k)? {
+                continue;
+            }
+            let pair: Kvpair = Ok((k, v)).into();
+            if glob_match(pattern, &pair.key) {
+                pairs.push(pair);
+            }
+            if pairs.len() > limit {
+                break;
+            }
+        }
+        Ok(paginate(pairs, limit))
+    }
+
+    fn range(
+        &self,
+        table: &str,
+        start: Bound<&str>,
+        end: Bound<&str>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Vec<Kvpair>, KvError> {
+        if is_empty_range(start, end) {
+            return Ok(vec![]);
+        }
+
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(vec![]),
+        };
+        let iter = tree.range::<&str, _>((start, end));
+        let iter: Box<dyn Iterator<Item = _>> = match reverse {
+            true => Box::new(iter.rev()),
+            false => Box::new(iter),
+        };
+
+        let mut pairs = Vec::new();
+        for item in iter {
+            if pairs.len() >= limit {
+                break;
+            }
+            let (k, v) = item?;
+            if !self.is_expired(table, &k)? {
+                pairs.push(Ok((k, v)).into());
+            }
+        }
+        Ok(pairs)
+    }
+
+    fn tables(&self) -> Result<Vec<String>, KvError> {
+        let mut names = Vec::new();
+        for name in self.db.tree_names() {
+            let table = match name.strip_prefix(TABLE_TREE_PREFIX) {
+                Some(table) => String::from_utf8_lossy(table).to_string(),
+                None => continue,
+            };
+            if self.has_live_keys(&table)? {
+                names.push(table);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn table_len(&self, table: &str) -> Result<usize, KvError> {
+        let tree = match self.find_tree(table)? {
+            Some(tree) => tree,
+            None => return Ok(0),
+        };
+        let now = now_ms();
+        let mut expired = 0;
+        for item in self.expires.scan_prefix(expire_prefix(table)) {
+            let (_, deadline) = item?;
+            if decode_deadline(&deadline) <= now {
+                expired += 1;
+            }
+        }
+        Ok(tree.len().saturating_sub(expired))
+    }
+
+    fn drop_table(&self, table: &str) -> Result<bool, KvError> {
+        let _guard = self.shared();
+        let live = self.has_live_keys(table)?;
+        self.db.drop_tree(tree_name(table))?;
+
+        let mut expires = Batch::default();
+        for item in self.expires.scan_prefix(expire_prefix(table)) {
+            expires.remove(item?.0);
+        }
+        self.expires.apply_batch(expires)?;
+        Ok(live)
+    }
+
+    fn rename_table(&self, from: &str, to: &str) -> Result<(), KvError> {
+        let _guard = self.shared();
+        if !self.has_live_keys(from)? {
+            return Err(KvError::TableNotFound(from.into()));
+        }
+        if from == to {
+            return Ok(());
+        }
+        if self.has_live_keys(to)? {
+            return Err(KvError::TableExists(to.into()));
+        }
+
+        // sled transactions cannot iterate, so the keys are listed first; keys written
+        // to `from` after that stay behind as if they were written after the rename
+        let from_tree = self.tree(from)?;
+        let keys = from_tree.iter().keys().collect::<Result<Vec<_>, _>>()?;
+        let to_tree = self.tree(to)?;
+        (&from_tree, &to_tree, &self.expires).transaction(|(src, dst, expires)| {
+            for key in &keys {
+                let value = match src.remove(key)? {
+                    Some(value) => value,
+                    None => continue,
+                };
+                dst.insert(key, value)?;
+                // replaces the expiry of an expired leftover in `to` as well
+                match expires.remove(expire_key(from, key))? {
+                    Some(deadline) => expires.insert(expire_key(to, key), deadline)?,
+                    None => expires.remove(expire_key(to, key))?,
+                };
+            }
+            Ok::<_, ConflictableTransactionError<KvError>>(())
+        })?;
+        Ok(())
+    }
+
+    fn transaction(
+        &self,
+        tables: &[String],
+        f: &mut dyn FnMut(&dyn Storage) -> Result<(), KvError>,
+    ) -> Result<(), KvError> {
+        let _guard = self.shared();
+        let mut trees = tables
+            .iter()
+            .map(|t| self.tree(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        trees.push(self.expires.clone());
+
+        // sled wants a `Fn`, it reruns the closure when another writer got in between
+        let f = RefCell::new(f);
+        let res = trees.as_slice().transaction(|trees| {
+            let (expires, data) = trees.split_last().expect("expire tree is always there");
+            let tx = SledTx {
+                tables,
+                data,
+                expires,
+                error: RefCell::new(None),
+            };
+            let res = (f.borrow_mut())(&TxView::new(&tx, tables));
+            match (res, tx.error.into_inner()) {
+                (Ok(()), _) => Ok(()),
+                // a conflict has to reach sled for it to retry
+                (Err(_), Some(e)) => Err(e.into()),
+                (Err(e), None) => Err(ConflictableTransactionError::Abort(e)),
+            }
+        });
+        Ok(res?)
+    }
+
+    /// Writers wait until every tree was read, like for the copy a `MemTable` dumps.
+    fn dump(&self) -> Result<Box<dyn Iterator<Item = Result<WalPut, KvError>>>, KvError> {
+        // writers only wait for the iterators to be opened, each key is read as it comes
+        let trees = {
+            let _guard = self.exclusive();
+            let mut trees = Vec::new();
+            for name in self.db.tree_names() {
+                if let Some(table) = name.strip_prefix(TABLE_TREE_PREFIX) {
+                    let table = String::from_utf8_lossy(table).into_owned();
+                    trees.push((table, self.db.open_tree(&name)?.iter()));
+                }
+            }
+            trees
+        };
+
+        let expires = self.expires.clone();
+        let puts = trees.into_iter().flat_map(move |(table, iter)| {
+            let expires = expires.clone();
+            iter.filter_map(move |item| {
+                let expire_at = item.and_then(|(key, value)| {
+                    let deadline = expires.get(expire_key(&table, &key))?;
+                    Ok((key, value, deadline.map(|d| decode_deadline(&d))))
+                });
+                match expire_at {
+                    Ok((_, _, Some(t))) if t <= now_ms() => None,
+                    Ok((key, value, expire_at)) => {
+                        Some(dumped(&table, key.to_vec(), &value, expire_at))
+                    }
+                    Err(e) => Some(Err(e.into())),
+                }
+            })
+        });
+        Ok(Box::new(puts))
+    }
+
+    fn load(
+        &self,
+        puts: &mut dyn Iterator<Item = Result<WalPut, KvError>>,
+    ) -> Result<usize, KvError> {
+        let _guard = self.exclusive();
+        if let Some(table) = self.tables()?.into_iter().next() {
+            return Err(KvError::TableExists(table));
+        }
+
+        let now = now_ms();
+        let mut count = 0;
+        for put in puts {
+            let put = put?;
+            if expired_by(&put, now) {
+                continue;
+            }
+            let tree = self.tree(&put.table)?;
+            let name = expire_key(&put.table, put.key.as_bytes());
+            let data: Vec<u8> = put.value.unwrap_or_default().try_into()?;
+            let deadline = put.expire_at.to_be_bytes();
+            transaction(&tree, &self.expires, |tree, expires| {
+                if put.expire_at > 0 {
+                    expires.insert(name.as_slice(), &deadline)?;
+                }
+                tree.insert(put.key.as_bytes(), data.as_slice())?;
+                Ok(())
+            })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn dumped(
+    table: &str,
+    key: Vec<u8>,
+    value: &[u8],
+    expire_at: Option<u64>,
+) -> Result<WalPut, KvError> {
+    Ok(WalPut {
+        table: table.into(),
+        key: String::from_utf8(key).map_err(|e| KvError::Internal(e.to_string()))?,
+        value: Some(value.try_into()?),
+        expire_at: expire_at.unwrap_or_default(),
+    })
+}
+
+/// The trees of a sled transaction, in the order of the tables it was opened for.
+struct SledTx<'a> {
+    tables: &'a [String],
+    data: &'a [TransactionalTree],
+    expires: &'a TransactionalTree,
+    /// The first error sled raised, which decides whether the transaction is retried.
+    error: RefCell<Option<UnabortableTransactionError>>,
+}
+
+impl SledTx<'_> {
+    fn tree(&self, table: &str) -> &TransactionalTree {
+        let pos = self.tables.iter().position(|t| t == table);
+        &self.data[pos.expect("TxView only passes tables of the transaction")]
+    }
+
+    fn check<T>(&self, res: Result<T, UnabortableTransactionError>) -> Result<T, KvError> {
+        res.map_err(|e| {
+            let msg = e.to_string();
+            self.error.borrow_mut().get_or_insert(e);
+            KvError::Internal(msg)
+        })
+    }
+
+    fn deadline(&self, table: &str, key: &str) -> Result<Option<IVec>, KvError> {
+        self.check(self.expires.get(expire_key(table, key.as_bytes())))
+    }
+}
+
+impl TxStorage for SledTx<'_> {
+    fn get(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        if is_expired(self.deadline(table, key)?.as_ref()) {
+            return Ok(None);
+        }
+        let res = self.check(self.tree(table).get(key))?;
+        flip(res.map(|v| v.as_ref().try_into()))
+    }
+
+    fn set(&self, table: &str, key: &str, value: Value) -> Result<Option<Value>, KvError> {
+        let data: Vec<u8> = value.try_into()?;
+        let deadline = self.check(self.expires.remove(expire_key(table, key.as_bytes())))?;
+        let old = self.check(self.tree(table).insert(key, data))?;
+        let old = old.filter(|_| !is_expired(deadline.as_ref()));
+        flip(old.map(|v| v.as_ref().try_into()))
+    }
+
+    fn set_with_ttl(
+        &self,
+        table: &str,
+        key: &str,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<Option<Value>, KvError> {
+        let data: Vec<u8> = value.try_into()?;
+        let name = expire_key(table, key.as_bytes());
+        let deadline = deadline_after(ttl).to_be_bytes();
+        let old_deadline = self.check(self.expires.insert(name, &deadline))?;
+        let old = self.check(self.tree(table).insert(key, data))?;
+        let old = old.filter(|_| !is_expired(old_deadline.as_ref()));
+        flip(old.map(|v| v.as_ref().try_into()))
+    }
+
+    fn del(&self, table: &str, key: &str) -> Result<Option<Value>, KvError> {
+        let deadline = self.check(self.expires.remove(expire_key(table, key.as_bytes())))?;
+        let old = self.check(self.tree(table).remove(key))?;
+        let old = old.filter(|_| !is_expired(deadline.as_ref()));
+        flip(old.map(|v| v.as_ref().try_into()))
+    }
+
+    fn expire(&self, table: &str, key: &str, ttl: Duration) -> Result<bool, KvError> {
+        if TxStorage::get(self, table, key)?.is_none() {
+            return Ok(false);
+        }
+        let name = expire_key(table, key.as_bytes());
+        let deadline = deadline_after(ttl).to_be_bytes();
+        self.check(self.expires.insert(name, &deadline))?;
+        Ok(true)
+    }
+
+    fn ttl(&self, table: &str, key: &str) -> Result<Option<Duration>, KvError> {
+        if TxStorage::get(self, table, key)?.is_none() {
+            return Ok(None);
+        }
+        let deadline = self.deadline(table, key)?;
+        Ok(deadline.map(|v| remaining(decode_deadline(&v))))
+    }
+
+    fn persist(&self, table: &str, key: &str) -> Result<bool, KvError> {
+        if TxStorage::get(self, table, key)?.is_none() {
+            return Ok(false);
+        }
+        let name = expire_key(table, key.as_bytes());
+        Ok(self.check(self.expires.remove(name))?.is_some())
     }
 }
 
@@ -62,7 +735,7 @@ impl From<Result<(IVec, IVec), sled::Error>> for Kvpair {
     fn from(v: Result<(IVec, IVec), Error>) -> Self {
         match v {
             Ok((k, v)) => match v.as_ref().try_into() {
-                Ok(v) => Kvpair::new(ivec_to_key(k.as_ref()), v),
+                Ok(v) => Kvpair::new(String::from_utf8_lossy(k.as_ref()), v),
                 Err(_) => Kvpair::default(),
             },
             _ => Kvpair::default(),
@@ -70,21 +743,74 @@ impl From<Result<(IVec, IVec), sled::Error>> for Kvpair {
     }
 }
 
+impl From<TransactionError<KvError>> for KvError {
+    fn from(e: TransactionError<KvError>) -> Self {
+        match e {
+            TransactionError::Abort(e) => e,
+            TransactionError::Storage(e) => e.into(),
+        }
+    }
+}
+
+/// Run a transaction over a table tree and the expiration tree together.
+fn transaction<T, F>(tree: &Tree, expires: &Tree, f: F) -> Result<T, KvError>
+where
+    F: Fn(
+        &sled::transaction::TransactionalTree,
+        &sled::transaction::TransactionalTree,
+    ) -> Result<T, ConflictableTransactionError<KvError>>,
+{
+    Ok((tree, expires).transaction(|(tree, expires)| f(tree, expires))?)
+}
+
+fn tree_name(table: &str) -> Vec<u8> {
+    [TABLE_TREE_PREFIX, table.as_bytes()].concat()
+}
+
+/// The length of the table name is encoded up front, so any table name and key can be
+/// told apart and all expirations of a table share one prefix.
+fn expire_prefix(table: &str) -> Vec<u8> {
+    [&(table.len() as u32).to_be_bytes()[..], table.as_bytes()].concat()
+}
+
+fn expire_key(table: &str, key: &[u8]) -> Vec<u8> {
+    [expire_prefix(table).as_slice(), key].concat()
+}
+
+fn split_expire_key(name: &[u8]) -> Option<(&str, &[u8])> {
+    let len = u32::from_be_bytes(name.get(..4)?.try_into().ok()?) as usize;
+    let table = std::str::from_utf8(name.get(4..4 + len)?).ok()?;
+    Some((table, &name[4 + len..]))
+}
+
+fn split_legacy_key(name: &[u8]) -> Option<(&str, &[u8])> {
+    let pos = name.iter().position(|b| *b == b':')?;
+    let table = std::str::from_utf8(&name[..pos]).ok()?;
+    Some((table, &name[pos + 1..]))
+}
+
 fn flip<T, E>(x: Option<Result<T, E>>) -> Result<Option<T>, E> {
     x.map_or(Ok(None), |v| v.map(Some))
 }
 
-fn ivec_to_key(ivec: &[u8]) -> &str {
-    let s = std::str::from_utf8(ivec).unwrap();
-    let mut iter = s.split(":");
-    iter.next();
-    iter.next().unwrap()
+fn decode_deadline(v: &[u8]) -> u64 {
+    v.try_into().map(u64::from_be_bytes).unwrap_or(u64::MAX)
+}
+
+fn is_expired(deadline: Option<&IVec>) -> bool {
+    matches!(deadline, Some(v) if decode_deadline(v) <= now_ms())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::sleddb::SledDb;
-    use crate::{test_basi_interface, test_get_all, test_get_iter};
+    use crate::storage::sleddb::{expire_prefix, SledDb};
+    use crate::{
+        test_basi_interface, test_compare_and_swap, test_expire, test_get_all, test_get_iter,
+        test_incr, test_purge_expired, test_range, test_scan, test_table_management,
+        test_transaction, Kvpair, Storage, Value,
+    };
+    use std::thread;
+    use std::time::Duration;
     use tempfile::tempdir;
 
     #[test]
@@ -107,4 +833,163 @@ mod tests {
         let store = SledDb::new(dir);
         test_get_iter(&store);
     }
+
+    #[test]
+    fn sled_db_expire_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_expire(&store);
+    }
+
+    #[test]
+    fn sled_db_purge_expired_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_purge_expired(&store);
+    }
+
+    #[test]
+    fn sled_db_incr_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_incr(&store);
+    }
+
+    #[test]
+    fn sled_db_compare_and_swap_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_compare_and_swap(&store);
+    }
+
+    #[test]
+    fn sled_db_scan_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_scan(&store);
+    }
+
+    #[test]
+    fn sled_db_range_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_range(&store);
+    }
+
+    #[test]
+    fn sled_db_table_management_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_table_management(&store);
+    }
+
+    #[test]
+    fn sled_db_keys_with_colon_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+
+        let _v = store.set("a", "b:c".into(), 1.into());
+        let _v = store.set("a:b", "c".into(), 2.into());
+
+        assert_eq!(store.get("a", "b:c"), Ok(Some(1.into())));
+        assert_eq!(store.get("a:b", "c"), Ok(Some(2.into())));
+        assert_eq!(store.get_all("a"), Ok(vec![Kvpair::new("b:c", 1.into())]));
+        assert_eq!(store.tables(), Ok(vec!["a".into(), "a:b".into()]));
+    }
+
+    #[test]
+    fn sled_db_should_migrate_prefix_encoded_keys() {
+        let dir = tempdir().unwrap();
+        {
+            let db = sled::open(dir.path()).unwrap();
+            let data: Vec<u8> = Value::from("v1").try_into().unwrap();
+            db.insert("t1:k1", data.clone()).unwrap();
+            db.insert("t1:k2:x", data.clone()).unwrap();
+            db.insert("t2:k1", data).unwrap();
+            db.flush().unwrap();
+        }
+
+        let store = SledDb::new(dir.path());
+        assert_eq!(store.get("t1", "k1"), Ok(Some("v1".into())));
+        assert_eq!(store.get("t1", "k2:x"), Ok(Some("v1".into())));
+        assert_eq!(store.get("t2", "k1"), Ok(Some("v1".into())));
+        assert_eq!(store.tables(), Ok(vec!["t1".into(), "t2".into()]));
+        assert!(store.db.is_empty());
+    }
+
+    #[test]
+    fn sled_db_reads_should_not_create_tables() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        let trees = store.db.tree_names().len();
+
+        assert_eq!(store.get("t1", "k1"), Ok(None));
+        assert_eq!(store.contains("t1", "k1"), Ok(false));
+        assert_eq!(store.ttl("t1", "k1"), Ok(None));
+        assert_eq!(store.table_len("t1"), Ok(0));
+        assert_eq!(store.get_all("t1"), Ok(vec![]));
+        assert_eq!(store.scan("t1", "", 10, "", ""), Ok((vec![], None)));
+        assert_eq!(store.drop_table("t1"), Ok(false));
+        assert_eq!(store.db.tree_names().len(), trees);
+    }
+
+    #[test]
+    fn sled_db_rename_should_move_expiries() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        store.set("t1", "k1".into(), "v1".into()).unwrap();
+        store.set("t1", "k2".into(), "v2".into()).unwrap();
+        store.expire("t1", "k2", Duration::from_secs(60)).unwrap();
+        // an expired leftover in the target must not keep its expiry
+        store.set("t2", "k1".into(), "old".into()).unwrap();
+        store.expire("t2", "k1", Duration::from_millis(1)).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(store.rename_table("t1", "t2"), Ok(()));
+        assert_eq!(store.get("t2", "k1"), Ok(Some("v1".into())));
+        assert_eq!(store.ttl("t2", "k1"), Ok(None));
+        assert!(store.ttl("t2", "k2").unwrap().is_some());
+        assert_eq!(store.table_len("t1"), Ok(0));
+        assert_eq!(store.tables(), Ok(vec!["t2".into()]));
+        assert!(store
+            .expires
+            .scan_prefix(expire_prefix("t1"))
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn sled_db_dump_should_not_stop_writers() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir.path());
+        for i in 0..200 {
+            store.set("t", format!("k{:03}", i), i.into()).unwrap();
+        }
+        store
+            .set_with_ttl("t", "k100".into(), 0.into(), Duration::from_millis(1))
+            .unwrap();
+        thread::sleep(Duration::from_millis(5));
+
+        let mut puts = store.dump().unwrap();
+        assert_eq!(puts.next().unwrap().unwrap().key, "k000");
+        // neither a write nor a transaction waits for the rest of the dump
+        store.set("t", "k199".into(), 0.into()).unwrap();
+        let tables = vec!["t".to_string()];
+        store
+            .transaction(&tables, &mut |tx| {
+                tx.incr("t", "k001", 1.into()).map(|_| ())
+            })
+            .unwrap();
+        let rest: Vec<_> = puts.map(|put| put.unwrap()).collect();
+        assert_eq!(rest.len(), 198);
+        assert!(rest.iter().all(|put| put.key != "k100"));
+        assert_eq!(rest[197].value, Some(0.into()));
+    }
+
+    #[test]
+    fn sled_db_transaction_should_work() {
+        let dir = tempdir().unwrap();
+        let store = SledDb::new(dir);
+        test_transaction(&store);
+    }
 }
@@ -0,0 +1,577 @@
+use crate::error::KvError;
+use crate::{WalOp, WalRecord};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+/// Every record is framed by its length and the crc32 of its payload, both little endian.
+const HEADER_LEN: usize = 8;
+/// Ops per record of a snapshot.
+const SNAPSHOT_BATCH: usize = 1024;
+
+/// When appended records are flushed to disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FsyncPolicy {
+    /// After every record, so nothing acknowledged is lost.
+    Always,
+    /// From a background thread once a second, losing at most a second of writes.
+    #[default]
+    EverySecond,
+    /// Whenever the OS decides to.
+    Never,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = KvError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "every-second" => Ok(Self::EverySecond),
+            "never" => Ok(Self::Never),
+            _ => Err(KvError::InvalidCommand(format!(
+                "unknown fsync policy: {}, expected always, every-second or never",
+                s
+            ))),
+        }
+    }
+}
+
+/// Append-only log of the changes made to a `MemTable`, next to the snapshot
+/// it continues, if one was taken.
+#[derive(Debug)]
+pub(crate) struct Wal {
+    path: PathBuf,
+    file: Arc<Mutex<LogFile>>,
+    fsync: FsyncPolicy,
+    /// Ops of the running transaction, written as one record when it commits.
+    pending: Mutex<Option<Vec<WalOp>>>,
+    /// Held while a snapshot is taken, so there is one at a time.
+    compacting: Mutex<()>,
+}
+
+#[derive(Debug)]
+struct LogFile {
+    file: File,
+    /// Length of the intact records, a failed append is cut back to it.
+    len: u64,
+    generation: u64,
+    /// Appended to since the last sync.
+    dirty: bool,
+}
+
+/// The end of the log at some point, up to which a snapshot contains it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LogPosition {
+    generation: u64,
+    offset: u64,
+}
+
+impl Wal {
+    /// Open the log at `path`, creating it if needed, and pass the records of its
+    /// snapshot and then the intact records of the log to `replay` in order. A torn
+    /// or corrupt tail is cut off.
+    pub fn open(
+        path: impl AsRef<Path>,
+        fsync: FsyncPolicy,
+        mut replay: impl FnMut(WalRecord),
+    ) -> Result<Self, KvError> {
+        let path = path.as_ref().to_path_buf();
+        let snapshot = read_snapshot(&snapshot_path(&path), &mut replay)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        let mut records = Vec::new();
+        let mut len = 0;
+        while let Some(payload) = next_record(&buf[len..]) {
+            records.push((len as u64, payload));
+            len += HEADER_LEN + payload.len();
+        }
+        if len < buf.len() {
+            warn!(
+                "Cutting off {} bytes of torn or corrupt records at offset {} of {}",
+                buf.len() - len,
+                len,
+                path.display()
+            );
+            file.set_len(len as u64)?;
+            file.sync_data()?;
+        }
+
+        let generation = match records.first() {
+            Some((_, payload)) => WalRecord::decode(*payload)?.generation,
+            None => 0,
+        };
+        let skip = match snapshot {
+            None if generation == 0 => 0,
+            Some(p) if p.generation == generation => 0,
+            // the snapshot was taken but the log not cut yet
+            Some(p) if p.generation == generation + 1 || records.is_empty() => p.offset,
+            _ => {
+                return Err(KvError::Internal(format!(
+                    "{} does not continue its snapshot",
+                    path.display()
+                )))
+            }
+        };
+        for (offset, payload) in records {
+            if offset >= skip {
+                replay(WalRecord::decode(payload)?);
+            }
+        }
+
+        let mut log = LogFile {
+            file,
+            len: len as u64,
+            generation,
+            dirty: false,
+        };
+        if let Some(p) = snapshot.filter(|p| p.generation != generation) {
+            rewrite_log(&path, &mut log, p)?;
+        }
+        let file = Arc::new(Mutex::new(log));
+        if fsync == FsyncPolicy::EverySecond {
+            spawn_syncer(Arc::downgrade(&file));
+        }
+        Ok(Self {
+            path,
+            file,
+            fsync,
+            pending: Mutex::default(),
+            compacting: Mutex::default(),
+        })
+    }
+
+    /// Log a single change, or hold it back until the running transaction commits.
+    pub fn append(&self, op: WalOp) -> Result<(), KvError> {
+        if let Some(ops) = lock(&self.pending).as_mut() {
+            ops.push(op);
+            return Ok(());
+        }
+        self.write(WalRecord {
+            ops: vec![op],
+            ..Default::default()
+        })
+    }
+
+    pub fn begin(&self) {
+        *lock(&self.pending) = Some(Vec::new());
+    }
+
+    /// Write the ops of the transaction as a single record.
+    pub fn commit(&self) -> Result<(), KvError> {
+        match lock(&self.pending).take() {
+            Some(ops) if !ops.is_empty() => self.write(WalRecord {
+                ops,
+                ..Default::default()
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn abort(&self) {
+        *lock(&self.pending) = None;
+    }
+
+    /// Flush the log to disk now, whatever the fsync policy.
+    pub fn sync(&self) -> Result<(), KvError> {
+        let mut log = lock(&self.file);
+        if log.dirty {
+            log.file.sync_data()?;
+            log.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Size of the log in bytes, without the snapshot.
+    pub fn len(&self) -> u64 {
+        lock(&self.file).len
+    }
+
+    pub fn position(&self) -> LogPosition {
+        let log = lock(&self.file);
+        LogPosition {
+            generation: log.generation,
+            offset: log.len,
+        }
+    }
+
+    /// Taken before the state and position for `compact` are captured.
+    pub fn compacting(&self) -> MutexGuard<'_, ()> {
+        lock(&self.compacting)
+    }
+
+    /// Write a state at least as new as `position` as the new snapshot, then cut what
+    /// comes before `position` from the log, since replaying the rest on top settles
+    /// anything newer. Appends only wait for the log to be cut.
+    pub fn compact(
+        &self,
+        position: LogPosition,
+        ops: impl Iterator<Item = WalOp>,
+    ) -> Result<(), KvError> {
+        let position = LogPosition {
+            generation: position.generation + 1,
+            offset: position.offset,
+        };
+        let header = WalRecord {
+            generation: position.generation,
+            offset: position.offset,
+            ..Default::default()
+        };
+        write_snapshot(&snapshot_path(&self.path), header, ops.map(Ok))?;
+        rewrite_log(&self.path, &mut lock(&self.file), position)
+    }
+
+    fn write(&self, record: WalRecord) -> Result<(), KvError> {
+        let buf = frame(&record);
+        let mut log = lock(&self.file);
+        let res = log.file.write_all(&buf).and_then(|_| match self.fsync {
+            FsyncPolicy::Always => log.file.sync_data(),
+            _ => Ok(()),
+        });
+        if let Err(e) = res {
+            // don't leave a torn record for the next appends to hide behind
+            let _ = log.file.set_len(log.len);
+            return Err(e.into());
+        }
+        log.len += buf.len() as u64;
+        log.dirty = true;
+        Ok(())
+    }
+}
+
+impl Drop for Wal {
+    fn drop(&mut self) {
+        let log = lock(&self.file);
+        if self.fsync != FsyncPolicy::Never && log.dirty {
+            if let Err(e) = log.file.sync_data() {
+                warn!("Failed to sync write-ahead log: {}", e);
+            }
+        }
+    }
+}
+
+fn frame(record: &WalRecord) -> Vec<u8> {
+    let payload = record.encode_to_vec();
+    let mut buf = Vec::with_capacity(HEADER_LEN + payload.len());
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    buf.extend_from_slice(&payload);
+    buf
+}
+
+/// Write `header` and then `ops` in batches to a new file, which is moved to
+/// `path` once complete. Returns the number of ops.
+pub(super) fn write_snapshot(
+    path: &Path,
+    header: WalRecord,
+    ops: impl Iterator<Item = Result<WalOp, KvError>>,
+) -> Result<usize, KvError> {
+    let tmp = with_suffix(path, ".tmp");
+    let mut out = BufWriter::new(File::create(&tmp)?);
+    out.write_all(&frame(&header))?;
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(SNAPSHOT_BATCH);
+    for op in ops {
+        batch.push(op?);
+        count += 1;
+        if batch.len() == SNAPSHOT_BATCH {
+            let ops = mem::replace(&mut batch, Vec::with_capacity(SNAPSHOT_BATCH));
+            out.write_all(&frame(&WalRecord {
+                ops,
+                ..Default::default()
+            }))?;
+        }
+    }
+    if !batch.is_empty() {
+        out.write_all(&frame(&WalRecord {
+            ops: batch,
+            ..Default::default()
+        }))?;
+    }
+    let file = out.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    install(&tmp, path)?;
+    Ok(count)
+}
+
+/// Read the next record of a snapshot, `None` at its end. Snapshots are moved into
+/// place once complete, so a torn or corrupt record is an error rather than a crash.
+pub(super) fn read_record(reader: &mut impl Read) -> Result<Option<WalRecord>, KvError> {
+    let mut header = [0; HEADER_LEN];
+    match reader.read_exact(&mut header[..1]) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    reader.read_exact(&mut header[1..])?;
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+    let mut payload = Vec::new();
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() < len || crc32fast::hash(&payload) != crc {
+        return Err(KvError::Internal("Torn or corrupt snapshot record".into()));
+    }
+    Ok(Some(WalRecord::decode(payload.as_slice())?))
+}
+
+/// Payload of the first record in `buf`, `None` if it is incomplete or its checksum is off.
+fn next_record(buf: &[u8]) -> Option<&[u8]> {
+    let header = buf.get(..HEADER_LEN)?;
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+    let payload = buf.get(HEADER_LEN..HEADER_LEN.checked_add(len)?)?;
+    (crc32fast::hash(payload) == crc).then_some(payload)
+}
+
+/// Replay a snapshot, returning where in the log it leaves off.
+fn read_snapshot(
+    path: &Path,
+    replay: &mut impl FnMut(WalRecord),
+) -> Result<Option<LogPosition>, KvError> {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let header = match read_record(&mut reader)? {
+        Some(header) => header,
+        None => {
+            return Err(KvError::Internal(format!(
+                "Empty snapshot {}",
+                path.display()
+            )))
+        }
+    };
+    while let Some(record) = read_record(&mut reader)? {
+        replay(record);
+    }
+    Ok(Some(LogPosition {
+        generation: header.generation,
+        offset: header.offset,
+    }))
+}
+
+/// Replace the log by one of the next generation, holding what was appended after the snapshot.
+fn rewrite_log(path: &Path, log: &mut LogFile, snapshot: LogPosition) -> Result<(), KvError> {
+    let start = snapshot.offset.min(log.len);
+    let mut tail = Vec::new();
+    let mut old = File::open(path)?;
+    old.seek(SeekFrom::Start(start))?;
+    old.take(log.len - start).read_to_end(&mut tail)?;
+
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = File::create(&tmp)?;
+    let header = frame(&WalRecord {
+        generation: snapshot.generation,
+        ..Default::default()
+    });
+    file.write_all(&header)?;
+    file.write_all(&tail)?;
+    file.sync_all()?;
+    install(&tmp, path)?;
+
+    log.file = OpenOptions::new().append(true).open(path)?;
+    log.len = (header.len() + tail.len()) as u64;
+    log.generation = snapshot.generation;
+    log.dirty = false;
+    Ok(())
+}
+
+/// Atomically move a complete file into place.
+fn install(tmp: &Path, path: &Path) -> Result<(), KvError> {
+    fs::rename(tmp, path)?;
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all()?,
+        _ => File::open(".")?.sync_all()?,
+    }
+    Ok(())
+}
+
+fn snapshot_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".snapshot")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+/// Sync the log once a second while anything was appended, until the `Wal` is dropped.
+fn spawn_syncer(log: Weak<Mutex<LogFile>>) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let log = match log.upgrade() {
+            Some(log) => log,
+            None => break,
+        };
+        // sync a handle of its own so appends don't wait for the disk
+        let file = {
+            let mut log = lock(&log);
+            if !mem::take(&mut log.dirty) {
+                continue;
+            }
+            log.file.try_clone()
+        };
+        if let Err(e) = file.and_then(|f| f.sync_data()) {
+            warn!("Failed to sync write-ahead log: {}", e);
+        }
+    });
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal_op::Op;
+    use crate::Hdel;
+    use tempfile::tempdir;
+
+    fn del(key: &str) -> WalOp {
+        WalOp {
+            op: Some(Op::Del(Hdel {
+                table: "t1".into(),
+                key: key.into(),
+            })),
+        }
+    }
+
+    fn replay(path: &Path) -> Vec<WalRecord> {
+        let mut records = Vec::new();
+        Wal::open(path, FsyncPolicy::Never, |r| records.push(r)).unwrap();
+        records
+    }
+
+    #[test]
+    fn wal_should_replay_records_and_transactions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+
+        let wal = Wal::open(&path, FsyncPolicy::Always, |_| {}).unwrap();
+        wal.append(del("a")).unwrap();
+        wal.begin();
+        wal.append(del("b")).unwrap();
+        wal.append(del("c")).unwrap();
+        wal.commit().unwrap();
+        wal.begin();
+        wal.append(del("d")).unwrap();
+        wal.abort();
+        drop(wal);
+
+        let records = replay(&path);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].ops, vec![del("a")]);
+        assert_eq!(records[1].ops, vec![del("b"), del("c")]);
+    }
+
+    #[test]
+    fn wal_should_cut_off_torn_record() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+
+        let wal = Wal::open(&path, FsyncPolicy::EverySecond, |_| {}).unwrap();
+        wal.append(del("a")).unwrap();
+        wal.append(del("b")).unwrap();
+        drop(wal);
+
+        // lose the end of the last record as in a crash
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+
+        let mut records = Vec::new();
+        let wal = Wal::open(&path, FsyncPolicy::Never, |r| records.push(r)).unwrap();
+        assert_eq!(records.len(), 1);
+        wal.append(del("c")).unwrap();
+        drop(wal);
+
+        let records = replay(&path);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].ops, vec![del("c")]);
+    }
+
+    #[test]
+    fn compact_should_snapshot_and_cut_the_log() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+
+        let wal = Wal::open(&path, FsyncPolicy::Never, |_| {}).unwrap();
+        wal.append(del("a")).unwrap();
+        let position = wal.position();
+        wal.append(del("b")).unwrap();
+        let before = wal.len();
+        wal.compact(position, (0..3000).map(|_| del("s"))).unwrap();
+        assert!(wal.len() < before);
+        wal.append(del("c")).unwrap();
+        drop(wal);
+
+        let ops: Vec<_> = replay(&path).into_iter().flat_map(|r| r.ops).collect();
+        assert_eq!(ops.len(), 3002);
+        assert_eq!(&ops[3000..], &[del("b"), del("c")]);
+    }
+
+    #[test]
+    fn open_should_finish_interrupted_compaction() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kv.wal");
+
+        let wal = Wal::open(&path, FsyncPolicy::Never, |_| {}).unwrap();
+        wal.append(del("a")).unwrap();
+        let position = wal.position();
+        wal.append(del("b")).unwrap();
+        drop(wal);
+
+        // the snapshot got written, but not the cut log
+        let log = fs::read(&path).unwrap();
+        let wal = Wal::open(&path, FsyncPolicy::Never, |_| {}).unwrap();
+        wal.compact(position, [del("s")].into_iter()).unwrap();
+        drop(wal);
+        fs::write(&path, log).unwrap();
+
+        let ops: Vec<_> = replay(&path).into_iter().flat_map(|r| r.ops).collect();
+        assert_eq!(ops, vec![del("s"), del("b")]);
+        let ops: Vec<_> = replay(&path).into_iter().flat_map(|r| r.ops).collect();
+        assert_eq!(ops, vec![del("s"), del("b")]);
+
+        // a log which does not continue the snapshot is refused
+        fs::write(
+            &path,
+            frame(&WalRecord {
+                generation: 5,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert!(Wal::open(&path, FsyncPolicy::Never, |_| {}).is_err());
+    }
+
+    #[test]
+    fn fsync_policy_should_parse() {
+        assert_eq!("always".parse(), Ok(FsyncPolicy::Always));
+        assert_eq!("every-second".parse(), Ok(FsyncPolicy::EverySecond));
+        assert!("sometimes".parse::<FsyncPolicy>().is_err());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::pb::{decode_binary, encode_binary};
+use crate::{value, CommandRequest, KvError, Kvpair, Service, Storage, Value};
+use serde_json::{json, Value as Json};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// File format of an exported table. Every record holds a key, the type of its value
+/// and the value itself, so all value types survive a round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    /// One JSON object per line: `{"key":"k1","type":"integer","value":42}`
+    Jsonl,
+    /// A `key,type,value` header followed by one row per key
+    Csv,
+}
+
+impl TableFormat {
+    /// Guess the format from the extension of a file name.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TableFormat {
+    type Err = KvError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            _ => Err(KvError::InvalidCommand(format!(
+                "unknown table format: {}, expected jsonl or csv",
+                s
+            ))),
+        }
+    }
+}
+
+const CSV_HEADER: [&str; 3] = ["key", "type", "value"];
+
+/// Writes the pairs of a table in a `TableFormat`.
+pub struct TableWriter<W: Write> {
+    out: W,
+    format: TableFormat,
+    count: usize,
+}
+
+impl<W: Write> TableWriter<W> {
+    pub fn new(mut out: W, format: TableFormat) -> Result<Self, KvError> {
+        if format == TableFormat::Csv {
+            writeln!(out, "{}", CSV_HEADER.join(","))?;
+        }
+        Ok(Self {
+            out,
+            format,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, pair: &Kvpair) -> Result<(), KvError> {
+        let value = pair.value.clone().unwrap_or_default();
+        match self.format {
+            TableFormat::Jsonl => {
+                let (kind, value) = encode_json(&value);
+                let record = json!({ "key": pair.key, "type": kind, "value": value });
+                writeln!(self.out, "{}", record)?;
+            }
+            TableFormat::Csv => {
+                let (kind, value) = encode_text(&value);
+                let fields = [pair.key.as_str(), kind, value.as_str()].map(csv_field);
+                writeln!(self.out, "{}", fields.join(","))?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flush the output, returning the number of pairs written.
+    pub fn finish(mut self) -> Result<usize, KvError> {
+        self.out.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Reads the pairs written by a `TableWriter`.
+pub struct TableReader<R: BufRead> {
+    input: R,
+    format: TableFormat,
+    line: usize,
+    header_read: bool,
+}
+
+impl<R: BufRead> TableReader<R> {
+    pub fn new(input: R, format: TableFormat) -> Self {
+        Self {
+            input,
+            format,
+            line: 0,
+            header_read: false,
+        }
+    }
+
+    /// Read the next pair, or `None` at the end of the input.
+    pub fn read(&mut self) -> Result<Option<Kvpair>, KvError> {
+        loop {
+            let start = self.line + 1;
+            let record = match self.format {
+                TableFormat::Jsonl => self.next_line()?,
+                TableFormat::Csv => self.next_csv_record()?,
+            };
+            let record = match record {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            if record.trim().is_empty() {
+                continue;
+            }
+
+            let invalid = |e: String| KvError::InvalidRecord(start, e);
+            let pair = match self.format {
+                TableFormat::Jsonl => parse_json(&record).map_err(invalid)?,
+                TableFormat::Csv => {
+                    let fields = split_csv(&record).map_err(invalid)?;
+                    if !self.header_read {
+                        if fields != CSV_HEADER {
+                            return Err(invalid(format!(
+                                "expected header {}",
+                                CSV_HEADER.join(",")
+                            )));
+                        }
+                        self.header_read = true;
+                        continue;
+                    }
+                    match fields.as_slice() {
+                        [key, kind, value] => {
+                            Kvpair::new(key.as_str(), decode_text(kind, value).map_err(invalid)?)
+                        }
+                        _ => {
+                            return Err(invalid(format!("expected 3 fields, got {}", fields.len())))
+                        }
+                    }
+                }
+            };
+            return Ok(Some(pair));
+        }
+    }
+
+    /// Read up to `size` pairs; an empty batch means the input is exhausted.
+    pub fn next_batch(&mut self, size: usize) -> Result<Vec<Kvpair>, KvError> {
+        let mut batch = Vec::new();
+        while batch.len() < size.max(1) {
+            match self.read()? {
+                Some(pair) => batch.push(pair),
+                None => break,
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Next line without its line break.
+    fn next_line(&mut self) -> Result<Option<String>, KvError> {
+        Ok(self.next_raw_line()?.map(|mut line| {
+            trim_line_break(&mut line);
+            line
+        }))
+    }
+
+    fn next_raw_line(&mut self) -> Result<Option<String>, KvError> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some(line))
+    }
+
+    /// Next CSV record, which spans several lines when a quoted field holds line breaks.
+    fn next_csv_record(&mut self) -> Result<Option<String>, KvError> {
+        let start = self.line + 1;
+        let mut record = match self.next_raw_line()? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        // escaped quotes come in pairs, so an odd count means a quoted field is still open
+        while record.matches('"').count() % 2 == 1 {
+            match self.next_raw_line()? {
+                Some(line) => record.push_str(&line),
+                None => {
+                    return Err(KvError::InvalidRecord(
+                        start,
+                        "unterminated quoted field".into(),
+                    ))
+                }
+            }
+        }
+        trim_line_break(&mut record);
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead> Iterator for TableReader<R> {
+    type Item = Result<Kvpair, KvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// Write all pairs of a table to `out`, returning the number of pairs.
+pub fn export_table(
+    store: &dyn Storage,
+    table: &str,
+    format: TableFormat,
+    out: impl Write,
+) -> Result<usize, KvError> {
+    let mut writer = TableWriter::new(out, format)?;
+    for pair in store.get_iter(table)? {
+        writer.write(&pair)?;
+    }
+    writer.finish()
+}
+
+/// Load pairs from `input` into a table with `Hmset` requests of `batch_size` pairs,
+/// returning the number of pairs. Batches already sent stay when a later record is invalid.
+pub fn import_table<Store: Storage>(
+    service: &Service<Store>,
+    table: &str,
+    format: TableFormat,
+    input: impl BufRead,
+    batch_size: usize,
+) -> Result<usize, KvError> {
+    let mut reader = TableReader::new(input, format);
+    let mut count = 0;
+    loop {
+        let pairs = reader.next_batch(batch_size)?;
+        if pairs.is_empty() {
+            return Ok(count);
+        }
+        count += pairs.len();
+        service
+            .execute(CommandRequest::new_hmset(table, pairs))
+            .into_result()?;
+    }
+}
+
+/// Type name and text of a value.
+fn encode_text(value: &Value) -> (&'static str, String) {
+    match &value.value {
+        Some(value::Value::String(s)) => ("string", s.clone()),
+        Some(value::Value::Binary(b)) => ("binary", encode_binary(b)),
+        Some(value::Value::Integer(i)) => ("integer", i.to_string()),
+        Some(value::Value::Float(f)) => ("float", f.to_string()),
+        Some(value::Value::Bool(b)) => ("bool", b.to_string()),
+        None => ("null", String::new()),
+    }
+}
+
+/// Type name and JSON of a value; JSON has no numbers for NaN and infinities, so those
+/// floats are written as text.
+fn encode_json(value: &Value) -> (&'static str, Json) {
+    match &value.value {
+        Some(value::Value::Integer(i)) => ("integer", json!(i)),
+        Some(value::Value::Float(f)) if f.is_finite() => ("float", json!(f)),
+        Some(value::Value::Bool(b)) => ("bool", json!(b)),
+        None => ("null", Json::Null),
+        _ => {
+            let (kind, text) = encode_text(value);
+            (kind, json!(text))
+        }
+    }
+}
+
+fn decode_text(kind: &str, text: &str) -> Result<Value, String> {
+    let invalid = |e: &dyn std::fmt::Display| format!("invalid {} `{}`: {}", kind, text, e);
+    let value = match kind {
+        "string" => text.into(),
+        "binary" => decode_binary(text).map_err(|e| invalid(&e))?.into(),
+        "integer" => text.parse::<i64>().map_err(|e| invalid(&e))?.into(),
+        "float" => text.parse::<f64>().map_err(|e| invalid(&e))?.into(),
+        "bool" => text.parse::<bool>().map_err(|e| invalid(&e))?.into(),
+        "null" if text.is_empty() => Value::default(),
+        _ => return Err(format!("unknown type `{}`", kind)),
+    };
+    Ok(value)
+}
+
+fn decode_json(kind: &str, value: Json) -> Result<Value, String> {
+    match (kind, value) {
+        ("string" | "binary" | "float", Json::String(s)) => decode_text(kind, &s),
+        ("integer", Json::Number(n)) if n.is_i64() => Ok(n.as_i64().unwrap_or_default().into()),
+        ("float", Json::Number(n)) => Ok(n.as_f64().unwrap_or_default().into()),
+        ("bool", Json::Bool(b)) => Ok(b.into()),
+        ("null", Json::Null) => Ok(Value::default()),
+        (kind, value) => Err(format!("invalid {} `{}`", kind, value)),
+    }
+}
+
+/// Records without a `type` hold their value the way the HTTP gateway writes pairs.
+fn parse_json(line: &str) -> Result<Kvpair, String> {
+    let mut record: Json = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let key = match record["key"].take() {
+        Json::String(key) => key,
+        _ => return Err("expected a string field `key`".into()),
+    };
+    let value = record["value"].take();
+    let value = match record["type"].take() {
+        Json::String(kind) => decode_json(&kind, value)?,
+        Json::Null => Value::try_from(value).map_err(|e| e.to_string())?,
+        _ => return Err("expected a string field `type`".into()),
+    };
+    Ok(Kvpair::new(key, value))
+}
+
+/// Quote a field when it holds a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    match field.contains(&[',', '"', '\n', '\r'][..]) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+fn split_csv(record: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".into()),
+                }
+            }
+            if !matches!(chars.peek(), None | Some(',')) {
+                return Err(format!("unexpected text after quoted field `{}`", field));
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+fn trim_line_break(line: &mut String) {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemTable, ServiceInner};
+    use bytes::Bytes;
+    use std::io::Cursor;
+
+    fn pairs() -> Vec<Kvpair> {
+        vec![
+            Kvpair::new("s", "plain".into()),
+            Kvpair::new("quoted, \"with\"\nbreaks", "a,b\r\n\"c\"".into()),
+            Kvpair::new("bin", Bytes::from_static(b"\x00\xff\x10").into()),
+            Kvpair::new("int", (-42).into()),
+            Kvpair::new("float", 1.5.into()),
+            Kvpair::new("inf", f64::INFINITY.into()),
+            Kvpair::new("bool", true.into()),
+            Kvpair::new("empty", "".into()),
+        ]
+    }
+
+    fn round_trip(format: TableFormat) {
+        let mut out = Vec::new();
+        let mut writer = TableWriter::new(&mut out, format).unwrap();
+        for pair in pairs() {
+            writer.write(&pair).unwrap();
+        }
+        assert_eq!(writer.finish(), Ok(8));
+
+        let reader = TableReader::new(Cursor::new(out), format);
+        let read: Result<Vec<_>, _> = reader.collect();
+        assert_eq!(read.unwrap(), pairs());
+    }
+
+    #[test]
+    fn jsonl_should_round_trip_all_values() {
+        round_trip(TableFormat::Jsonl);
+    }
+
+    #[test]
+    fn csv_should_round_trip_all_values() {
+        round_trip(TableFormat::Csv);
+    }
+
+    #[test]
+    fn jsonl_should_read_gateway_pairs() {
+        let pair = Kvpair::new("bin", Bytes::from_static(b"\x00\xff").into());
+        let json = serde_json::json!({
+            "key": pair.key,
+            "value": pair.value.as_ref().map(Json::from),
+        });
+        let mut reader = TableReader::new(Cursor::new(json.to_string()), TableFormat::Jsonl);
+        assert_eq!(reader.read(), Ok(Some(pair)));
+    }
+
+    #[test]
+    fn invalid_records_should_report_their_line() {
+        let input = "{\"key\":\"k1\",\"type\":\"integer\",\"value\":1}\n\n{\"key\":\"k2\",\"type\":\"integer\",\"value\":\"x\"}\n";
+        let mut reader = TableReader::new(input.as_bytes(), TableFormat::Jsonl);
+        assert_eq!(reader.read(), Ok(Some(Kvpair::new("k1", 1.into()))));
+        assert!(matches!(reader.read(), Err(KvError::InvalidRecord(3, _))));
+
+        let input = "key,type,value\nk1,bool,yes\n";
+        let mut reader = TableReader::new(input.as_bytes(), TableFormat::Csv);
+        assert!(matches!(reader.read(), Err(KvError::InvalidRecord(2, _))));
+
+        let mut reader = TableReader::new("k1,string,v1\n".as_bytes(), TableFormat::Csv);
+        assert!(matches!(reader.read(), Err(KvError::InvalidRecord(1, _))));
+    }
+
+    #[test]
+    fn table_should_be_exported_and_imported() {
+        let store = MemTable::new();
+        for pair in pairs() {
+            store.set("t1", pair.key, pair.value.unwrap()).unwrap();
+        }
+        let mut out = Vec::new();
+        assert_eq!(
+            export_table(&store, "t1", TableFormat::Csv, &mut out),
+            Ok(8)
+        );
+
+        let service: Service = ServiceInner::new(MemTable::new()).into();
+        let res = import_table(&service, "t2", TableFormat::Csv, Cursor::new(out), 3);
+        assert_eq!(res, Ok(8));
+        let mut imported = service
+            .execute(CommandRequest::new_hgetall("t2"))
+            .into_result()
+            .unwrap()
+            .pairs;
+        imported.sort_by(|a, b| a.key.cmp(&b.key));
+        let mut expected = pairs();
+        expected.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(imported, expected);
+    }
+
+    #[test]
+    fn table_format_should_be_parsed() {
+        assert_eq!("csv".parse(), Ok(TableFormat::Csv));
+        assert!("xml".parse::<TableFormat>().is_err());
+        assert_eq!(
+            TableFormat::from_path("out/t1.jsonl"),
+            Some(TableFormat::Jsonl)
+        );
+        assert_eq!(TableFormat::from_path("t1.txt"), None);
+    }
+}